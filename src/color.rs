@@ -0,0 +1,82 @@
+use std::env;
+use std::io::{stderr, IsTerminal};
+use std::sync::OnceLock;
+
+use crossterm::style::{Color, Colored, Stylize};
+
+/// When the messages are colored
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ColorChoice {
+    /// Only if stderr is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// The messages are written to stderr, so the terminal is checked there
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && stderr().is_terminal()
+            }
+        }
+    }
+}
+
+static CHOICE: OnceLock<ColorChoice> = OnceLock::new();
+
+/// Set the choice for the whole run, the first call wins
+pub(crate) fn set_choice(choice: ColorChoice) {
+    // crossterm drops the colors itself if NO_COLOR is set, `always` overrides it
+    if choice == ColorChoice::Always {
+        Colored::set_ansi_color_disabled(false);
+    }
+    let _ = CHOICE.set(choice);
+}
+
+fn choice() -> ColorChoice {
+    CHOICE.get().copied().unwrap_or_default()
+}
+
+pub(crate) fn enabled() -> bool {
+    choice().enabled()
+}
+
+fn paint(choice: ColorChoice, text: &str, color: Color) -> String {
+    if choice.enabled() {
+        text.with(color).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+pub(crate) fn error(text: &str) -> String {
+    paint(choice(), text, Color::Red)
+}
+
+pub(crate) fn warning(text: &str) -> String {
+    paint(choice(), text, Color::Yellow)
+}
+
+pub(crate) fn success(text: &str) -> String {
+    paint(choice(), text, Color::Green)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_produces_no_escape_codes() {
+        for color in [Color::Red, Color::Yellow, Color::Green] {
+            assert_eq!(paint(ColorChoice::Never, "x", color), "x");
+        }
+        assert!(!ColorChoice::Never.enabled());
+        assert!(ColorChoice::Always.enabled());
+    }
+}
@@ -1,15 +1,48 @@
-use anyhow::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{ensure, Context};
 use clap::{Parser, Subcommand};
-use log::{info, trace};
+use log::{info, trace, warn, LevelFilter};
+use simple_logger::SimpleLogger;
 
-use crate::preparing::context::ProjectContext;
+use crate::color::ColorChoice;
+use crate::messages::{Locale, Message};
+use crate::preparing::context::{Author, ProjectContext, TaskOrder, STDIO_CONFIG};
+use crate::preparing::info::ProjectInfo;
+use crate::preparing::task::TaskInfo;
+use crate::reviewing::review::{
+    input_reader, GroupBy, InputReader, Review, ReviewOptions, TASK_SEPARATOR,
+};
 
+mod color;
+mod messages;
 mod preparing;
 mod reviewing;
+#[cfg(test)]
+mod testing;
+mod version;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about = "A helper tool for assisting C++ course")]
+#[command(author, version = version::VERSION, about = "A helper tool for assisting C++ course")]
 struct Args {
+    /// Check if a newer version of the tool is released (requires network)
+    #[arg(long, global = true)]
+    check_update: bool,
+
+    /// Use config.<PROFILE>.json instead of the config (for several courses or identities)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Fail instead of warning on suspicious input, e.g. wrong numbers of notes (for CI)
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Color the messages: only if stderr is a terminal and NO_COLOR is not set, always (for pagers) or never
+    /// (for CI logs). The messages are written to stderr, so `auto` checks stderr, not stdout.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    color: ColorChoice,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -26,14 +59,62 @@ enum Commands {
         #[arg(short, long, default_value = ".")]
         project_dir: String,
 
-        /// Task to perform(from task list)
+        /// Tasks to review one after another (from task list)
+        #[arg(short, long, required_unless_present = "task_all")]
+        task: Vec<String>,
+
+        /// Review all tasks of the project one after another
+        #[arg(long, conflicts_with = "task")]
+        task_all: bool,
+
+        /// Git revision (commit, branch, tag) to review the code at
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Link to the reviewed code to show in the review header
+        #[arg(long)]
+        source_url: Option<String>,
+
+        /// Group notes in the shown review
+        #[arg(long, value_enum, default_value_t)]
+        group_by: GroupBy,
+
+        /// Recreate missing task directory, code file and notes file
+        #[arg(long)]
+        fix: bool,
+
+        /// Zip archive of the submission to take the code file from
+        #[arg(long)]
+        archive: Option<PathBuf>,
+
+        /// Don't print Ok after successful actions
         #[arg(short, long)]
-        task: String,
+        quiet: bool,
+
+        /// Show the review to this file instead of the task's one (`-` for stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Run commands without arguments by a single key press (n, a, p, m start the command line)
+        #[arg(long)]
+        interactive_keys: bool,
+
+        /// Only view the existing review (show, template, open, peek), without changing it
+        #[arg(long, conflicts_with_all = ["fix", "archive"])]
+        read_only: bool,
+
+        /// Review under this name instead of the author from the config
+        #[arg(long, requires = "contacts")]
+        author: Option<String>,
+
+        /// Contacts of the author given by --author
+        #[arg(long, requires = "author")]
+        contacts: Option<String>,
     },
 
     /// Add a new task to the project
     Add {
-        /// Path to config of the author and the settings
+        /// Path to config of the author and the settings (`-` to read it from stdin)
         #[arg(long, default_value = "config.json")]
         config_path: String,
 
@@ -42,16 +123,115 @@ enum Commands {
         project_dir: String,
 
         /// Task to perform(from task list)
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = parse_file_name)]
         task: String,
 
         /// Name of file with code to reviewing
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = parse_file_name)]
         code_file_name: String,
 
+        /// Path of the code file inside the task directory if it is nested (e.g. src/task1/main.cpp)
+        #[arg(long)]
+        code_file_path: Option<PathBuf>,
+
+        /// Other file of the task to reference, relative to the task directory (e.g. main.h), can be repeated
+        #[arg(long = "extra-code-file")]
+        extra_code_files: Vec<PathBuf>,
+
         /// File name to show if you want to use file show method
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = parse_file_name)]
         show_file_name: Option<String>,
+
+        /// Write the review to the show file as LaTeX
+        #[arg(long, value_enum, requires = "show_file_name")]
+        latex: Option<LatexOutput>,
+
+        /// Human-readable title of the task
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Deadline of the task in the YYYY-MM-DD format
+        #[arg(long)]
+        deadline: Option<String>,
+
+        /// Maximum points for the task
+        #[arg(long)]
+        max_points: Option<u32>,
+
+        /// Name or initials of the reviewer the task is assigned to
+        #[arg(long)]
+        assigned_to: Option<String>,
+
+        /// Zip archive of the submission to take the code file from
+        #[arg(long)]
+        archive: Option<PathBuf>,
+    },
+
+    /// List tasks of the project
+    List {
+        /// Path to config of the author and the settings (`-` to read it from stdin)
+        #[arg(long, default_value = "config.json")]
+        config_path: String,
+
+        /// Project directory path with structure from README.md
+        #[arg(short, long, default_value = ".")]
+        project_dir: String,
+
+        /// Order of the tasks
+        #[arg(long, value_enum, default_value_t)]
+        sort_by: TaskOrder,
+
+        /// Show only the tasks assigned to the author of the config
+        #[arg(long)]
+        mine: bool,
+    },
+
+    /// Show statistics over all reviews of the project
+    Stats {
+        /// Path to config of the author and the settings (`-` to read it from stdin)
+        #[arg(long, default_value = "config.json")]
+        config_path: String,
+
+        /// Project directory path with structure from README.md
+        #[arg(short, long, default_value = ".")]
+        project_dir: String,
+
+        /// Print statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the project structure and tasks as JSON for editor integrations
+    Info {
+        /// Path to config of the author and the settings (`-` to read it from stdin)
+        #[arg(long, default_value = "config.json")]
+        config_path: String,
+
+        /// Project directory path with structure from README.md
+        #[arg(short, long, default_value = ".")]
+        project_dir: String,
+    },
+
+    /// Check that files of all tasks exist
+    Validate {
+        /// Path to config of the author and the settings (`-` to read it from stdin)
+        #[arg(long, default_value = "config.json")]
+        config_path: String,
+
+        /// Project directory path with structure from README.md
+        #[arg(short, long, default_value = ".")]
+        project_dir: String,
+    },
+
+    /// Check that all notes and reviews files can be parsed
+    Lint {
+        /// Path to config of the author and the settings (`-` to read it from stdin)
+        #[arg(long, default_value = "config.json")]
+        config_path: String,
+
+        /// Project directory path with structure from README.md
+        #[arg(short, long, default_value = ".")]
+        project_dir: String,
     },
 
     /// Init project directory at current directory with config file at config.json
@@ -63,44 +243,202 @@ enum Commands {
         /// Contacts of the author (Telegram for example)
         #[arg(short, long)]
         contacts: String,
+
+        /// Print the config and the directories which would be created without creating them
+        #[arg(long)]
+        dry_run: bool,
     },
+
+    /// Print the JSON Schema of the config file for validation and completion in editors
+    Schema,
+}
+
+/// Name of the file or directory without the whitespace around it, which is easy to paste by mistake
+fn parse_file_name(name: &str) -> anyhow::Result<String> {
+    let name = name.trim();
+    ensure!(!name.is_empty(), Message::NameIsEmpty.text());
+    ensure!(
+        !name.contains(['/', '\\']) && name != "." && name != "..",
+        "{}",
+        Message::NameIsPath.format(&[&name])
+    );
+    Ok(name.to_string())
+}
+
+/// Kind of the LaTeX review file
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum LatexOutput {
+    /// Compilable document
+    Standalone,
+    /// Fragment to include into another document
+    Fragment,
 }
 
-fn main() -> anyhow::Result<()> {
-    simple_logger::init_with_level(log::Level::Warn).unwrap();
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{} {:#}", color::error(Message::Error.text()), err);
+            match std::env::var("RUST_BACKTRACE") {
+                Err(_) => eprintln!("{}", Message::BacktraceHint.text()),
+                Ok(value) if value != "0" => eprintln!("{}", err.backtrace()),
+                Ok(_) => {}
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
 
+fn run() -> anyhow::Result<()> {
     let args = Args::parse();
+    color::set_choice(args.color);
+    SimpleLogger::new()
+        .with_level(LevelFilter::Warn)
+        .with_colors(color::enabled())
+        .init()
+        .context(Message::CantSetUpLogger.text())?;
     trace!("Args: {:?}", args);
 
+    if args.check_update {
+        if let Err(err) = version::check_for_update() {
+            warn!(
+                "{}",
+                Message::CantCheckForUpdates.format(&[&format!("{:#}", err)])
+            );
+        }
+    }
+
+    let profile = args.profile.as_deref();
+    let load_state = |config_path: String, project_dir: String| {
+        let mut context = ProjectContext::load_state(config_path, project_dir, profile)?;
+        context.settings.cli_strict = args.strict;
+        messages::set_locale(context.settings.locale.unwrap_or_else(Locale::from_env));
+        context.check_unknown_fields()?;
+        anyhow::Ok(context)
+    };
     match args.command {
-        Commands::Init { author, contacts } => return ProjectContext::init_state(author, contacts),
+        Commands::Init {
+            author,
+            contacts,
+            dry_run,
+        } => return ProjectContext::init_state(author, contacts, profile, dry_run),
         Commands::Review {
             task,
+            task_all,
             config_path,
             project_dir,
+            rev,
+            source_url,
+            group_by,
+            fix,
+            archive,
+            quiet,
+            output,
+            author,
+            contacts,
+            interactive_keys,
+            read_only,
         } => {
-            let mut context = ProjectContext::load_state(config_path, project_dir)
-                .context("Can't load context")?;
-            info!("Context load: {:?}", context);
+            // The commands of the review are read from stdin too, and the config is loaded for each task
+            ensure!(
+                config_path != STDIO_CONFIG,
+                Message::StdinConfigInReview.text()
+            );
+            let tasks = if task_all {
+                load_state(config_path.clone(), project_dir.clone())
+                    .context(Message::CantLoadContext.text())?
+                    .tasks
+                    .into_iter()
+                    .map(|task| task.name)
+                    .collect()
+            } else {
+                task
+            };
             info!("Review command",);
-            context
-                .switch_to_task(&task)
-                .context("Can't switch to task")?;
-            trace!("State switched to the task {}", task);
-            context.check_task(&task).context("Check task fail")?;
-            trace!("Task checked");
-            println!("Start review with task: {}", task);
-            start_review(context)?
+            let options = ReviewOptions {
+                revision: rev,
+                source_url,
+                group_by,
+                quiet,
+                output,
+                interactive_keys,
+                read_only,
+            };
+            let mut reader = input_reader();
+            for (index, task) in tasks.iter().enumerate() {
+                if index > 0 {
+                    eprint!("{}", TASK_SEPARATOR.trim_start());
+                }
+                // Each task gets the fresh context, the review takes its task out of it
+                let mut context = load_state(config_path.clone(), project_dir.clone())
+                    .context(Message::CantLoadContext.text())?;
+                info!("Context load: {:?}", context);
+                // The config is not saved by the review, so the override lasts only for this run
+                if let (Some(author), Some(contacts)) = (&author, &contacts) {
+                    context.author = Author::new(author.clone(), contacts.clone());
+                }
+                if fix {
+                    for path in context
+                        .repair_task(task)
+                        .context(Message::CantRepairTask.text())?
+                    {
+                        eprintln!("{}", Message::Created.format(&[&path.display()]));
+                    }
+                }
+                if let Some(archive) = &archive {
+                    let code_file = context
+                        .extract_code(task, archive)
+                        .context(Message::CantExtractCodeFromArchive.text())?;
+                    eprintln!("{}", Message::Extracted.format(&[&code_file.display()]));
+                }
+                context
+                    .switch_to_task(task)
+                    .context(Message::CantSwitchToTask.text())?;
+                trace!("State switched to the task {}", task);
+                context
+                    .check_task(task)
+                    .context(Message::CheckTaskFail.text())?;
+                context.check_assignment(task)?;
+                trace!("Task checked");
+                let current_task = context.current_task.context(Message::TaskIsNotSet.text())?;
+                eprintln!(
+                    "{}",
+                    Message::StartReview.format(&[&context.tasks[current_task]])
+                );
+                let review = start_review(context, options.clone(), reader)?;
+                if review.is_completed() {
+                    // The context was given to the review, the config is read again to keep the other changes
+                    let mut context = load_state(config_path.clone(), project_dir.clone())
+                        .context(Message::CantLoadContext.text())?;
+                    context.mark_reviewed(task)?;
+                    context
+                        .dump_state()
+                        .context(Message::CantSaveReviewTime.text())?;
+                }
+                if review.is_aborted() {
+                    eprintln!("{}", Message::RestSkipped.text());
+                    break;
+                }
+                reader = review.into_reader();
+            }
         }
         Commands::Add {
             config_path,
             project_dir,
             task,
             code_file_name,
+            code_file_path,
+            extra_code_files,
             show_file_name,
+            latex,
+            title,
+            deadline,
+            max_points,
+            assigned_to,
+            archive,
         } => {
-            let mut context = ProjectContext::load_state(config_path, project_dir)
-                .context("Can't load context")?;
+            let mut context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
             info!("Context load: {:?}", context);
             info!("Add command");
             let show_method = match show_file_name {
@@ -110,25 +448,264 @@ fn main() -> anyhow::Result<()> {
                         .join("tasks")
                         .join(task.as_str())
                         .join(file_name);
-                    preparing::task::ShowMethod::File { file_name }
+                    match latex {
+                        Some(output) => preparing::task::ShowMethod::Latex {
+                            file_name,
+                            standalone: matches!(output, LatexOutput::Standalone),
+                        },
+                        None => preparing::task::ShowMethod::File { file_name },
+                    }
                 }
                 None => preparing::task::ShowMethod::Console,
             };
             context
-                .add_task(task, code_file_name, show_method)
-                .context("Can't add task")?;
+                .add_task(
+                    task.clone(),
+                    code_file_name,
+                    show_method,
+                    TaskInfo {
+                        title,
+                        deadline,
+                        max_points,
+                        code_file_path,
+                        assigned_to,
+                        extra_code_files,
+                    },
+                )
+                .context(Message::CantAddTask.text())?;
+            if let Some(archive) = &archive {
+                context
+                    .extract_code(&task, archive)
+                    .context(Message::CantExtractCodeFromArchive.text())?;
+            }
+            // The config written to stdout must not be mixed with messages
+            let stdio_config = context.is_stdio_config();
             context.dump_state()?;
-            println!("Successfully add");
+            if stdio_config {
+                eprintln!("{}", Message::TaskAdded.text());
+            } else {
+                println!("{}", Message::TaskAdded.text());
+            }
+        }
+        Commands::List {
+            config_path,
+            project_dir,
+            sort_by,
+            mine,
+        } => {
+            let context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
+            info!("List command");
+            for task in context
+                .sorted_tasks(sort_by)
+                .into_iter()
+                .filter(|task| !mine || context.is_mine(task))
+            {
+                println!("{}", task);
+            }
+        }
+        Commands::Stats {
+            config_path,
+            project_dir,
+            json,
+        } => {
+            let context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
+            info!("Stats command");
+            let stats = reviewing::stats::collect_stats(&context)?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&stats)
+                        .context(Message::CantSerializeStats.text())?
+                );
+            } else {
+                println!("{}", stats);
+            }
+        }
+        Commands::Info {
+            config_path,
+            project_dir,
+        } => {
+            let mut context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
+            info!("Info command");
+            let info = ProjectInfo::collect(&mut context)?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&info)
+                    .context(Message::CantSerializeProjectInfo.text())?
+            );
+        }
+        Commands::Validate {
+            config_path,
+            project_dir,
+        } => {
+            let context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
+            info!("Validate command");
+            let problems = context.validate_tasks();
+            for (task, err) in &problems {
+                println!("{}: {:#}", task, err);
+            }
+            ensure!(
+                problems.is_empty(),
+                "{}",
+                Message::BrokenTasks.format(&[&problems.len(), &context.tasks.len()])
+            );
+            println!("{}", Message::TasksFine.format(&[&context.tasks.len()]));
+        }
+        Commands::Lint {
+            config_path,
+            project_dir,
+        } => {
+            let context =
+                load_state(config_path, project_dir).context(Message::CantLoadContext.text())?;
+            info!("Lint command");
+            let report = reviewing::lint::lint_project(&context)?;
+            for (path, err) in &report.failures {
+                println!("{}: {:#}", path.display(), err);
+            }
+            ensure!(
+                report.failures.is_empty(),
+                "{}",
+                Message::InvalidFiles.format(&[&report.failures.len(), &report.checked])
+            );
+            println!("{}", Message::FilesFine.format(&[&report.checked]));
+        }
+        Commands::Schema => {
+            info!("Schema command");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&ProjectContext::config_schema())
+                    .context(Message::CantSerializeSchema.text())?
+            );
         }
     }
     Ok(())
 }
 
-pub(crate) fn start_review(context: ProjectContext) -> anyhow::Result<()> {
-    let mut review = reviewing::review::Review::new(context)?;
+pub(crate) fn start_review(
+    context: ProjectContext,
+    options: ReviewOptions,
+    reader: InputReader,
+) -> anyhow::Result<Review> {
+    let mut review = Review::new(context, options, reader)?;
     while !review.is_finished() {
         review.step()?;
     }
 
-    Ok(())
+    Ok(review)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(["assist_tool"].iter().chain(args))
+    }
+
+    fn parse(args: &[&str]) -> Args {
+        try_parse(args).unwrap()
+    }
+
+    #[test]
+    fn review_is_quiet_with_the_flag() {
+        for (args, expected) in [
+            (&["review", "-t", "t"][..], false),
+            (&["review", "-t", "t", "--quiet"], true),
+            (&["review", "-q", "-t", "t"], true),
+        ] {
+            let Commands::Review { quiet, .. } = parse(args).command else {
+                panic!("Not a review command");
+            };
+            assert_eq!(quiet, expected, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn several_tasks_are_reviewed() {
+        let Commands::Review { task, task_all, .. } =
+            parse(&["review", "-t", "a", "--task", "b"]).command
+        else {
+            panic!("Not a review command");
+        };
+        assert_eq!(task, ["a", "b"]);
+        assert!(!task_all);
+        let Commands::Review { task, task_all, .. } = parse(&["review", "--task-all"]).command
+        else {
+            panic!("Not a review command");
+        };
+        assert!(task.is_empty());
+        assert!(task_all);
+        for args in [&["review"][..], &["review", "--task-all", "-t", "a"]] {
+            assert!(try_parse(args).is_err());
+        }
+    }
+
+    #[test]
+    fn author_is_given_with_contacts() {
+        let args = [
+            "review",
+            "-t",
+            "t",
+            "--author",
+            "Ivan Ivanov",
+            "--contacts",
+            "@ivanov",
+        ];
+        let Commands::Review {
+            author, contacts, ..
+        } = parse(&args).command
+        else {
+            panic!("Not a review command");
+        };
+        assert_eq!(author.as_deref(), Some("Ivan Ivanov"));
+        assert_eq!(contacts.as_deref(), Some("@ivanov"));
+        for args in [
+            &["review", "-t", "t", "--author", "Ivan Ivanov"][..],
+            &["review", "-t", "t", "--contacts", "@ivanov"],
+        ] {
+            assert!(try_parse(args).is_err());
+        }
+    }
+
+    #[test]
+    fn file_names_are_trimmed_and_checked() {
+        assert_eq!(parse_file_name("  main.cpp\t").unwrap(), "main.cpp");
+        for name in ["", "  ", "src/main.cpp", "src\\main.cpp", ".", ".."] {
+            assert!(parse_file_name(name).is_err(), "{:?}", name);
+        }
+        let Commands::Add { task, .. } = parse(&["add", "-t", " t1 ", "-c", "main.cpp"]).command
+        else {
+            panic!("Not an add command");
+        };
+        assert_eq!(task, "t1");
+    }
+
+    #[test]
+    fn color_is_chosen_by_the_flag() {
+        assert_eq!(parse(&["list"]).color, ColorChoice::Auto);
+        assert_eq!(
+            parse(&["--color", "never", "list"]).color,
+            ColorChoice::Never
+        );
+        assert_eq!(
+            parse(&["list", "--color", "always"]).color,
+            ColorChoice::Always
+        );
+        assert!(try_parse(&["--color", "sometimes", "list"]).is_err());
+    }
+
+    #[test]
+    fn read_only_review_conflicts_with_the_changes() {
+        let Commands::Review { read_only, .. } =
+            parse(&["review", "-t", "t", "--read-only"]).command
+        else {
+            panic!("Not a review command");
+        };
+        assert!(read_only);
+        assert!(try_parse(&["review", "-t", "t", "--read-only", "--fix"]).is_err());
+    }
 }
@@ -0,0 +1,959 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Language of the messages of the tool, the reviews are written as the notes are
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Locale {
+    /// Locale of the environment variables as in the C library, English if it's not translated
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or_else(Self::default, |value| {
+                if value.starts_with("ru") {
+                    Self::Ru
+                } else {
+                    Self::En
+                }
+            })
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Set the locale for the whole run, the first call wins
+pub(crate) fn set_locale(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+fn locale() -> Locale {
+    *LOCALE.get_or_init(Locale::from_env)
+}
+
+/// Messages shown to the reviewer, `{}` are replaced by the arguments in order
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Message {
+    Ok,
+    Error,
+    Warning,
+    UnknownAction,
+    StartReview,
+    NewReview,
+    DuplicateSkipped,
+    CantAddNote,
+    LeftUnfinished,
+    RestSkipped,
+    ReviewFinished,
+    OpenedReadOnly,
+    ReviewResumed,
+    ReadOnlyMode,
+    CantMergeNotes,
+    CantPromoteNote,
+    CantDemoteNote,
+    CantSuggestFix,
+    CantResolveNote,
+    CantShowCode,
+    CantBuildNote,
+    CantImportDiagnostics,
+    CantUseFile,
+    CantShowReview,
+    CantWaiveNote,
+    NoteResolved,
+    NoteReopened,
+    NoTaskNotes,
+    NoBookmarks,
+    ActiveAuthor,
+    UnknownAuthor,
+    CategorySet,
+    CategoryCleared,
+    ActiveFile,
+    NotAddressed,
+    AddOrWaive,
+    FinalizeReview,
+    ResumeSession,
+    ResumeReviews,
+    ChecklistIntro,
+    ChecklistSkipped,
+    LongReference,
+    BlankReference,
+    KeepReference,
+    NoCheckCommands,
+    RunCheck,
+    CheckFinished,
+    AddDiagnostic,
+    ImportedDiagnostics,
+    StaleReferences,
+    MissingReferenceFile,
+    NotCodeFile,
+    OutOfFile,
+    UnreadableReference,
+    TemplatesNecessary,
+    TemplatesOptional,
+    TaskNotesUsed,
+    BacktraceHint,
+    Created,
+    Extracted,
+    TaskAdded,
+    BrokenTasks,
+    TasksFine,
+    InvalidFiles,
+    FilesFine,
+    NewVersion,
+    LatestVersion,
+    NoReleases,
+    WouldCreateDirectory,
+    WouldOverwrite,
+    WouldWrite,
+    NameIsEmpty,
+    NameIsPath,
+    CantSetUpLogger,
+    CantCheckForUpdates,
+    StdinConfigInReview,
+    CantLoadContext,
+    CantRepairTask,
+    CantExtractCodeFromArchive,
+    CantSwitchToTask,
+    CheckTaskFail,
+    TaskIsNotSet,
+    CantSaveReviewTime,
+    CantAddTask,
+    CantSerializeStats,
+    CantSerializeProjectInfo,
+    CantSerializeSchema,
+    CantOpenArchive,
+    CantReadZipArchive,
+    CantReadZipArchiveEntry,
+    ArchiveEntryOutside,
+    ArchiveHasNoFile,
+    CodeFileIsADirectory,
+    CantCreateDirectoryForCode,
+    CantCreateCodeFile,
+    CantExtractCodeFileFromArchive,
+    ProfileWithStdinConfig,
+    FailedToReadConfigFromStdin,
+    FailedToReadConfigFile,
+    UnsupportedTaskSchema,
+    IncorrectFirstNoteNumberSetting,
+    CantGetCurrentDirectory,
+    TaskNotFound,
+    CantLoadTaskNotes,
+    ProjectDirectoryDoesntExist,
+    ProjectDirectoryIsNotADirectory,
+    CantCreateReviewDirectory,
+    CantCreateTaskDirectory,
+    CantCreateNotesDirectory,
+    CantWriteConfig,
+    CantSerializeState,
+    NoHomeDirectory,
+    CharactersReplaced,
+    TextNotInEncoding,
+    UnknownOutputEncoding,
+    UnsupportedEncoding,
+    BomOnlyForUtf8,
+    CantLoadNotesOfTask,
+    CantResolveConfigPath,
+    CantOpenFileForNotes,
+    CantReadFileForNotes,
+    CantReadNote,
+    NoteNotFound,
+    CantMergeNoteWithItself,
+    NumberedNoteNotFound,
+    NotesNotUtf8,
+    CantWriteNotesFile,
+    IncorrectNoteNumberAtLine,
+    NoteRenumbered,
+    FirstLineReadProblem,
+    IncorrectSectionHeader,
+    IncorrectLineStart,
+    NoNoteNumber,
+    IncorrectLine,
+    NoteFormatWithoutNumber,
+    NoteFormatWithoutText,
+    NoteFormatWithoutSeparator,
+    IncorrectNoteNumber,
+    NotesAreNumberedFrom,
+    IncorrectDeadline,
+    CodeFilePathOutsideTask,
+    CodeFilePathWithoutName,
+    CantCreateTaskCodeFile,
+    CantCreateFileForCode,
+    CantCreateNotesFile,
+    TaskDirectoryDoesntExist,
+    TaskDirectoryIsNotADirectory,
+    NotesDirectoryDoesntExist,
+    NotesDirectoryIsNotADirectory,
+    TaskCodeFileDoesntExist,
+    TaskCodeFileIsNotAFile,
+    NotesFileDoesntExist,
+    NotesFileIsNotAFile,
+    CantParseClangTidyDiagnostics,
+    CantReadNamedFile,
+    IncorrectHunkHeader,
+    HunkHeaderHasNoNewRange,
+    RangeDoesntStartWith,
+    IncorrectHunkStart,
+    IncorrectHunkLength,
+    HunkLongerThanHeader,
+    MalformedHunkLine,
+    MalformedHunkHeaderAtLine,
+    HunkWithoutFileHeader,
+    DiffEndsInHunk,
+    DiffHasNoHunks,
+    CantReadReview,
+    ReviewHasNoHeader,
+    ReviewHasNoNotes,
+    ReviewHasNoNotesAfterContents,
+    ReviewHasTooManySeparators,
+    CantReadResolvedNotes,
+    CantReadNotesOf,
+    CantRunExternalCommand,
+    CantWaitExternalCommand,
+    ExternalCommandTimedOut,
+    CantSwitchTerminalToRawMode,
+    CantRestoreTerminal,
+    CantReadKey,
+    CantReadFile,
+    CantReadPath,
+    EmptyReference,
+    ReferenceLineWithoutNumber,
+    ReferenceHasNoQuotedLines,
+    ReferenceRowsNotInOrder,
+    LineFromEndOutOfFile,
+    LinesNumberedFromOne,
+    NoTextInNote,
+    NoDiffFileInReference,
+    NoHunkNumberInReference,
+    IncorrectHunkNumberInReference,
+    NoLineNumberInReference,
+    IncorrectLineNumberInReference,
+    NoBookmarkNameInReference,
+    NoFirstNumberInReference,
+    IncorrectFirstNumberInReference,
+    NoSecondNumberInReference,
+    IncorrectSecondNumberInReference,
+    NoLineNumberInMark,
+    IncorrectLineNumberInMark,
+    NoCommentInMark,
+    EmptyCodeFile,
+    ReferenceStartAfterEnd,
+    ReferenceOutOfFile,
+    MarkOutOfReference,
+    IncorrectInlineReference,
+    CantReadReference,
+    NoReviewToView,
+    InputIsNotTerminal,
+    InvalidReferenceIgnorePattern,
+    CantSaveNotes,
+    CantPrintTaskNotes,
+    CantSaveSession,
+    CantRemoveSessionFile,
+    CantRestoreSession,
+    UnknownSessionAuthor,
+    CantFlushPrompt,
+    ReadingLineFail,
+    NoCommandToRepeat,
+    IncorrectNoteType,
+    IncorrectFirstNoteNumber,
+    IncorrectSecondNoteNumber,
+    NoSuggestedCode,
+    NoBookmarkName,
+    NoFirstNumberInBookmark,
+    IncorrectFirstNumberInBookmark,
+    NoSecondNumberInBookmark,
+    IncorrectSecondNumberInBookmark,
+    NoDiagnosticsFile,
+    NoAuthorName,
+    NoPreviousReference,
+    AmbiguousCodeFile,
+    NoNumberOfNote,
+    CantReadDiffFile,
+    IncorrectDiffFile,
+    NoHunk,
+    NoNumberInNote,
+    CantReadRevision,
+    CantReadFileWithCode,
+    BinaryCodeFile,
+    CodeFileNotUtf8,
+    CodeFileHasNoDirectory,
+    CodeFileHasNoName,
+    GitShowFailed,
+    EmptyPagerCommand,
+    CantStartPager,
+    PagerFailed,
+    CantReadDiagnosticsFile,
+    ReviewNotUtf8,
+    CantPrintReview,
+    CantWriteReviewTo,
+    CantReadSessionFile,
+    CantParseSessionFile,
+    CantSerializeSession,
+    CantWriteSessionFile,
+    CantReadReviewsDirectory,
+    CantReadNamedReview,
+    ReviewSkipped,
+    CantGetTags,
+    IncorrectCrateVersion,
+    CantReadPreviousReview,
+    NotTaskCodeFile,
+    NoBookmark,
+    NoOptionalTaskNotes,
+    NoNecessaryTaskNotes,
+    NoteIsOptional,
+    NoteIsNecessary,
+    CantParseLine,
+    CantResolveProjectDirectory,
+    CantParseConfig,
+    TaskAssigned,
+    UnknownTaskFields,
+    ExtensionNotAllowed,
+    SeveralCodeFilesInArchive,
+    StatsTasks,
+    StatsReviews,
+    StatsNecessaryNotes,
+    StatsOptionalNotes,
+    StatsNotesPerReview,
+    StatsReferences,
+    StatsReferencedLines,
+    StatsCategories,
+    StatsCategory,
+    ComparedToPrevious,
+}
+
+impl Message {
+    fn template(self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Message::Ok, Locale::En) => "Ok",
+            (Message::Ok, Locale::Ru) => "Ок",
+            (Message::Error, Locale::En) => "Error:",
+            (Message::Error, Locale::Ru) => "Ошибка:",
+            (Message::Warning, Locale::En) => "Warning:",
+            (Message::Warning, Locale::Ru) => "Предупреждение:",
+            (Message::UnknownAction, Locale::En) => "Unknown action",
+            (Message::UnknownAction, Locale::Ru) => "Неизвестная команда",
+            (Message::StartReview, Locale::En) => "Start review with task: {}",
+            (Message::StartReview, Locale::Ru) => "Начинаем ревью задачи: {}",
+            (Message::NewReview, Locale::En) => "Let's start new review:",
+            (Message::NewReview, Locale::Ru) => "Начинаем новое ревью:",
+            (Message::DuplicateSkipped, Locale::En) => "Duplicate skipped",
+            (Message::DuplicateSkipped, Locale::Ru) => "Повторное замечание пропущено",
+            (Message::CantAddNote, Locale::En) => "Can't add note: {}",
+            (Message::CantAddNote, Locale::Ru) => "Не удалось добавить замечание: {}",
+            (Message::LeftUnfinished, Locale::En) => "Review of {} is left unfinished",
+            (Message::LeftUnfinished, Locale::Ru) => "Ревью задачи {} не завершено",
+            (Message::RestSkipped, Locale::En) => "The rest of the tasks are skipped",
+            (Message::RestSkipped, Locale::Ru) => "Остальные задачи пропущены",
+            (Message::ReviewFinished, Locale::En) => "Review finished in {}",
+            (Message::ReviewFinished, Locale::Ru) => "Ревью завершено за {}",
+            (Message::OpenedReadOnly, Locale::En) => "Review of {} is opened read-only with {} notes",
+            (Message::OpenedReadOnly, Locale::Ru) => "Ревью задачи {} открыто только для чтения, замечаний: {}",
+            (Message::ReviewResumed, Locale::En) => "Review resumed with {} notes",
+            (Message::ReviewResumed, Locale::Ru) => "Ревью продолжено, замечаний: {}",
+            (Message::ReadOnlyMode, Locale::En) => "Read-only mode, the review can't be changed",
+            (Message::ReadOnlyMode, Locale::Ru) => "Режим только для чтения, ревью нельзя изменить",
+            (Message::CantMergeNotes, Locale::En) => "Can't merge notes: {}",
+            (Message::CantMergeNotes, Locale::Ru) => "Не удалось объединить замечания: {}",
+            (Message::CantPromoteNote, Locale::En) => "Can't promote note: {}",
+            (Message::CantPromoteNote, Locale::Ru) => "Не удалось сделать замечание необязательным: {}",
+            (Message::CantDemoteNote, Locale::En) => "Can't demote note: {}",
+            (Message::CantDemoteNote, Locale::Ru) => "Не удалось сделать замечание обязательным: {}",
+            (Message::CantSuggestFix, Locale::En) => "Can't suggest the fix: {}",
+            (Message::CantSuggestFix, Locale::Ru) => "Не удалось предложить исправление: {}",
+            (Message::CantResolveNote, Locale::En) => "Can't resolve note: {}",
+            (Message::CantResolveNote, Locale::Ru) => "Не удалось отметить замечание исправленным: {}",
+            (Message::CantShowCode, Locale::En) => "Can't show the code: {}",
+            (Message::CantShowCode, Locale::Ru) => "Не удалось показать код: {}",
+            (Message::CantBuildNote, Locale::En) => "Can't build note: {}",
+            (Message::CantBuildNote, Locale::Ru) => "Не удалось составить замечание: {}",
+            (Message::CantImportDiagnostics, Locale::En) => "Can't import diagnostics: {}",
+            (Message::CantImportDiagnostics, Locale::Ru) => "Не удалось импортировать диагностики: {}",
+            (Message::CantUseFile, Locale::En) => "Can't use file: {}",
+            (Message::CantUseFile, Locale::Ru) => "Не удалось выбрать файл: {}",
+            (Message::CantShowReview, Locale::En) => "Can't show review: {}",
+            (Message::CantShowReview, Locale::Ru) => "Не удалось показать ревью: {}",
+            (Message::CantWaiveNote, Locale::En) => "Can't waive note: Note {} not found",
+            (Message::CantWaiveNote, Locale::Ru) => "Не удалось пропустить замечание: замечание {} не найдено",
+            (Message::NoteResolved, Locale::En) => "Resolved",
+            (Message::NoteResolved, Locale::Ru) => "Исправлено",
+            (Message::NoteReopened, Locale::En) => "Reopened",
+            (Message::NoteReopened, Locale::Ru) => "Снова открыто",
+            (Message::NoTaskNotes, Locale::En) => "Task has no notes, add them with `new`",
+            (Message::NoTaskNotes, Locale::Ru) => "У задачи нет заметок, добавьте их командой `new`",
+            (Message::NoBookmarks, Locale::En) => "No bookmarks",
+            (Message::NoBookmarks, Locale::Ru) => "Закладок нет",
+            (Message::ActiveAuthor, Locale::En) => "Active author: {}",
+            (Message::ActiveAuthor, Locale::Ru) => "Текущий автор: {}",
+            (Message::UnknownAuthor, Locale::En) => "Unknown author: {}",
+            (Message::UnknownAuthor, Locale::Ru) => "Неизвестный автор: {}",
+            (Message::CategorySet, Locale::En) => "Category: {}",
+            (Message::CategorySet, Locale::Ru) => "Категория: {}",
+            (Message::CategoryCleared, Locale::En) => "Category cleared",
+            (Message::CategoryCleared, Locale::Ru) => "Категория сброшена",
+            (Message::ActiveFile, Locale::En) => "Active file: {}",
+            (Message::ActiveFile, Locale::Ru) => "Текущий файл: {}",
+            (Message::NotAddressed, Locale::En) => "Necessary notes are not addressed:",
+            (Message::NotAddressed, Locale::Ru) => "Не учтены обязательные заметки:",
+            (Message::AddOrWaive, Locale::En) => "Add them or leave out with `waive <num>`",
+            (Message::AddOrWaive, Locale::Ru) => "Добавьте их или пропустите командой `waive <num>`",
+            (Message::FinalizeReview, Locale::En) => "Finalize review with {} notes?",
+            (Message::FinalizeReview, Locale::Ru) => "Завершить ревью с замечаниями: {}?",
+            (Message::ResumeSession, Locale::En) => "Found unfinished review of {} with {} notes, resume it? Otherwise it is overwritten",
+            (Message::ResumeSession, Locale::Ru) => "Найдено незавершенное ревью задачи {}, замечаний: {}. Продолжить его? Иначе оно будет перезаписано",
+            (Message::ResumeReviews, Locale::En) => "Reviews file of {} already has {} notes, resume it? Otherwise it is overwritten",
+            (Message::ResumeReviews, Locale::Ru) => "В файле ревью задачи {} уже есть замечания: {}. Продолжить его? Иначе он будет перезаписан",
+            (Message::ChecklistIntro, Locale::En) => "Before the review check (press Enter for each item or type skip):",
+            (Message::ChecklistIntro, Locale::Ru) => "Перед ревью проверьте (нажмите Enter после каждого пункта или введите skip):",
+            (Message::ChecklistSkipped, Locale::En) => "Checklist skipped",
+            (Message::ChecklistSkipped, Locale::Ru) => "Проверка пропущена",
+            (Message::LongReference, Locale::En) => "Reference {}-{} has {} lines, more than {}, split it into focused references",
+            (Message::LongReference, Locale::Ru) => "Ссылка {}-{} занимает строк: {}, больше {}, разделите ее на более точные ссылки",
+            (Message::BlankReference, Locale::En) => "lines {}-{} contain only blank lines or comments",
+            (Message::BlankReference, Locale::Ru) => "в строках {}-{} только пустые строки или комментарии",
+            (Message::KeepReference, Locale::En) => "Keep the reference?",
+            (Message::KeepReference, Locale::Ru) => "Оставить ссылку?",
+            (Message::NoCheckCommands, Locale::En) => "No check commands configured",
+            (Message::NoCheckCommands, Locale::Ru) => "Команды проверки не настроены",
+            (Message::RunCheck, Locale::En) => "Run: {}",
+            (Message::RunCheck, Locale::Ru) => "Запуск: {}",
+            (Message::CheckFinished, Locale::En) => "Finished with {}",
+            (Message::CheckFinished, Locale::Ru) => "Завершено: {}",
+            (Message::AddDiagnostic, Locale::En) => "Add?",
+            (Message::AddDiagnostic, Locale::Ru) => "Добавить?",
+            (Message::ImportedDiagnostics, Locale::En) => "Imported {} of {} diagnostics",
+            (Message::ImportedDiagnostics, Locale::Ru) => "Импортировано диагностик: {} из {}",
+            (Message::StaleReferences, Locale::En) => "{} stale references:",
+            (Message::StaleReferences, Locale::Ru) => "устаревших ссылок: {}",
+            (Message::MissingReferenceFile, Locale::En) => "{} doesn't exist",
+            (Message::MissingReferenceFile, Locale::Ru) => "{} не существует",
+            (Message::NotCodeFile, Locale::En) => "{} is not a code file of the task",
+            (Message::NotCodeFile, Locale::Ru) => "{} не файл с кодом задачи",
+            (Message::OutOfFile, Locale::En) => "{} is out of the file with {} lines",
+            (Message::OutOfFile, Locale::Ru) => "{} за пределами файла, в нем строк: {}",
+            (Message::UnreadableReference, Locale::En) => "{} can't be read: {}",
+            (Message::UnreadableReference, Locale::Ru) => "{} не удалось прочитать: {}",
+            (Message::TemplatesNecessary, Locale::En) => "necessary {}",
+            (Message::TemplatesNecessary, Locale::Ru) => "обязательные {}",
+            (Message::TemplatesOptional, Locale::En) => "optional {}",
+            (Message::TemplatesOptional, Locale::Ru) => "необязательные {}",
+            (Message::TaskNotesUsed, Locale::En) => "Task notes used: {}",
+            (Message::TaskNotesUsed, Locale::Ru) => "Использованы заметки задачи: {}",
+            (Message::BacktraceHint, Locale::En) => "Run with RUST_BACKTRACE=1 to see where the error happened",
+            (Message::BacktraceHint, Locale::Ru) => "Запустите с RUST_BACKTRACE=1, чтобы увидеть, где произошла ошибка",
+            (Message::Created, Locale::En) => "Created {}",
+            (Message::Created, Locale::Ru) => "Создан {}",
+            (Message::Extracted, Locale::En) => "Extracted {}",
+            (Message::Extracted, Locale::Ru) => "Извлечен {}",
+            (Message::TaskAdded, Locale::En) => "Successfully add",
+            (Message::TaskAdded, Locale::Ru) => "Задача добавлена",
+            (Message::BrokenTasks, Locale::En) => "{} of {} tasks are broken",
+            (Message::BrokenTasks, Locale::Ru) => "Сломанных задач: {} из {}",
+            (Message::TasksFine, Locale::En) => "All {} tasks are fine",
+            (Message::TasksFine, Locale::Ru) => "Все задачи в порядке: {}",
+            (Message::InvalidFiles, Locale::En) => "{} of {} files are invalid",
+            (Message::InvalidFiles, Locale::Ru) => "Некорректных файлов: {} из {}",
+            (Message::FilesFine, Locale::En) => "All {} files are fine",
+            (Message::FilesFine, Locale::Ru) => "Все файлы в порядке: {}",
+            (Message::NewVersion, Locale::En) => "New version {} is available, you use {}. Update with: cargo install --git {}",
+            (Message::NewVersion, Locale::Ru) => "Доступна новая версия {}, у вас {}. Обновите командой: cargo install --git {}",
+            (Message::LatestVersion, Locale::En) => "You use the latest version {}",
+            (Message::LatestVersion, Locale::Ru) => "У вас последняя версия {}",
+            (Message::NoReleases, Locale::En) => "No released versions found in {}",
+            (Message::NoReleases, Locale::Ru) => "В {} не найдено выпущенных версий",
+            (Message::WouldCreateDirectory, Locale::En) => "Would create directory {}",
+            (Message::WouldCreateDirectory, Locale::Ru) => "Будет создана папка {}",
+            (Message::WouldOverwrite, Locale::En) => "Would overwrite {}:",
+            (Message::WouldOverwrite, Locale::Ru) => "Будет перезаписан {}:",
+            (Message::WouldWrite, Locale::En) => "Would write {}:",
+            (Message::WouldWrite, Locale::Ru) => "Будет записан {}:",
+            (Message::NameIsEmpty, Locale::En) => "Name is empty",
+            (Message::NameIsEmpty, Locale::Ru) => "Пустое имя",
+            (Message::NameIsPath, Locale::En) => "Name {} should not be a path",
+            (Message::NameIsPath, Locale::Ru) => "Имя {} не должно быть путем",
+            (Message::CantSetUpLogger, Locale::En) => "Can't set up logger",
+            (Message::CantSetUpLogger, Locale::Ru) => "Не удалось настроить логирование",
+            (Message::CantCheckForUpdates, Locale::En) => "Can't check for updates: {}",
+            (Message::CantCheckForUpdates, Locale::Ru) => "Не удалось проверить обновления: {}",
+            (Message::StdinConfigInReview, Locale::En) => "The config can't be read from stdin for the review, stdin is used for the commands",
+            (Message::StdinConfigInReview, Locale::Ru) => "Для ревью конфиг нельзя читать из stdin, stdin используется для команд",
+            (Message::CantLoadContext, Locale::En) => "Can't load context",
+            (Message::CantLoadContext, Locale::Ru) => "Не удалось загрузить проект",
+            (Message::CantRepairTask, Locale::En) => "Can't repair task",
+            (Message::CantRepairTask, Locale::Ru) => "Не удалось восстановить задачу",
+            (Message::CantExtractCodeFromArchive, Locale::En) => "Can't extract code from archive",
+            (Message::CantExtractCodeFromArchive, Locale::Ru) => "Не удалось извлечь код из архива",
+            (Message::CantSwitchToTask, Locale::En) => "Can't switch to task",
+            (Message::CantSwitchToTask, Locale::Ru) => "Не удалось переключиться на задачу",
+            (Message::CheckTaskFail, Locale::En) => "Check task fail",
+            (Message::CheckTaskFail, Locale::Ru) => "Задача не прошла проверку",
+            (Message::TaskIsNotSet, Locale::En) => "Task is not set",
+            (Message::TaskIsNotSet, Locale::Ru) => "Задача не выбрана",
+            (Message::CantSaveReviewTime, Locale::En) => "Can't save review time",
+            (Message::CantSaveReviewTime, Locale::Ru) => "Не удалось сохранить время ревью",
+            (Message::CantAddTask, Locale::En) => "Can't add task",
+            (Message::CantAddTask, Locale::Ru) => "Не удалось добавить задачу",
+            (Message::CantSerializeStats, Locale::En) => "Can't serialize stats",
+            (Message::CantSerializeStats, Locale::Ru) => "Не удалось сериализовать статистику",
+            (Message::CantSerializeProjectInfo, Locale::En) => "Can't serialize project info",
+            (Message::CantSerializeProjectInfo, Locale::Ru) => "Не удалось сериализовать сведения о проекте",
+            (Message::CantSerializeSchema, Locale::En) => "Can't serialize schema",
+            (Message::CantSerializeSchema, Locale::Ru) => "Не удалось сериализовать схему",
+            (Message::CantOpenArchive, Locale::En) => "Can't open archive {}",
+            (Message::CantOpenArchive, Locale::Ru) => "Не удалось открыть архив {}",
+            (Message::CantReadZipArchive, Locale::En) => "Can't read zip archive",
+            (Message::CantReadZipArchive, Locale::Ru) => "Не удалось прочитать zip-архив",
+            (Message::CantReadZipArchiveEntry, Locale::En) => "Can't read zip archive entry",
+            (Message::CantReadZipArchiveEntry, Locale::Ru) => "Не удалось прочитать элемент zip-архива",
+            (Message::ArchiveEntryOutside, Locale::En) => "Skip archive entry {} outside of the archive",
+            (Message::ArchiveEntryOutside, Locale::Ru) => "Пропущен элемент архива {} за пределами архива",
+            (Message::ArchiveHasNoFile, Locale::En) => "Archive {} has no {}",
+            (Message::ArchiveHasNoFile, Locale::Ru) => "В архиве {} нет {}",
+            (Message::CodeFileIsADirectory, Locale::En) => "Code file {} is a directory",
+            (Message::CodeFileIsADirectory, Locale::Ru) => "Файл с кодом {} является папкой",
+            (Message::CantCreateDirectoryForCode, Locale::En) => "Can't create directory for code",
+            (Message::CantCreateDirectoryForCode, Locale::Ru) => "Не удалось создать папку для кода",
+            (Message::CantCreateCodeFile, Locale::En) => "Can't create code file",
+            (Message::CantCreateCodeFile, Locale::Ru) => "Не удалось создать файл с кодом",
+            (Message::CantExtractCodeFileFromArchive, Locale::En) => "Can't extract code file from archive",
+            (Message::CantExtractCodeFileFromArchive, Locale::Ru) => "Не удалось извлечь файл с кодом из архива",
+            (Message::ProfileWithStdinConfig, Locale::En) => "Profile can't be used with the config from stdin",
+            (Message::ProfileWithStdinConfig, Locale::Ru) => "Профиль нельзя использовать с конфигом из stdin",
+            (Message::FailedToReadConfigFromStdin, Locale::En) => "Failed to read config from stdin",
+            (Message::FailedToReadConfigFromStdin, Locale::Ru) => "Не удалось прочитать конфиг из stdin",
+            (Message::FailedToReadConfigFile, Locale::En) => "Failed to read config file",
+            (Message::FailedToReadConfigFile, Locale::Ru) => "Не удалось прочитать файл конфига",
+            (Message::UnsupportedTaskSchema, Locale::En) => "Task {} has schema {}, but this version of the tool supports only schema up to {}, update the tool",
+            (Message::UnsupportedTaskSchema, Locale::Ru) => "У задачи {} схема {}, а эта версия инструмента поддерживает схему не выше {}, обновите инструмент",
+            (Message::IncorrectFirstNoteNumberSetting, Locale::En) => "first_note_number should be 0 or 1",
+            (Message::IncorrectFirstNoteNumberSetting, Locale::Ru) => "first_note_number должен быть 0 или 1",
+            (Message::CantGetCurrentDirectory, Locale::En) => "Can't get current directory",
+            (Message::CantGetCurrentDirectory, Locale::Ru) => "Не удалось получить текущую папку",
+            (Message::TaskNotFound, Locale::En) => "Task not found",
+            (Message::TaskNotFound, Locale::Ru) => "Задача не найдена",
+            (Message::CantLoadTaskNotes, Locale::En) => "Can't load task notes",
+            (Message::CantLoadTaskNotes, Locale::Ru) => "Не удалось загрузить заметки задачи",
+            (Message::ProjectDirectoryDoesntExist, Locale::En) => "Project directory doesn't exist",
+            (Message::ProjectDirectoryDoesntExist, Locale::Ru) => "Папка проекта не существует",
+            (Message::ProjectDirectoryIsNotADirectory, Locale::En) => "Project directory is not a directory",
+            (Message::ProjectDirectoryIsNotADirectory, Locale::Ru) => "Путь к папке проекта указывает не на папку",
+            (Message::CantCreateReviewDirectory, Locale::En) => "Can't create review directory",
+            (Message::CantCreateReviewDirectory, Locale::Ru) => "Не удалось создать папку ревью",
+            (Message::CantCreateTaskDirectory, Locale::En) => "Can't create task directory",
+            (Message::CantCreateTaskDirectory, Locale::Ru) => "Не удалось создать папку задачи",
+            (Message::CantCreateNotesDirectory, Locale::En) => "Can't create notes directory",
+            (Message::CantCreateNotesDirectory, Locale::Ru) => "Не удалось создать папку заметок",
+            (Message::CantWriteConfig, Locale::En) => "Can't write to the config path",
+            (Message::CantWriteConfig, Locale::Ru) => "Не удалось записать конфиг",
+            (Message::CantSerializeState, Locale::En) => "Can't serialize state to json",
+            (Message::CantSerializeState, Locale::Ru) => "Не удалось сериализовать состояние в json",
+            (Message::NoHomeDirectory, Locale::En) => "Can't find the home directory to expand ~",
+            (Message::NoHomeDirectory, Locale::Ru) => "Не удалось найти домашнюю папку, чтобы раскрыть ~",
+            (Message::CharactersReplaced, Locale::En) => "Some characters can't be represented in {}, they were replaced",
+            (Message::CharactersReplaced, Locale::Ru) => "Некоторые символы нельзя записать в {}, они заменены",
+            (Message::TextNotInEncoding, Locale::En) => "Text is not in {}, check output_encoding",
+            (Message::TextNotInEncoding, Locale::Ru) => "Текст не в кодировке {}, проверьте output_encoding",
+            (Message::UnknownOutputEncoding, Locale::En) => "Unknown output encoding: {}",
+            (Message::UnknownOutputEncoding, Locale::Ru) => "Неизвестная кодировка: {}",
+            (Message::UnsupportedEncoding, Locale::En) => "Output encoding {} is not supported",
+            (Message::UnsupportedEncoding, Locale::Ru) => "Кодировка {} не поддерживается",
+            (Message::BomOnlyForUtf8, Locale::En) => "BOM is supported only for UTF-8",
+            (Message::BomOnlyForUtf8, Locale::Ru) => "BOM поддерживается только для UTF-8",
+            (Message::CantLoadNotesOfTask, Locale::En) => "Can't load notes of task {}",
+            (Message::CantLoadNotesOfTask, Locale::Ru) => "Не удалось загрузить заметки задачи {}",
+            (Message::CantResolveConfigPath, Locale::En) => "Can't resolve config path",
+            (Message::CantResolveConfigPath, Locale::Ru) => "Не удалось найти путь к конфигу",
+            (Message::CantOpenFileForNotes, Locale::En) => "Can't open file for notes",
+            (Message::CantOpenFileForNotes, Locale::Ru) => "Не удалось открыть файл заметок",
+            (Message::CantReadFileForNotes, Locale::En) => "Can't read file for notes",
+            (Message::CantReadFileForNotes, Locale::Ru) => "Не удалось прочитать файл заметок",
+            (Message::CantReadNote, Locale::En) => "Can't read note {}",
+            (Message::CantReadNote, Locale::Ru) => "Не удалось прочитать заметку {}",
+            (Message::NoteNotFound, Locale::En) => "Note not found",
+            (Message::NoteNotFound, Locale::Ru) => "Заметка не найдена",
+            (Message::CantMergeNoteWithItself, Locale::En) => "Can't merge note with itself",
+            (Message::CantMergeNoteWithItself, Locale::Ru) => "Нельзя объединить заметку с ней самой",
+            (Message::NumberedNoteNotFound, Locale::En) => "Note {} not found",
+            (Message::NumberedNoteNotFound, Locale::Ru) => "Заметка {} не найдена",
+            (Message::NotesNotUtf8, Locale::En) => "Notes are not valid UTF-8",
+            (Message::NotesNotUtf8, Locale::Ru) => "Заметки не в кодировке UTF-8",
+            (Message::CantWriteNotesFile, Locale::En) => "Can't write notes file",
+            (Message::CantWriteNotesFile, Locale::Ru) => "Не удалось записать файл заметок",
+            (Message::IncorrectNoteNumberAtLine, Locale::En) => "Incorrect number of note at line {}: {} instead of {}",
+            (Message::IncorrectNoteNumberAtLine, Locale::Ru) => "Неверный номер заметки в строке {}: {} вместо {}",
+            (Message::NoteRenumbered, Locale::En) => "Note {} at line {} is renumbered to {}",
+            (Message::NoteRenumbered, Locale::Ru) => "Заметка {} в строке {} перенумерована в {}",
+            (Message::FirstLineReadProblem, Locale::En) => "First line read problem",
+            (Message::FirstLineReadProblem, Locale::Ru) => "Не удалось прочитать первую строку",
+            (Message::IncorrectSectionHeader, Locale::En) => "Line {} should be '{}' or '{}'",
+            (Message::IncorrectSectionHeader, Locale::Ru) => "Строка {} должна быть '{}' или '{}'",
+            (Message::IncorrectLineStart, Locale::En) => "Incorrect line start",
+            (Message::IncorrectLineStart, Locale::Ru) => "Неверное начало строки",
+            (Message::NoNoteNumber, Locale::En) => "No note number",
+            (Message::NoNoteNumber, Locale::Ru) => "Нет номера заметки",
+            (Message::IncorrectLine, Locale::En) => "Incorrect line",
+            (Message::IncorrectLine, Locale::Ru) => "Неверная строка",
+            (Message::NoteFormatWithoutNumber, Locale::En) => "Note format should contain {n}",
+            (Message::NoteFormatWithoutNumber, Locale::Ru) => "Формат заметки должен содержать {n}",
+            (Message::NoteFormatWithoutText, Locale::En) => "Note format should contain {text} after {n}",
+            (Message::NoteFormatWithoutText, Locale::Ru) => "Формат заметки должен содержать {text} после {n}",
+            (Message::NoteFormatWithoutSeparator, Locale::En) => "Note format should separate {n} and {text}",
+            (Message::NoteFormatWithoutSeparator, Locale::Ru) => "Формат заметки должен разделять {n} и {text}",
+            (Message::IncorrectNoteNumber, Locale::En) => "Incorrect note number {}",
+            (Message::IncorrectNoteNumber, Locale::Ru) => "Неверный номер заметки {}",
+            (Message::NotesAreNumberedFrom, Locale::En) => "Notes are numbered from {}",
+            (Message::NotesAreNumberedFrom, Locale::Ru) => "Заметки нумеруются с {}",
+            (Message::IncorrectDeadline, Locale::En) => "Deadline {} is not in the YYYY-MM-DD format",
+            (Message::IncorrectDeadline, Locale::Ru) => "Срок {} не в формате ГГГГ-ММ-ДД",
+            (Message::CodeFilePathOutsideTask, Locale::En) => "Code file path {} should be relative and stay in the task directory",
+            (Message::CodeFilePathOutsideTask, Locale::Ru) => "Путь к файлу с кодом {} должен быть относительным и не выходить за папку задачи",
+            (Message::CodeFilePathWithoutName, Locale::En) => "Code file path {} should end with the code file name {}",
+            (Message::CodeFilePathWithoutName, Locale::Ru) => "Путь к файлу с кодом {} должен заканчиваться именем файла {}",
+            (Message::CantCreateTaskCodeFile, Locale::En) => "Can't create new file for code to task",
+            (Message::CantCreateTaskCodeFile, Locale::Ru) => "Не удалось создать файл для кода задачи",
+            (Message::CantCreateFileForCode, Locale::En) => "Can't create file for code",
+            (Message::CantCreateFileForCode, Locale::Ru) => "Не удалось создать файл для кода",
+            (Message::CantCreateNotesFile, Locale::En) => "Can't create notes file",
+            (Message::CantCreateNotesFile, Locale::Ru) => "Не удалось создать файл заметок",
+            (Message::TaskDirectoryDoesntExist, Locale::En) => "Task directory doesn't exist",
+            (Message::TaskDirectoryDoesntExist, Locale::Ru) => "Папка задачи не существует",
+            (Message::TaskDirectoryIsNotADirectory, Locale::En) => "Task directory is not a directory",
+            (Message::TaskDirectoryIsNotADirectory, Locale::Ru) => "Путь к папке задачи указывает не на папку",
+            (Message::NotesDirectoryDoesntExist, Locale::En) => "Notes directory doesn't exist",
+            (Message::NotesDirectoryDoesntExist, Locale::Ru) => "Папка заметок не существует",
+            (Message::NotesDirectoryIsNotADirectory, Locale::En) => "Notes directory is not a directory",
+            (Message::NotesDirectoryIsNotADirectory, Locale::Ru) => "Путь к папке заметок указывает не на папку",
+            (Message::TaskCodeFileDoesntExist, Locale::En) => "Task code file doesn't exist",
+            (Message::TaskCodeFileDoesntExist, Locale::Ru) => "Файл с кодом задачи не существует",
+            (Message::TaskCodeFileIsNotAFile, Locale::En) => "Task code file is not a file",
+            (Message::TaskCodeFileIsNotAFile, Locale::Ru) => "Путь к коду задачи указывает не на файл",
+            (Message::NotesFileDoesntExist, Locale::En) => "Notes file doesn't exist",
+            (Message::NotesFileDoesntExist, Locale::Ru) => "Файл заметок не существует",
+            (Message::NotesFileIsNotAFile, Locale::En) => "Notes file is not a file",
+            (Message::NotesFileIsNotAFile, Locale::Ru) => "Путь к заметкам указывает не на файл",
+            (Message::CantParseClangTidyDiagnostics, Locale::En) => "Can't parse clang-tidy diagnostics",
+            (Message::CantParseClangTidyDiagnostics, Locale::Ru) => "Не удалось разобрать диагностики clang-tidy",
+            (Message::CantReadNamedFile, Locale::En) => "Can't read file {}",
+            (Message::CantReadNamedFile, Locale::Ru) => "Не удалось прочитать файл {}",
+            (Message::IncorrectHunkHeader, Locale::En) => "Incorrect hunk header",
+            (Message::IncorrectHunkHeader, Locale::Ru) => "Неверный заголовок фрагмента",
+            (Message::HunkHeaderHasNoNewRange, Locale::En) => "Hunk header has no new range",
+            (Message::HunkHeaderHasNoNewRange, Locale::Ru) => "В заголовке фрагмента нет нового диапазона",
+            (Message::RangeDoesntStartWith, Locale::En) => "Range {} doesn't start with {}",
+            (Message::RangeDoesntStartWith, Locale::Ru) => "Диапазон {} не начинается с {}",
+            (Message::IncorrectHunkStart, Locale::En) => "Incorrect hunk start",
+            (Message::IncorrectHunkStart, Locale::Ru) => "Неверное начало фрагмента",
+            (Message::IncorrectHunkLength, Locale::En) => "Incorrect hunk length",
+            (Message::IncorrectHunkLength, Locale::Ru) => "Неверная длина фрагмента",
+            (Message::HunkLongerThanHeader, Locale::En) => "Hunk is longer than its header says at line {}",
+            (Message::HunkLongerThanHeader, Locale::Ru) => "Фрагмент длиннее, чем указано в заголовке, в строке {}",
+            (Message::MalformedHunkLine, Locale::En) => "Malformed hunk line {}: {}",
+            (Message::MalformedHunkLine, Locale::Ru) => "Некорректная строка фрагмента {}: {}",
+            (Message::MalformedHunkHeaderAtLine, Locale::En) => "Malformed hunk header at line {}",
+            (Message::MalformedHunkHeaderAtLine, Locale::Ru) => "Некорректный заголовок фрагмента в строке {}",
+            (Message::HunkWithoutFileHeader, Locale::En) => "Hunk without a file header at line {}",
+            (Message::HunkWithoutFileHeader, Locale::Ru) => "Фрагмент без заголовка файла в строке {}",
+            (Message::DiffEndsInHunk, Locale::En) => "Diff ends in the middle of the last hunk",
+            (Message::DiffEndsInHunk, Locale::Ru) => "Diff обрывается посреди последнего фрагмента",
+            (Message::DiffHasNoHunks, Locale::En) => "Diff has no hunks",
+            (Message::DiffHasNoHunks, Locale::Ru) => "В diff нет фрагментов",
+            (Message::CantReadReview, Locale::En) => "Can't read review",
+            (Message::CantReadReview, Locale::Ru) => "Не удалось прочитать ревью",
+            (Message::ReviewHasNoHeader, Locale::En) => "Review has no header",
+            (Message::ReviewHasNoHeader, Locale::Ru) => "У ревью нет заголовка",
+            (Message::ReviewHasNoNotes, Locale::En) => "Review has no notes",
+            (Message::ReviewHasNoNotes, Locale::Ru) => "В ревью нет замечаний",
+            (Message::ReviewHasNoNotesAfterContents, Locale::En) => "Review has no notes after contents",
+            (Message::ReviewHasNoNotesAfterContents, Locale::Ru) => "В ревью нет замечаний после оглавления",
+            (Message::ReviewHasTooManySeparators, Locale::En) => "Review has too many separators",
+            (Message::ReviewHasTooManySeparators, Locale::Ru) => "В ревью слишком много разделителей",
+            (Message::CantReadResolvedNotes, Locale::En) => "Can't read resolved notes",
+            (Message::CantReadResolvedNotes, Locale::Ru) => "Не удалось прочитать исправленные замечания",
+            (Message::CantReadNotesOf, Locale::En) => "Can't read notes of {}",
+            (Message::CantReadNotesOf, Locale::Ru) => "Не удалось прочитать замечания {}",
+            (Message::CantRunExternalCommand, Locale::En) => "Can't run external command {}",
+            (Message::CantRunExternalCommand, Locale::Ru) => "Не удалось запустить внешнюю команду {}",
+            (Message::CantWaitExternalCommand, Locale::En) => "Can't wait external command",
+            (Message::CantWaitExternalCommand, Locale::Ru) => "Не удалось дождаться внешней команды",
+            (Message::ExternalCommandTimedOut, Locale::En) => "External command timed out after {} seconds: {}",
+            (Message::ExternalCommandTimedOut, Locale::Ru) => "Внешняя команда не завершилась за {} секунд: {}",
+            (Message::CantSwitchTerminalToRawMode, Locale::En) => "Can't switch terminal to raw mode",
+            (Message::CantSwitchTerminalToRawMode, Locale::Ru) => "Не удалось переключить терминал в raw-режим",
+            (Message::CantRestoreTerminal, Locale::En) => "Can't switch terminal back from raw mode",
+            (Message::CantRestoreTerminal, Locale::Ru) => "Не удалось вернуть терминал из raw-режима",
+            (Message::CantReadKey, Locale::En) => "Can't read key",
+            (Message::CantReadKey, Locale::Ru) => "Не удалось прочитать клавишу",
+            (Message::CantReadFile, Locale::En) => "Can't read file",
+            (Message::CantReadFile, Locale::Ru) => "Не удалось прочитать файл",
+            (Message::CantReadPath, Locale::En) => "Can't read {}",
+            (Message::CantReadPath, Locale::Ru) => "Не удалось прочитать {}",
+            (Message::EmptyReference, Locale::En) => "Empty reference",
+            (Message::EmptyReference, Locale::Ru) => "Пустая ссылка",
+            (Message::ReferenceLineWithoutNumber, Locale::En) => "Line '{}' of the reference has no line number",
+            (Message::ReferenceLineWithoutNumber, Locale::Ru) => "В строке '{}' ссылки нет номера строки",
+            (Message::ReferenceHasNoQuotedLines, Locale::En) => "Reference has no quoted lines",
+            (Message::ReferenceHasNoQuotedLines, Locale::Ru) => "В ссылке нет процитированных строк",
+            (Message::ReferenceRowsNotInOrder, Locale::En) => "Line numbers of the reference are not in order: {}",
+            (Message::ReferenceRowsNotInOrder, Locale::Ru) => "Номера строк в ссылке идут не по порядку: {}",
+            (Message::LineFromEndOutOfFile, Locale::En) => "Line -{} is out of the file with {} lines",
+            (Message::LineFromEndOutOfFile, Locale::Ru) => "Строка -{} за пределами файла, в нем строк: {}",
+            (Message::LinesNumberedFromOne, Locale::En) => "Lines are numbered from 1",
+            (Message::LinesNumberedFromOne, Locale::Ru) => "Строки нумеруются с 1",
+            (Message::NoTextInNote, Locale::En) => "No text in note",
+            (Message::NoTextInNote, Locale::Ru) => "Нет текста замечания",
+            (Message::NoDiffFileInReference, Locale::En) => "No diff file in reference",
+            (Message::NoDiffFileInReference, Locale::Ru) => "В ссылке нет файла diff",
+            (Message::NoHunkNumberInReference, Locale::En) => "No hunk number in reference",
+            (Message::NoHunkNumberInReference, Locale::Ru) => "В ссылке нет номера фрагмента",
+            (Message::IncorrectHunkNumberInReference, Locale::En) => "Incorrect hunk number in reference",
+            (Message::IncorrectHunkNumberInReference, Locale::Ru) => "Неверный номер фрагмента в ссылке",
+            (Message::NoLineNumberInReference, Locale::En) => "No line number in reference",
+            (Message::NoLineNumberInReference, Locale::Ru) => "В ссылке нет номера строки",
+            (Message::IncorrectLineNumberInReference, Locale::En) => "Incorrect line number in reference",
+            (Message::IncorrectLineNumberInReference, Locale::Ru) => "Неверный номер строки в ссылке",
+            (Message::NoBookmarkNameInReference, Locale::En) => "No bookmark name in reference",
+            (Message::NoBookmarkNameInReference, Locale::Ru) => "В ссылке нет имени закладки",
+            (Message::NoFirstNumberInReference, Locale::En) => "No first number in reference",
+            (Message::NoFirstNumberInReference, Locale::Ru) => "В ссылке нет первого номера",
+            (Message::IncorrectFirstNumberInReference, Locale::En) => "Incorrect first number in reference",
+            (Message::IncorrectFirstNumberInReference, Locale::Ru) => "Неверный первый номер в ссылке",
+            (Message::NoSecondNumberInReference, Locale::En) => "No second number in reference",
+            (Message::NoSecondNumberInReference, Locale::Ru) => "В ссылке нет второго номера",
+            (Message::IncorrectSecondNumberInReference, Locale::En) => "Incorrect second number in reference",
+            (Message::IncorrectSecondNumberInReference, Locale::Ru) => "Неверный второй номер в ссылке",
+            (Message::NoLineNumberInMark, Locale::En) => "No line number in mark",
+            (Message::NoLineNumberInMark, Locale::Ru) => "В пометке нет номера строки",
+            (Message::IncorrectLineNumberInMark, Locale::En) => "Incorrect line number in mark",
+            (Message::IncorrectLineNumberInMark, Locale::Ru) => "Неверный номер строки в пометке",
+            (Message::NoCommentInMark, Locale::En) => "No comment in mark",
+            (Message::NoCommentInMark, Locale::Ru) => "В пометке нет комментария",
+            (Message::EmptyCodeFile, Locale::En) => "The code file is empty, there is nothing to reference",
+            (Message::EmptyCodeFile, Locale::Ru) => "Файл с кодом пуст, ссылаться не на что",
+            (Message::ReferenceStartAfterEnd, Locale::En) => "Reference start {} is after its end {}",
+            (Message::ReferenceStartAfterEnd, Locale::Ru) => "Начало ссылки {} после ее конца {}",
+            (Message::ReferenceOutOfFile, Locale::En) => "Reference {}-{} is out of the file with {} lines",
+            (Message::ReferenceOutOfFile, Locale::Ru) => "Ссылка {}-{} за пределами файла, в нем строк: {}",
+            (Message::MarkOutOfReference, Locale::En) => "Marked line {} is out of the reference {}-{}",
+            (Message::MarkOutOfReference, Locale::Ru) => "Помеченная строка {} за пределами ссылки {}-{}",
+            (Message::IncorrectInlineReference, Locale::En) => "Inline reference should be one line without marks",
+            (Message::IncorrectInlineReference, Locale::Ru) => "Встроенная ссылка должна быть одной строкой без пометок",
+            (Message::CantReadReference, Locale::En) => "Can't read reference {}",
+            (Message::CantReadReference, Locale::Ru) => "Не удалось прочитать ссылку {}",
+            (Message::NoReviewToView, Locale::En) => "Task {} has no review to view",
+            (Message::NoReviewToView, Locale::Ru) => "У задачи {} нет ревью для просмотра",
+            (Message::InputIsNotTerminal, Locale::En) => "Input is not a terminal, commands are read by lines",
+            (Message::InputIsNotTerminal, Locale::Ru) => "Ввод не из терминала, команды читаются по строкам",
+            (Message::InvalidReferenceIgnorePattern, Locale::En) => "Invalid reference ignore pattern {}",
+            (Message::InvalidReferenceIgnorePattern, Locale::Ru) => "Неверный шаблон игнорируемых строк ссылок {}",
+            (Message::CantSaveNotes, Locale::En) => "Can't save notes",
+            (Message::CantSaveNotes, Locale::Ru) => "Не удалось сохранить замечания",
+            (Message::CantPrintTaskNotes, Locale::En) => "Can't print task notes",
+            (Message::CantPrintTaskNotes, Locale::Ru) => "Не удалось вывести заметки задачи",
+            (Message::CantSaveSession, Locale::En) => "Can't save the review session: {}",
+            (Message::CantSaveSession, Locale::Ru) => "Не удалось сохранить сессию ревью: {}",
+            (Message::CantRemoveSessionFile, Locale::En) => "Can't remove session file",
+            (Message::CantRemoveSessionFile, Locale::Ru) => "Не удалось удалить файл сессии",
+            (Message::CantRestoreSession, Locale::En) => "Can't restore the review session: {}",
+            (Message::CantRestoreSession, Locale::Ru) => "Не удалось восстановить сессию ревью: {}",
+            (Message::UnknownSessionAuthor, Locale::En) => "Author {} of the session is unknown",
+            (Message::UnknownSessionAuthor, Locale::Ru) => "Автор сессии {} неизвестен",
+            (Message::CantFlushPrompt, Locale::En) => "Can't flush prompt",
+            (Message::CantFlushPrompt, Locale::Ru) => "Не удалось вывести приглашение",
+            (Message::ReadingLineFail, Locale::En) => "Reading line fail",
+            (Message::ReadingLineFail, Locale::Ru) => "Не удалось прочитать строку",
+            (Message::NoCommandToRepeat, Locale::En) => "No command to repeat",
+            (Message::NoCommandToRepeat, Locale::Ru) => "Нет команды для повтора",
+            (Message::IncorrectNoteType, Locale::En) => "Incorrect note type",
+            (Message::IncorrectNoteType, Locale::Ru) => "Неверный тип замечания",
+            (Message::IncorrectFirstNoteNumber, Locale::En) => "Incorrect first note number",
+            (Message::IncorrectFirstNoteNumber, Locale::Ru) => "Неверный номер первого замечания",
+            (Message::IncorrectSecondNoteNumber, Locale::En) => "Incorrect second note number",
+            (Message::IncorrectSecondNoteNumber, Locale::Ru) => "Неверный номер второго замечания",
+            (Message::NoSuggestedCode, Locale::En) => "No suggested code",
+            (Message::NoSuggestedCode, Locale::Ru) => "Нет предложенного кода",
+            (Message::NoBookmarkName, Locale::En) => "No bookmark name",
+            (Message::NoBookmarkName, Locale::Ru) => "Нет имени закладки",
+            (Message::NoFirstNumberInBookmark, Locale::En) => "No first number in bookmark",
+            (Message::NoFirstNumberInBookmark, Locale::Ru) => "В закладке нет первого номера",
+            (Message::IncorrectFirstNumberInBookmark, Locale::En) => "Incorrect first number in bookmark",
+            (Message::IncorrectFirstNumberInBookmark, Locale::Ru) => "Неверный первый номер в закладке",
+            (Message::NoSecondNumberInBookmark, Locale::En) => "No second number in bookmark",
+            (Message::NoSecondNumberInBookmark, Locale::Ru) => "В закладке нет второго номера",
+            (Message::IncorrectSecondNumberInBookmark, Locale::En) => "Incorrect second number in bookmark",
+            (Message::IncorrectSecondNumberInBookmark, Locale::Ru) => "Неверный второй номер в закладке",
+            (Message::NoDiagnosticsFile, Locale::En) => "No diagnostics file",
+            (Message::NoDiagnosticsFile, Locale::Ru) => "Нет файла диагностик",
+            (Message::NoAuthorName, Locale::En) => "No author name",
+            (Message::NoAuthorName, Locale::Ru) => "Нет имени автора",
+            (Message::NoPreviousReference, Locale::En) => "No previous reference in this review to reuse",
+            (Message::NoPreviousReference, Locale::Ru) => "В этом ревью еще нет ссылки для повтора",
+            (Message::AmbiguousCodeFile, Locale::En) => "Several code files end with {}, write more of the path",
+            (Message::AmbiguousCodeFile, Locale::Ru) => "Несколько файлов с кодом заканчиваются на {}, укажите путь подробнее",
+            (Message::NoNumberOfNote, Locale::En) => "No number of note",
+            (Message::NoNumberOfNote, Locale::Ru) => "Нет номера заметки",
+            (Message::CantReadDiffFile, Locale::En) => "Can't read diff file",
+            (Message::CantReadDiffFile, Locale::Ru) => "Не удалось прочитать файл diff",
+            (Message::IncorrectDiffFile, Locale::En) => "Incorrect diff file",
+            (Message::IncorrectDiffFile, Locale::Ru) => "Неверный файл diff",
+            (Message::NoHunk, Locale::En) => "Diff has only {} hunks, no hunk {}",
+            (Message::NoHunk, Locale::Ru) => "В diff только фрагментов: {}, фрагмента {} нет",
+            (Message::NoNumberInNote, Locale::En) => "No number in note",
+            (Message::NoNumberInNote, Locale::Ru) => "Нет номера в заметке",
+            (Message::CantReadRevision, Locale::En) => "Can't read {} at revision {}, use the working tree: {}",
+            (Message::CantReadRevision, Locale::Ru) => "Не удалось прочитать {} в ревизии {}, используется рабочая копия: {}",
+            (Message::CantReadFileWithCode, Locale::En) => "Can't read file with code",
+            (Message::CantReadFileWithCode, Locale::Ru) => "Не удалось прочитать файл с кодом",
+            (Message::BinaryCodeFile, Locale::En) => "Refusing to reference binary file {}",
+            (Message::BinaryCodeFile, Locale::Ru) => "Нельзя сослаться на двоичный файл {}",
+            (Message::CodeFileNotUtf8, Locale::En) => "Code file {} is not valid UTF-8",
+            (Message::CodeFileNotUtf8, Locale::Ru) => "Файл с кодом {} не в кодировке UTF-8",
+            (Message::CodeFileHasNoDirectory, Locale::En) => "Code file has no directory",
+            (Message::CodeFileHasNoDirectory, Locale::Ru) => "У файла с кодом нет папки",
+            (Message::CodeFileHasNoName, Locale::En) => "Code file has no name",
+            (Message::CodeFileHasNoName, Locale::Ru) => "У файла с кодом нет имени",
+            (Message::GitShowFailed, Locale::En) => "git show failed: {}",
+            (Message::GitShowFailed, Locale::Ru) => "git show завершился с ошибкой: {}",
+            (Message::EmptyPagerCommand, Locale::En) => "Empty pager command",
+            (Message::EmptyPagerCommand, Locale::Ru) => "Пустая команда просмотра",
+            (Message::CantStartPager, Locale::En) => "Can't start pager {}: {}",
+            (Message::CantStartPager, Locale::Ru) => "Не удалось запустить просмотр {}: {}",
+            (Message::PagerFailed, Locale::En) => "Pager failed",
+            (Message::PagerFailed, Locale::Ru) => "Ошибка просмотра",
+            (Message::CantReadDiagnosticsFile, Locale::En) => "Can't read diagnostics file",
+            (Message::CantReadDiagnosticsFile, Locale::Ru) => "Не удалось прочитать файл диагностик",
+            (Message::ReviewNotUtf8, Locale::En) => "Review is not valid UTF-8",
+            (Message::ReviewNotUtf8, Locale::Ru) => "Ревью не в кодировке UTF-8",
+            (Message::CantPrintReview, Locale::En) => "Can't print review",
+            (Message::CantPrintReview, Locale::Ru) => "Не удалось вывести ревью",
+            (Message::CantWriteReviewTo, Locale::En) => "Can't write review to {}",
+            (Message::CantWriteReviewTo, Locale::Ru) => "Не удалось записать ревью в {}",
+            (Message::CantReadSessionFile, Locale::En) => "Can't read session file",
+            (Message::CantReadSessionFile, Locale::Ru) => "Не удалось прочитать файл сессии",
+            (Message::CantParseSessionFile, Locale::En) => "Can't parse session file",
+            (Message::CantParseSessionFile, Locale::Ru) => "Не удалось разобрать файл сессии",
+            (Message::CantSerializeSession, Locale::En) => "Can't serialize session",
+            (Message::CantSerializeSession, Locale::Ru) => "Не удалось сериализовать сессию",
+            (Message::CantWriteSessionFile, Locale::En) => "Can't write session file",
+            (Message::CantWriteSessionFile, Locale::Ru) => "Не удалось записать файл сессии",
+            (Message::CantReadReviewsDirectory, Locale::En) => "Can't read reviews directory",
+            (Message::CantReadReviewsDirectory, Locale::Ru) => "Не удалось прочитать папку ревью",
+            (Message::CantReadNamedReview, Locale::En) => "Can't read review {}",
+            (Message::CantReadNamedReview, Locale::Ru) => "Не удалось прочитать ревью {}",
+            (Message::ReviewSkipped, Locale::En) => "Skip review {}: {}",
+            (Message::ReviewSkipped, Locale::Ru) => "Ревью {} пропущено: {}",
+            (Message::CantGetTags, Locale::En) => "Can't get tags of {}: {}",
+            (Message::CantGetTags, Locale::Ru) => "Не удалось получить теги {}: {}",
+            (Message::IncorrectCrateVersion, Locale::En) => "Incorrect crate version",
+            (Message::IncorrectCrateVersion, Locale::Ru) => "Неверная версия пакета",
+            (Message::CantReadPreviousReview, Locale::En) => "Can't read the previous review {}, fix or remove it",
+            (Message::CantReadPreviousReview, Locale::Ru) => "Не удалось прочитать предыдущее ревью {}, исправьте или удалите его",
+            (Message::NotTaskCodeFile, Locale::En) => "{} is not a code file of the task, add it to extra_code_files of the task",
+            (Message::NotTaskCodeFile, Locale::Ru) => "{} не файл с кодом задачи, добавьте его в extra_code_files задачи",
+            (Message::NoBookmark, Locale::En) => "No bookmark {}, add it with `bookmark {} first second`",
+            (Message::NoBookmark, Locale::Ru) => "Нет закладки {}, добавьте ее командой `bookmark {} first second`",
+            (Message::NoOptionalTaskNotes, Locale::En) => "Task has no optional notes, add one with `new` or write the text instead of the number",
+            (Message::NoOptionalTaskNotes, Locale::Ru) => "У задачи нет необязательных заметок, добавьте их командой `new` или напишите текст вместо номера",
+            (Message::NoNecessaryTaskNotes, Locale::En) => "Task has no necessary notes, add one with `new` or write the text instead of the number",
+            (Message::NoNecessaryTaskNotes, Locale::Ru) => "У задачи нет обязательных заметок, добавьте их командой `new` или напишите текст вместо номера",
+            (Message::NoteIsOptional, Locale::En) => "Note {} is optional",
+            (Message::NoteIsOptional, Locale::Ru) => "Заметка {} необязательная",
+            (Message::NoteIsNecessary, Locale::En) => "Note {} is necessary",
+            (Message::NoteIsNecessary, Locale::Ru) => "Заметка {} обязательная",
+            (Message::CantParseLine, Locale::En) => "Can't parse line {}",
+            (Message::CantParseLine, Locale::Ru) => "Не удалось разобрать строку {}",
+            (Message::CantResolveProjectDirectory, Locale::En) => "Can't resolve project directory {}",
+            (Message::CantResolveProjectDirectory, Locale::Ru) => "Не удалось найти папку проекта {}",
+            (Message::CantParseConfig, Locale::En) => "Can't parse config {} at line {}, column {}: {}",
+            (Message::CantParseConfig, Locale::Ru) => "Не удалось разобрать конфиг {} в строке {}, столбце {}: {}",
+            (Message::TaskAssigned, Locale::En) => "Task {} is assigned to {}",
+            (Message::TaskAssigned, Locale::Ru) => "Задача {} назначена на {}",
+            (Message::UnknownTaskFields, Locale::En) => "Task {} has unknown fields {}, they may be from a newer version of the tool",
+            (Message::UnknownTaskFields, Locale::Ru) => "У задачи {} неизвестные поля {}, возможно, они из более новой версии инструмента",
+            (Message::ExtensionNotAllowed, Locale::En) => "Code file {} doesn't have an allowed extension ({})",
+            (Message::ExtensionNotAllowed, Locale::Ru) => "У файла с кодом {} недопустимое расширение (допустимы: {})",
+            (Message::SeveralCodeFilesInArchive, Locale::En) => "Archive has several code files, choose one with --code-file-path: {}",
+            (Message::SeveralCodeFilesInArchive, Locale::Ru) => "В архиве несколько файлов с кодом, выберите один через --code-file-path: {}",
+            (Message::StatsTasks, Locale::En) => "Tasks:",
+            (Message::StatsTasks, Locale::Ru) => "Задачи:",
+            (Message::StatsReviews, Locale::En) => "Reviews:",
+            (Message::StatsReviews, Locale::Ru) => "Ревью:",
+            (Message::StatsNecessaryNotes, Locale::En) => "Necessary notes:",
+            (Message::StatsNecessaryNotes, Locale::Ru) => "Обязательные замечания:",
+            (Message::StatsOptionalNotes, Locale::En) => "Optional notes:",
+            (Message::StatsOptionalNotes, Locale::Ru) => "Опциональные замечания:",
+            (Message::StatsNotesPerReview, Locale::En) => "Notes per review:",
+            (Message::StatsNotesPerReview, Locale::Ru) => "Замечаний на ревью:",
+            (Message::StatsReferences, Locale::En) => "References:",
+            (Message::StatsReferences, Locale::Ru) => "Ссылки на код:",
+            (Message::StatsReferencedLines, Locale::En) => "Referenced lines:",
+            (Message::StatsReferencedLines, Locale::Ru) => "Строки в ссылках:",
+            (Message::StatsCategories, Locale::En) => "Categories:",
+            (Message::StatsCategories, Locale::Ru) => "Категории:",
+            (Message::StatsCategory, Locale::En) => "{}: necessary {}, optional {}",
+            (Message::StatsCategory, Locale::Ru) => "{}: обязательных {}, опциональных {}",
+            (Message::ComparedToPrevious, Locale::En) => "Compared to the previous review: {} new, {} kept, {} dropped",
+            (Message::ComparedToPrevious, Locale::Ru) => "По сравнению с прошлым ревью: новых {}, сохранено {}, убрано {}",
+        }
+    }
+
+    /// Text of the message without arguments
+    pub fn text(self) -> &'static str {
+        self.template(locale())
+    }
+
+    /// Text of the message with the arguments in place of `{}`
+    pub fn format(self, args: &[&dyn Display]) -> String {
+        self.format_in(locale(), args)
+    }
+
+    fn format_in(self, locale: Locale, args: &[&dyn Display]) -> String {
+        let mut parts = self.template(locale).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for (index, part) in parts.enumerate() {
+            if let Some(arg) = args.get(index) {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_is_translated() {
+        assert_eq!(
+            Message::UnknownAction.template(Locale::En),
+            "Unknown action"
+        );
+        assert_eq!(
+            Message::UnknownAction.template(Locale::Ru),
+            "Неизвестная команда"
+        );
+    }
+
+    #[test]
+    fn arguments_are_put_in_order() {
+        let args: [&dyn Display; 2] = [&"t", &3];
+        assert_eq!(
+            Message::OpenedReadOnly.format_in(Locale::En, &args),
+            "Review of t is opened read-only with 3 notes"
+        );
+        assert_eq!(
+            Message::OpenedReadOnly.format_in(Locale::Ru, &args),
+            "Ревью задачи t открыто только для чтения, замечаний: 3"
+        );
+    }
+}
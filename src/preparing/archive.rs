@@ -0,0 +1,132 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use log::{trace, warn};
+use zip::ZipArchive;
+
+use crate::messages::Message;
+
+/// Copy the code file from the zip archive of the submission to `destination`.
+///
+/// The file is found by its name anywhere in the archive, or by the end of its path if `code_file_path` is given
+/// (e.g. `src/task1/main.cpp`). Entries with paths leaving the archive (absolute or with `..`) are skipped.
+pub(crate) fn extract_code_file(
+    archive_path: &Path,
+    code_file_name: &str,
+    code_file_path: Option<&Path>,
+    destination: &Path,
+) -> anyhow::Result<()> {
+    let file = File::open(archive_path)
+        .with_context(|| Message::CantOpenArchive.format(&[&archive_path.display()]))?;
+    let mut archive = ZipArchive::new(file).context(Message::CantReadZipArchive.text())?;
+    let mut found: Vec<(usize, PathBuf)> = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index(index)
+            .context(Message::CantReadZipArchiveEntry.text())?;
+        let Some(path) = entry.enclosed_name() else {
+            warn!(
+                "{}",
+                Message::ArchiveEntryOutside.format(&[&String::from_utf8_lossy(entry.name_raw())])
+            );
+            continue;
+        };
+        let matches = match code_file_path {
+            Some(code_file_path) => path.ends_with(code_file_path),
+            None => path
+                .file_name()
+                .is_some_and(|file_name| file_name == code_file_name),
+        };
+        if entry.is_file() && matches {
+            found.push((index, path));
+        }
+    }
+    let index = match found.as_slice() {
+        [] => bail!(
+            "{}",
+            Message::ArchiveHasNoFile.format(&[&archive_path.display(), &code_file_name])
+        ),
+        [(index, path)] => {
+            trace!("Code file {} found in archive", path.display());
+            *index
+        }
+        several => bail!(
+            "{}",
+            Message::SeveralCodeFilesInArchive.format(&[&several
+                .iter()
+                .map(|(_, path)| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")])
+        ),
+    };
+    let mut entry = archive
+        .by_index(index)
+        .context(Message::CantReadZipArchiveEntry.text())?;
+    ensure!(
+        !destination.is_dir(),
+        "{}",
+        Message::CodeFileIsADirectory.format(&[&destination.display()])
+    );
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).context(Message::CantCreateDirectoryForCode.text())?;
+    }
+    let mut output = File::create(destination).context(Message::CantCreateCodeFile.text())?;
+    io::copy(&mut entry, &mut output).context(Message::CantExtractCodeFileFromArchive.text())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn archive(dir: &TempDir, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.join("submission.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn code_file_is_extracted_by_name_or_path() {
+        let dir = TempDir::new();
+        let archive = archive(
+            &dir,
+            &[
+                ("student/readme.txt", "Hello"),
+                ("student/task1/main.cpp", "int main() {}\n"),
+                ("student/task2/main.cpp", "int main() { return 1; }\n"),
+            ],
+        );
+        let destination = dir.join("tasks/t/main.cpp");
+        assert!(extract_code_file(&archive, "main.cpp", None, &destination).is_err());
+        extract_code_file(
+            &archive,
+            "main.cpp",
+            Some(Path::new("task2/main.cpp")),
+            &destination,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&destination).unwrap(),
+            "int main() { return 1; }\n"
+        );
+        let err = extract_code_file(&archive, "solution.cpp", None, &destination).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::ArchiveHasNoFile.format(&[&archive.display(), &"solution.cpp"])
+        );
+    }
+}
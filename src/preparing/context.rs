@@ -1,100 +1,466 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::env::current_dir;
 use std::fmt::Display;
 use std::fs;
+use std::io::{stdin, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use anyhow::{ensure, Context};
-use log::trace;
+use anyhow::{bail, ensure, Context};
+use itertools::Itertools;
+use log::{trace, warn};
 
-use super::task::{ShowMethod, Task};
+use super::encoding::OutputEncoding;
+use super::notes::{Gutter, NoteIds, NoteTemplate, NotesFormat};
+use super::task::{ShowMethod, Task, TaskInfo, SUPPORTED_TASK_SCHEMA};
+use crate::messages::{Locale, Message};
+
+/// Config path to read the config from stdin and write it to stdout
+pub(crate) const STDIO_CONFIG: &str = "-";
 
 #[derive(Debug)]
 pub(crate) struct ProjectContext {
     pub author: Author,
+    pub co_authors: Vec<Author>,
     pub current_task: Option<usize>,
     pub tasks: Vec<Task>,
     pub project_dir: PathBuf,
+    pub settings: Settings,
     config_path: PathBuf,
+    /// `$schema` of the config, kept for the editors
+    schema_url: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 struct Config {
+    /// Schema of the config for the editors, see the `schema` command
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    schema_url: Option<String>,
     author_name: String,
     author_contacts: String,
+    /// Other reviewers of the project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    co_authors: Vec<Author>,
     tasks: Vec<Task>,
+    #[serde(flatten)]
+    settings: Settings,
+}
+
+/// Order of tasks in the list
+#[derive(Debug, Default, Clone, Copy, clap::ValueEnum)]
+pub(crate) enum TaskOrder {
+    /// As in the config
+    #[default]
+    Config,
+    Name,
+    /// Nearest deadline first, tasks without deadline last
+    Deadline,
+    /// Never completed first, then by `last_reviewed` from the least recent
+    Reviewed,
+}
+
+/// Optional settings of the project, all of them have defaults.
+///
+/// Only the settings which differ from the defaults are written to the config, so it stays as the user wrote it.
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Skip adding a note if the same note is already in the list
+    #[serde(skip_serializing_if = "is_default")]
+    pub deduplicate_notes: bool,
+
+    /// Encoding of the review files, e.g. `utf-8`, `utf-8-bom` or `windows-1251`
+    #[schemars(with = "String")]
+    #[serde(skip_serializing_if = "is_default")]
+    pub output_encoding: OutputEncoding,
+
+    /// Commands to check the code file (`{file}` is replaced with its path)
+    #[serde(skip_serializing_if = "is_default")]
+    pub check_commands: Vec<String>,
+
+    /// Time limit for external commands in seconds
+    #[serde(skip_serializing_if = "is_default_timeout")]
+    pub external_command_timeout_secs: u64,
+
+    /// Print the table of contents before the notes in the review
+    #[serde(skip_serializing_if = "is_default")]
+    pub table_of_contents: bool,
+
+    /// Sort tasks by name when saving the config
+    #[serde(skip_serializing_if = "is_default")]
+    pub sort_tasks: bool,
+
+    /// Link to the reviewed code in the review header, `{task}` and `{rev}` are replaced
+    #[serde(skip_serializing_if = "is_default")]
+    pub source_url: Option<String>,
+
+    /// Format of the note line with `{n}` and `{text}` placeholders
+    #[schemars(with = "String")]
+    #[serde(skip_serializing_if = "is_default")]
+    pub note_format: NoteTemplate,
+
+    /// Extensions of code files, any extension is allowed if empty
+    #[serde(skip_serializing_if = "is_default_extensions")]
+    pub allowed_extensions: Vec<String>,
+
+    /// Fail instead of warning on suspicious input
+    #[serde(skip_serializing_if = "is_default")]
+    pub strict: bool,
+
+    /// The strict mode is turned on by `--strict` for this run only, it's not written to the config
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub cli_strict: bool,
+
+    /// Language of the code in the LaTeX review for the `listings` package
+    #[serde(skip_serializing_if = "is_default_latex_language")]
+    pub latex_language: String,
+
+    /// Print the new numbers of notes after merge, promote and demote
+    #[serde(skip_serializing_if = "is_true")]
+    pub print_renumbered_notes: bool,
+
+    /// Text added after the notes of every review
+    #[serde(skip_serializing_if = "is_default")]
+    pub footer: String,
+
+    /// Things to check before the review, each is acknowledged by the reviewer
+    #[serde(skip_serializing_if = "is_default")]
+    pub checklist: Vec<String>,
+
+    /// Ask for confirmation before completing the review
+    #[serde(skip_serializing_if = "is_default")]
+    pub confirm_complete: bool,
+
+    /// Text between the line number and the code in references
+    #[serde(skip_serializing_if = "is_default_reference_separator")]
+    pub reference_separator: String,
+
+    /// Show line numbers in references
+    #[serde(skip_serializing_if = "is_true")]
+    pub reference_line_numbers: bool,
+
+    /// Write the review notes at most once in this many seconds, 0 writes every change
+    #[serde(skip_serializing_if = "is_default")]
+    pub autosave_secs: u64,
+
+    /// Block completing the review until every necessary task note is added or waived
+    #[serde(skip_serializing_if = "is_default")]
+    pub require_all_necessary: bool,
+
+    /// Print the `file:line` link before each reference
+    #[serde(skip_serializing_if = "is_default")]
+    pub reference_links: bool,
+
+    /// Write `N` and `O` before the numbers of necessary and optional notes
+    #[serde(skip_serializing_if = "is_default")]
+    pub note_id_prefix: bool,
+
+    /// Number of the first note, 0 or 1
+    #[serde(skip_serializing_if = "is_default")]
+    pub first_note_number: usize,
+
+    /// Cut the references going past the end of the file instead of failing
+    #[serde(skip_serializing_if = "is_default")]
+    pub clamp_references: bool,
+
+    /// Warn about references longer than this, or fail in the strict mode
+    #[serde(skip_serializing_if = "is_default")]
+    pub max_reference_lines: Option<usize>,
+
+    /// Write the optional notes before the necessary ones
+    #[serde(skip_serializing_if = "is_default")]
+    pub optional_notes_first: bool,
+
+    /// Regexes of the lines quoted as `...` in references, e.g. include guards or license headers
+    #[serde(skip_serializing_if = "is_default")]
+    pub reference_ignore_patterns: Vec<String>,
+
+    /// Quote the references of one line after the text of the note as `(line 42: code)`
+    #[serde(skip_serializing_if = "is_default")]
+    pub inline_single_line_references: bool,
+
+    /// Other names of the keywords of the review commands, e.g. `{"опц": "optional"}`
+    #[serde(skip_serializing_if = "is_default")]
+    pub command_aliases: BTreeMap<String, String>,
+
+    /// Language of the messages, from the environment (`LANG`) if not set
+    #[serde(skip_serializing_if = "is_default")]
+    pub locale: Option<Locale>,
+}
+
+const DEFAULT_EXTERNAL_COMMAND_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_ALLOWED_EXTENSIONS: [&str; 10] = [
+    "cpp", "hpp", "h", "cc", "hh", "cxx", "hxx", "c", "ipp", "tpp",
+];
+const DEFAULT_LATEX_LANGUAGE: &str = "C++";
+const DEFAULT_REFERENCE_SEPARATOR: &str = ": ";
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_default_timeout(secs: &u64) -> bool {
+    *secs == DEFAULT_EXTERNAL_COMMAND_TIMEOUT_SECS
+}
+
+fn is_default_extensions(extensions: &[String]) -> bool {
+    extensions == DEFAULT_ALLOWED_EXTENSIONS
+}
+
+fn is_default_latex_language(language: &str) -> bool {
+    language == DEFAULT_LATEX_LANGUAGE
+}
+
+fn is_default_reference_separator(separator: &str) -> bool {
+    separator == DEFAULT_REFERENCE_SEPARATOR
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            deduplicate_notes: false,
+            output_encoding: OutputEncoding::default(),
+            check_commands: Vec::new(),
+            external_command_timeout_secs: DEFAULT_EXTERNAL_COMMAND_TIMEOUT_SECS,
+            table_of_contents: false,
+            sort_tasks: false,
+            source_url: None,
+            note_format: NoteTemplate::default(),
+            allowed_extensions: DEFAULT_ALLOWED_EXTENSIONS.map(String::from).to_vec(),
+            strict: false,
+            cli_strict: false,
+            latex_language: DEFAULT_LATEX_LANGUAGE.to_string(),
+            print_renumbered_notes: true,
+            footer: String::new(),
+            checklist: Vec::new(),
+            confirm_complete: false,
+            reference_separator: DEFAULT_REFERENCE_SEPARATOR.to_string(),
+            reference_line_numbers: true,
+            autosave_secs: 0,
+            require_all_necessary: false,
+            reference_links: false,
+            note_id_prefix: false,
+            first_note_number: 0,
+            clamp_references: false,
+            max_reference_lines: None,
+            optional_notes_first: false,
+            reference_ignore_patterns: Vec::new(),
+            inline_single_line_references: false,
+            command_aliases: BTreeMap::new(),
+            locale: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Strict mode of the config or of the command line
+    pub fn is_strict(&self) -> bool {
+        self.strict || self.cli_strict
+    }
+
+    pub fn notes_format(&self) -> NotesFormat {
+        NotesFormat {
+            template: self.note_format.clone(),
+            ids: NoteIds {
+                prefixed: self.note_id_prefix,
+                first: self.first_note_number,
+            },
+            strict: self.is_strict(),
+            optional_first: self.optional_notes_first,
+            gutter: Gutter {
+                separator: self.reference_separator.clone(),
+                line_numbers: self.reference_line_numbers,
+                link_base: None,
+            },
+        }
+    }
 }
 
 impl ProjectContext {
-    /// Load state from the config file
-    pub fn load_state(config_path: String, project_dir: String) -> anyhow::Result<Self> {
-        let project_dir = PathBuf::from(project_dir);
+    /// Load state from the config file of the profile
+    pub fn load_state(
+        config_path: String,
+        project_dir: String,
+        profile: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let project_dir = expand_home(&project_dir)?;
+        // Resolve symlinks and the relative path once, so joined paths don't depend on them
+        let project_dir = fs::canonicalize(&project_dir).with_context(|| {
+            Message::CantResolveProjectDirectory.format(&[&project_dir.display()])
+        })?;
+        trace!("Project directory resolved to {}", project_dir.display());
         Self::check_environment(&project_dir)?;
         trace!("Project directories checked");
 
-        let config_path = PathBuf::from(config_path);
-        trace!("Load state from {}", config_path.display());
-        let config = serde_json::from_str::<Config>(
-            &fs::read_to_string(&config_path).context("Failed to read config file")?,
-        )?;
+        let config_text;
+        let config_path = if config_path == STDIO_CONFIG {
+            ensure!(profile.is_none(), Message::ProfileWithStdinConfig.text());
+            trace!("Load state from stdin");
+            let mut text = String::new();
+            stdin()
+                .read_to_string(&mut text)
+                .context(Message::FailedToReadConfigFromStdin.text())?;
+            config_text = text;
+            PathBuf::from(STDIO_CONFIG)
+        } else {
+            let config_path = profile_config_path(&expand_home(&config_path)?, profile);
+            trace!("Load state from {}", config_path.display());
+            config_text =
+                fs::read_to_string(&config_path).context(Message::FailedToReadConfigFile.text())?;
+            config_path
+        };
+        let config = serde_json::from_str::<Config>(&config_text).map_err(|err| {
+            let line = config_text.lines().nth(err.line().saturating_sub(1));
+            let message = Message::CantParseConfig.format(&[
+                &config_path.display(),
+                &err.line(),
+                &err.column(),
+                &line.unwrap_or_default().trim(),
+            ]);
+            anyhow::Error::new(err).context(message)
+        })?;
         trace!("Config loaded: {:?}", config);
+        // A task of the newer format could be misread and then saved broken
+        for task in &config.tasks {
+            if task.schema > SUPPORTED_TASK_SCHEMA {
+                bail!(
+                    "{}",
+                    Message::UnsupportedTaskSchema.format(&[
+                        &task.name,
+                        &task.schema,
+                        &SUPPORTED_TASK_SCHEMA
+                    ])
+                );
+            }
+        }
+        ensure!(
+            config.settings.first_note_number <= 1,
+            Message::IncorrectFirstNoteNumberSetting.text()
+        );
         Ok(ProjectContext {
             author: Author::new(config.author_name, config.author_contacts),
+            co_authors: config.co_authors,
             current_task: None,
             tasks: config.tasks,
             project_dir,
+            settings: config.settings,
             config_path,
+            schema_url: config.schema_url,
         })
     }
 
-    pub fn init_state(author: String, contacts: String) -> anyhow::Result<()> {
-        let dir = current_dir().context("Can't get current directory")?;
-        Self::check_environment(&dir)?;
+    /// Init the project in the current directory, with `dry_run` only print what would be created
+    pub fn init_state(
+        author: String,
+        contacts: String,
+        profile: Option<&str>,
+        dry_run: bool,
+    ) -> anyhow::Result<()> {
+        let dir = current_dir().context(Message::CantGetCurrentDirectory.text())?;
+        Self::init_state_in(dir, author, contacts, profile, dry_run)
+    }
 
-        let config_path = dir.join("config.json");
+    fn init_state_in(
+        dir: PathBuf,
+        author: String,
+        contacts: String,
+        profile: Option<&str>,
+        dry_run: bool,
+    ) -> anyhow::Result<()> {
+        if !dry_run {
+            Self::check_environment(&dir)?;
+        }
+
+        let config_path = profile_config_path(&dir.join("config.json"), profile);
 
         let context = Self {
             author: Author::new(author, contacts),
+            co_authors: vec![],
             project_dir: dir,
             config_path,
             current_task: None,
-            tasks: vec![]
+            tasks: vec![],
+            settings: Settings::default(),
+            schema_url: None,
         };
-        
+
+        if dry_run {
+            for name in ["reviews", "tasks", "notes"] {
+                let path = context.project_dir.join(name);
+                if !path.exists() {
+                    println!(
+                        "{}",
+                        Message::WouldCreateDirectory.format(&[&path.display()])
+                    );
+                }
+            }
+            let (config_path, config_text) = context.serialize()?;
+            let message = if config_path.exists() {
+                Message::WouldOverwrite
+            } else {
+                Message::WouldWrite
+            };
+            println!("{}", message.format(&[&config_path.display()]));
+            println!("{}", config_text);
+            return Ok(());
+        }
         context.dump_state()
     }
 
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// The config was read from stdin and is written to stdout
+    pub fn is_stdio_config(&self) -> bool {
+        self.config_path == Path::new(STDIO_CONFIG)
+    }
+
     /// Set the task to reviewing
     pub fn switch_to_task(&mut self, task_name: &str) -> anyhow::Result<()> {
-        let task = self
+        let index = self
             .tasks
             .iter()
-            .enumerate()
-            .find(|(_, task)| task.name == task_name)
-            .context("Task not found")?;
-        self.current_task = task.0.into();
+            .position(|task| task.name == task_name)
+            .context(Message::TaskNotFound.text())?;
+        self.tasks[index]
+            .load_notes(self.settings.notes_format())
+            .context(Message::CantLoadTaskNotes.text())?;
+        self.current_task = index.into();
         Ok(())
     }
 
     /// Check the environment for the task
     fn check_environment(project_dir: &Path) -> anyhow::Result<()> {
-        ensure!(project_dir.exists(), "Project directory doesn't exist");
+        ensure!(
+            project_dir.exists(),
+            Message::ProjectDirectoryDoesntExist.text()
+        );
 
         ensure!(
             &project_dir.is_dir(),
-            "Project directory is not a directory"
+            Message::ProjectDirectoryIsNotADirectory.text()
         );
         trace!("Project checked");
 
         let review_dir = project_dir.join("reviews");
-        fs::create_dir_all(review_dir.as_path()).context("Can't create review directory")?;
+        fs::create_dir_all(review_dir.as_path())
+            .context(Message::CantCreateReviewDirectory.text())?;
         trace!("Review directory created {}", review_dir.display());
 
         let tasks_dir = project_dir.join("tasks");
-        fs::create_dir_all(tasks_dir.as_path()).context("Can't create task directory")?;
+        fs::create_dir_all(tasks_dir.as_path()).context(Message::CantCreateTaskDirectory.text())?;
         trace!("Task directory created {}", tasks_dir.display());
 
         let notes_dir = project_dir.join("notes");
-        fs::create_dir_all(notes_dir.as_path()).context("Can't create notes directory")?;
+        fs::create_dir_all(notes_dir.as_path())
+            .context(Message::CantCreateNotesDirectory.text())?;
         trace!("Notes directory created {}", notes_dir.display());
 
         Ok(())
@@ -105,46 +471,226 @@ impl ProjectContext {
             .tasks
             .iter()
             .find(|task| task.name == task_name)
-            .context("Task not found")?;
+            .context(Message::TaskNotFound.text())?;
         task.check_environment(&self.project_dir)
     }
 
+    /// Check the environment of every task, returns the problems of the broken ones
+    pub(crate) fn validate_tasks(&self) -> Vec<(&str, anyhow::Error)> {
+        self.tasks
+            .iter()
+            .filter_map(|task| {
+                task.check_environment(&self.project_dir)
+                    .err()
+                    .map(|err| (task.name.as_str(), err))
+            })
+            .collect()
+    }
+
+    /// Recreate missing files of the task instead of failing the check
+    pub(crate) fn repair_task(&self, task_name: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let task = self
+            .tasks
+            .iter()
+            .find(|task| task.name == task_name)
+            .context(Message::TaskNotFound.text())?;
+        task.repair_environment(&self.project_dir)
+    }
+
+    /// Check that the task is not assigned to another reviewer, it's an error only in the strict mode
+    pub(crate) fn check_assignment(&self, task_name: &str) -> anyhow::Result<()> {
+        let task = self
+            .tasks
+            .iter()
+            .find(|task| task.name == task_name)
+            .context(Message::TaskNotFound.text())?;
+        let Some(assigned_to) = &task.assigned_to else {
+            return Ok(());
+        };
+        if self.author.matches(assigned_to) {
+            return Ok(());
+        }
+        let message = Message::TaskAssigned.format(&[&task_name, assigned_to]);
+        if self.settings.is_strict() {
+            bail!(message);
+        }
+        warn!("{}", message);
+        Ok(())
+    }
+
+    /// Report the task fields unknown to this version of the tool, they are errors only in the strict mode
+    pub(crate) fn check_unknown_fields(&self) -> anyhow::Result<()> {
+        for task in &self.tasks {
+            if task.unknown_fields.is_empty() {
+                continue;
+            }
+            let message = Message::UnknownTaskFields
+                .format(&[&task.name, &task.unknown_fields.keys().join(", ")]);
+            if self.settings.is_strict() {
+                bail!(message);
+            }
+            warn!("{}", message);
+        }
+        Ok(())
+    }
+
+    /// Record that the review of the task is completed now
+    pub(crate) fn mark_reviewed(&mut self, task_name: &str) -> anyhow::Result<()> {
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|task| task.name == task_name)
+            .context(Message::TaskNotFound.text())?;
+        task.last_reviewed = Some(humantime::format_rfc3339_seconds(SystemTime::now()).to_string());
+        Ok(())
+    }
+
+    /// The task is assigned to the author
+    pub(crate) fn is_mine(&self, task: &Task) -> bool {
+        task.assigned_to
+            .as_ref()
+            .is_some_and(|assigned_to| self.author.matches(assigned_to))
+    }
+
+    /// Take the code file of the task from the zip archive, returns the written file
+    pub(crate) fn extract_code(&self, task_name: &str, archive: &Path) -> anyhow::Result<PathBuf> {
+        let task = self
+            .tasks
+            .iter()
+            .find(|task| task.name == task_name)
+            .context(Message::TaskNotFound.text())?;
+        task.extract_code(&self.project_dir, archive)
+    }
+
+    /// Tasks in the given order, the stored order is not changed
+    pub(crate) fn sorted_tasks(&self, order: TaskOrder) -> Vec<&Task> {
+        let mut tasks = self.tasks.iter().collect::<Vec<_>>();
+        match order {
+            TaskOrder::Config => {}
+            TaskOrder::Name => tasks.sort_by(|first, second| first.name.cmp(&second.name)),
+            TaskOrder::Deadline => {
+                tasks.sort_by(|first, second| match (&first.deadline, &second.deadline) {
+                    (Some(first), Some(second)) => first.cmp(second),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
+            }
+            // The unfinished reviews don't count, only the completed ones have the time
+            TaskOrder::Reviewed => tasks.sort_by_cached_key(|task| {
+                task.last_reviewed
+                    .as_deref()
+                    .and_then(|time| humantime::parse_rfc3339(time).ok())
+            }),
+        }
+        tasks
+    }
+
+    /// Check that the code file has one of the allowed extensions
+    fn check_extension(&self, code_file_name: &str) -> anyhow::Result<()> {
+        let allowed = &self.settings.allowed_extensions;
+        let extension = Path::new(code_file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        if allowed.is_empty() || extension.is_some_and(|extension| allowed.contains(&extension)) {
+            return Ok(());
+        }
+        let message = Message::ExtensionNotAllowed.format(&[&code_file_name, &allowed.join(", ")]);
+        if self.settings.is_strict() {
+            bail!(message);
+        }
+        warn!("{}", message);
+        Ok(())
+    }
+
     /// Add new task
     pub(crate) fn add_task(
         &mut self,
         task_name: String,
         code_file_name: String,
         show_method: ShowMethod,
+        info: TaskInfo,
     ) -> anyhow::Result<()> {
         trace!(
             "Start adding task {} with code_file_name {}",
             task_name,
             code_file_name
         );
+        self.check_extension(&code_file_name)?;
         let task = Task::new(
             self.project_dir.as_path(),
             task_name,
             code_file_name,
             show_method,
+            self.settings.notes_format(),
+            info,
         )?;
         self.tasks.push(task);
         Ok(())
     }
 
+    /// JSON Schema of the config file for the editors
+    pub(crate) fn config_schema() -> schemars::Schema {
+        schemars::schema_for!(Config)
+    }
+
     /// Save the state in the config
     pub(crate) fn dump_state(self) -> anyhow::Result<()> {
+        let (config_path, value_to_write) = self.serialize()?;
+        if config_path == Path::new(STDIO_CONFIG) {
+            println!("{}", value_to_write);
+            return Ok(());
+        }
+        fs::write(config_path, value_to_write).context(Message::CantWriteConfig.text())
+    }
+
+    /// Config path and the text of the config to write there
+    fn serialize(mut self) -> anyhow::Result<(PathBuf, String)> {
+        if self.settings.sort_tasks {
+            self.tasks
+                .sort_by(|first, second| first.name.cmp(&second.name));
+        }
         let new_config = Config {
+            schema_url: self.schema_url,
             author_name: self.author.name,
             author_contacts: self.author.contacts,
+            co_authors: self.co_authors,
             tasks: self.tasks,
+            settings: self.settings,
         };
-        let value_to_write =
-            serde_json::to_string_pretty(&new_config).context("Can't serialize state to json")?;
-        fs::write(self.config_path, value_to_write).context("Can't write to the config path")
+        let value_to_write = serde_json::to_string_pretty(&new_config)
+            .context(Message::CantSerializeState.text())?;
+        Ok((self.config_path, value_to_write))
     }
 }
 
-#[derive(Debug)]
+/// Replace the leading `~` of the path with the home directory
+fn expand_home(path: &str) -> anyhow::Result<PathBuf> {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => rest,
+        _ => return Ok(PathBuf::from(path)),
+    };
+    let home = dirs::home_dir().context(Message::NoHomeDirectory.text())?;
+    Ok(home.join(rest.trim_start_matches(std::path::is_separator)))
+}
+
+/// Config of the named profile next to the default one, e.g. `config.work.json` for `config.json`
+fn profile_config_path(config_path: &Path, profile: Option<&str>) -> PathBuf {
+    let Some(profile) = profile else {
+        return config_path.to_owned();
+    };
+    let stem = config_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let file_name = match config_path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, profile, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, profile),
+    };
+    config_path.with_file_name(file_name)
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub(crate) struct Author {
     pub name: String,
     pub contacts: String,
@@ -154,6 +700,20 @@ impl Author {
     pub fn new(name: String, contacts: String) -> Self {
         Self { name, contacts }
     }
+
+    /// First letters of the name words, e.g. `AK` for `Anton Kopanov`
+    pub fn initials(&self) -> String {
+        self.name
+            .split_whitespace()
+            .filter_map(|word| word.chars().next())
+            .flat_map(char::to_uppercase)
+            .collect()
+    }
+
+    /// Check if the name or the initials match the query
+    pub fn matches(&self, query: &str) -> bool {
+        self.name.to_lowercase() == query.to_lowercase() || self.initials() == query.to_uppercase()
+    }
 }
 
 impl Display for Author {
@@ -161,3 +721,350 @@ impl Display for Author {
         write!(f, "Author: {}(tg: {})", self.name, self.contacts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{project, TempDir};
+
+    #[test]
+    fn default_settings_are_not_written() {
+        let text = serde_json::to_string(&Settings::default()).unwrap();
+        assert_eq!(text, "{}");
+    }
+
+    #[test]
+    fn changed_settings_are_written() {
+        let settings = Settings {
+            deduplicate_notes: true,
+            external_command_timeout_secs: 5,
+            reference_line_numbers: false,
+            reference_separator: " | ".to_string(),
+            ..Settings::default()
+        };
+        let value = serde_json::to_value(&settings).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "deduplicate_notes": true,
+                "external_command_timeout_secs": 5,
+                "reference_separator": " | ",
+                "reference_line_numbers": false,
+            })
+        );
+        let read = serde_json::from_value::<Settings>(value).unwrap();
+        assert_eq!(read.external_command_timeout_secs, 5);
+        assert_eq!(read.latex_language, DEFAULT_LATEX_LANGUAGE);
+    }
+
+    #[test]
+    fn strict_flag_is_not_written() {
+        let settings = Settings {
+            cli_strict: true,
+            ..Settings::default()
+        };
+        assert!(settings.is_strict());
+        assert!(settings.notes_format().strict);
+        assert_eq!(serde_json::to_string(&settings).unwrap(), "{}");
+    }
+
+    #[test]
+    fn profile_config_is_next_to_the_default_one() {
+        let config = Path::new("project/config.json");
+        assert_eq!(profile_config_path(config, None), config);
+        assert_eq!(
+            profile_config_path(config, Some("work")),
+            Path::new("project/config.work.json")
+        );
+        assert_eq!(
+            profile_config_path(Path::new("config"), Some("work")),
+            Path::new("config.work")
+        );
+    }
+
+    #[test]
+    fn task_of_newer_schema_is_not_loaded() {
+        let dir = TempDir::new();
+        let config = serde_json::json!({
+            "author_name": "Anton Kopanov",
+            "author_contacts": "@ak",
+            "tasks": [{
+                "name": "t",
+                "code_file_name": "main.cpp",
+                "show_method": "Console",
+                "notes": dir.join("notes/t.txt"),
+                "schema": SUPPORTED_TASK_SCHEMA + 1,
+            }],
+        });
+        fs::write(dir.join("config.json"), config.to_string()).unwrap();
+        let err = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            dir.join("").display().to_string(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::UnsupportedTaskSchema.format(&[
+                &"t",
+                &(SUPPORTED_TASK_SCHEMA + 1),
+                &SUPPORTED_TASK_SCHEMA
+            ])
+        );
+    }
+
+    #[test]
+    fn config_error_shows_the_line() {
+        let dir = TempDir::new();
+        fs::write(
+            dir.join("config.json"),
+            "{\n  \"author_name\": 5,\n  \"tasks\": []\n}\n",
+        )
+        .unwrap();
+        let err = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            dir.join("").display().to_string(),
+            None,
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(&dir.join("config.json").display().to_string()));
+        assert!(message.ends_with("\"author_name\": 5,"), "{}", message);
+        assert_eq!(err.downcast_ref::<serde_json::Error>().unwrap().line(), 2);
+    }
+
+    #[test]
+    fn code_file_extension_is_checked() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        context.check_extension("Main.CPP").unwrap();
+        context.check_extension("solution.txt").unwrap();
+        context.settings.cli_strict = true;
+        assert!(context.check_extension("solution.txt").is_err());
+        context.settings.allowed_extensions.clear();
+        context.check_extension("solution.txt").unwrap();
+    }
+
+    #[test]
+    fn tasks_are_sorted_without_changing_the_config() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        context.tasks[0].deadline = Some("2024-05-01".to_string());
+        for (name, deadline) in [("b", None), ("a", Some("2024-03-01"))] {
+            let info = TaskInfo {
+                deadline: deadline.map(str::to_string),
+                ..TaskInfo::default()
+            };
+            context
+                .add_task(
+                    name.to_string(),
+                    "main.cpp".to_string(),
+                    ShowMethod::Console,
+                    info,
+                )
+                .unwrap();
+        }
+        context.tasks[0].last_reviewed = Some("2024-05-02T10:00:00Z".to_string());
+        context.tasks[2].last_reviewed = Some("2024-04-30T10:00:00Z".to_string());
+        let names = |order| {
+            context
+                .sorted_tasks(order)
+                .iter()
+                .map(|task| task.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(TaskOrder::Config), ["t", "b", "a"]);
+        assert_eq!(names(TaskOrder::Name), ["a", "b", "t"]);
+        assert_eq!(names(TaskOrder::Deadline), ["a", "t", "b"]);
+        // The reviews file of the unfinished review doesn't make the task reviewed
+        fs::write(dir.join("reviews/b.txt"), "Format: 2\n").unwrap();
+        assert_eq!(names(TaskOrder::Reviewed), ["b", "a", "t"]);
+        assert_eq!(context.tasks[0].name, "t");
+    }
+
+    #[test]
+    fn project_directory_is_resolved() {
+        let dir = TempDir::new();
+        project(&dir, serde_json::json!({}));
+        let context = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            dir.join("tasks/../.").display().to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(context.project_dir, fs::canonicalize(dir.join("")).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_project_directory_is_resolved() {
+        let dir = TempDir::new();
+        project(&dir, serde_json::json!({}));
+        let link = TempDir::new();
+        std::os::unix::fs::symlink(dir.join(""), link.join("project")).unwrap();
+        let context = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            link.join("project").display().to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(context.project_dir, fs::canonicalize(dir.join("")).unwrap());
+        assert_ne!(context.project_dir, link.join("project"));
+    }
+
+    #[test]
+    fn missing_project_directory_is_not_resolved() {
+        let dir = TempDir::new();
+        let missing = dir.join("missing");
+        let err = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            missing.display().to_string(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::CantResolveProjectDirectory.format(&[&missing.display()])
+        );
+    }
+
+    #[test]
+    fn home_is_expanded_at_the_start() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~").unwrap(), home);
+        assert_eq!(
+            expand_home("~/course/config.json").unwrap(),
+            home.join("course/config.json")
+        );
+        assert_eq!(expand_home("~user/x").unwrap(), Path::new("~user/x"));
+        assert_eq!(expand_home("dir/~").unwrap(), Path::new("dir/~"));
+    }
+
+    #[test]
+    fn stdin_config_is_written_to_stdout() {
+        let dir = TempDir::new();
+        let err = ProjectContext::load_state(
+            STDIO_CONFIG.to_string(),
+            dir.join("").display().to_string(),
+            Some("work"),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), Message::ProfileWithStdinConfig.text());
+
+        let mut context = project(&dir, serde_json::json!({}));
+        assert!(!context.is_stdio_config());
+        context.config_path = PathBuf::from(STDIO_CONFIG);
+        assert!(context.is_stdio_config());
+        let (path, text) = context.serialize().unwrap();
+        assert_eq!(path, Path::new(STDIO_CONFIG));
+        assert!(text.contains("\"author_name\": \"Anton Kopanov\""));
+    }
+
+    #[test]
+    fn assigned_task_is_checked_against_the_author() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        assert!(!context.is_mine(&context.tasks[0]));
+        context.check_assignment("t").unwrap();
+
+        for assigned_to in ["anton kopanov", "ak"] {
+            context.tasks[0].assigned_to = Some(assigned_to.to_string());
+            assert!(context.is_mine(&context.tasks[0]), "{}", assigned_to);
+        }
+        context.tasks[0].assigned_to = Some("II".to_string());
+        assert!(!context.is_mine(&context.tasks[0]));
+        context.check_assignment("t").unwrap();
+        context.settings.cli_strict = true;
+        assert_eq!(
+            context.check_assignment("t").unwrap_err().to_string(),
+            Message::TaskAssigned.format(&[&"t", &"II"])
+        );
+    }
+
+    #[test]
+    fn dry_run_of_init_creates_nothing() {
+        let dir = TempDir::new();
+        let init = |dry_run| {
+            ProjectContext::init_state_in(
+                dir.join(""),
+                "Anton Kopanov".to_string(),
+                "@ak".to_string(),
+                Some("work"),
+                dry_run,
+            )
+        };
+        init(true).unwrap();
+        assert!(!dir.join("config.work.json").exists());
+        assert!(!dir.join("reviews").exists());
+
+        init(false).unwrap();
+        for name in ["config.work.json", "reviews", "tasks", "notes"] {
+            assert!(dir.join(name).exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn review_time_is_saved_in_the_config() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        assert!(context.mark_reviewed("missing").is_err());
+        context.mark_reviewed("t").unwrap();
+        context.dump_state().unwrap();
+
+        let context = ProjectContext::load_state(
+            dir.join("config.json").display().to_string(),
+            dir.join("").display().to_string(),
+            None,
+        )
+        .unwrap();
+        let last_reviewed = context.tasks[0].last_reviewed.as_deref().unwrap();
+        humantime::parse_rfc3339(last_reviewed).unwrap();
+    }
+
+    #[test]
+    fn unknown_task_fields_are_kept_and_rejected_only_in_the_strict_mode() {
+        let dir = TempDir::new();
+        project(&dir, serde_json::json!({}));
+        let config_path = dir.join("config.json");
+        let mut config: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        config["tasks"][0]["future_field"] = serde_json::json!({"x": 1});
+        fs::write(&config_path, config.to_string()).unwrap();
+        let load = || {
+            ProjectContext::load_state(
+                config_path.display().to_string(),
+                dir.join("").display().to_string(),
+                None,
+            )
+            .unwrap()
+        };
+        let mut context = load();
+        context.check_unknown_fields().unwrap();
+        context.settings.cli_strict = true;
+        let err = context.check_unknown_fields().unwrap_err();
+        assert!(err.to_string().contains("future_field"), "{}", err);
+        context.dump_state().unwrap();
+        let context = load();
+        assert_eq!(
+            context.tasks[0].unknown_fields["future_field"],
+            serde_json::json!({"x": 1})
+        );
+    }
+
+    #[test]
+    fn schema_describes_the_config_and_its_url_is_kept() {
+        let schema = serde_json::to_value(ProjectContext::config_schema()).unwrap();
+        let properties = &schema["properties"];
+        for field in ["$schema", "author_name", "tasks", "deduplicate_notes"] {
+            assert!(properties.get(field).is_some(), "{}", field);
+        }
+        let dir = TempDir::new();
+        let url = "https://example.com/assist_tool.schema.json";
+        project(&dir, serde_json::json!({"$schema": url}))
+            .dump_state()
+            .unwrap();
+        let config: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("config.json")).unwrap()).unwrap();
+        assert_eq!(config["$schema"], url);
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::Display;
+
+use anyhow::{bail, ensure, Context};
+use encoding_rs::{Encoding, UTF_8};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+
+const UTF_8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Encoding of the files written by the tool
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct OutputEncoding {
+    encoding: &'static Encoding,
+    bom: bool,
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl OutputEncoding {
+    /// Encode the text, warns if some characters can't be represented
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let (bytes, _, had_errors) = self.encoding.encode(text);
+        if had_errors {
+            warn!("{}", Message::CharactersReplaced.format(&[&self]));
+        }
+        if self.bom {
+            [UTF_8_BOM, &bytes].concat()
+        } else {
+            bytes.into_owned()
+        }
+    }
+
+    /// Decode the file written in this encoding, the UTF-8 BOM is skipped in any case
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        let (text, had_errors) = match bytes.strip_prefix(UTF_8_BOM) {
+            Some(bytes) => UTF_8.decode_without_bom_handling(bytes),
+            None => self.encoding.decode_without_bom_handling(bytes),
+        };
+        ensure!(
+            !had_errors,
+            "{}",
+            Message::TextNotInEncoding.format(&[&self.encoding.name().to_lowercase()])
+        );
+        Ok(text.into_owned())
+    }
+}
+
+impl Display for OutputEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bom {
+            write!(f, "{}-bom", self.encoding.name().to_lowercase())
+        } else {
+            write!(f, "{}", self.encoding.name().to_lowercase())
+        }
+    }
+}
+
+impl TryFrom<String> for OutputEncoding {
+    type Error = anyhow::Error;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        let label = label.trim().to_lowercase();
+        let (label, bom) = match label.strip_suffix("-bom") {
+            Some(label) => (label, true),
+            None => (label.as_str(), false),
+        };
+        let encoding = Encoding::for_label(label.as_bytes())
+            .with_context(|| Message::UnknownOutputEncoding.format(&[&label]))?;
+        if encoding.output_encoding() != encoding {
+            bail!(
+                "{}",
+                Message::UnsupportedEncoding.format(&[&encoding.name()])
+            );
+        }
+        if bom && encoding != UTF_8 {
+            bail!(Message::BomOnlyForUtf8.text());
+        }
+        Ok(Self { encoding, bom })
+    }
+}
+
+impl From<OutputEncoding> for String {
+    fn from(value: OutputEncoding) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(label: &str) -> OutputEncoding {
+        OutputEncoding::try_from(label.to_string()).unwrap()
+    }
+
+    #[test]
+    fn text_round_trips_in_every_encoding() {
+        let text = "1) Имя переменной\n\tmain.cpp: 3\n";
+        for label in ["utf-8", "utf-8-bom", "windows-1251", "koi8-r"] {
+            let encoding = encoding(label);
+            let bytes = encoding.encode(text);
+            assert_eq!(encoding.decode(&bytes).unwrap(), text, "{}", label);
+        }
+    }
+
+    #[test]
+    fn bom_is_written_and_skipped() {
+        let bytes = encoding("utf-8-bom").encode("текст");
+        assert!(bytes.starts_with(UTF_8_BOM));
+        assert_eq!(OutputEncoding::default().decode(&bytes).unwrap(), "текст");
+    }
+
+    #[test]
+    fn wrong_encoding_is_an_error() {
+        let bytes = encoding("windows-1251").encode("текст");
+        assert!(OutputEncoding::default().decode(&bytes).is_err());
+    }
+}
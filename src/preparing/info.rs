@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use super::context::ProjectContext;
+use crate::messages::Message;
+
+/// Version of the project info JSON, increased on incompatible changes
+const SCHEMA_VERSION: u32 = 1;
+
+/// Project structure for editor integrations
+#[derive(Debug, Serialize)]
+pub(crate) struct ProjectInfo {
+    schema_version: u32,
+    project_dir: PathBuf,
+    config_path: PathBuf,
+    tasks_dir: PathBuf,
+    notes_dir: PathBuf,
+    reviews_dir: PathBuf,
+    tasks: Vec<TaskInfoJson>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskInfoJson {
+    name: String,
+    code_files: Vec<PathBuf>,
+    notes_file: PathBuf,
+    reviews_file: PathBuf,
+    necessary_notes: usize,
+    optional_notes: usize,
+    /// The review was completed at least once, an unfinished or dropped one doesn't count
+    reviewed: bool,
+    /// Time of the last completed review, `null` if it was never completed
+    last_reviewed: Option<String>,
+}
+
+impl ProjectInfo {
+    /// Collect the info, template notes of all tasks are read
+    pub(crate) fn collect(context: &mut ProjectContext) -> anyhow::Result<Self> {
+        let format = context.settings.notes_format();
+        let mut tasks = Vec::new();
+        for task in &mut context.tasks {
+            task.load_notes(format.clone())
+                .with_context(|| Message::CantLoadNotesOfTask.format(&[&task.name]))?;
+            let (necessary_notes, optional_notes) = task.notes_count();
+            let reviews_file = task.reviews_file_path(&context.project_dir);
+            tasks.push(TaskInfoJson {
+                name: task.name.clone(),
+                code_files: task.code_files(&context.project_dir),
+                notes_file: task.notes_file_path().to_owned(),
+                reviewed: task.last_reviewed.is_some(),
+                reviews_file,
+                necessary_notes,
+                optional_notes,
+                last_reviewed: task.last_reviewed.clone(),
+            });
+        }
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            project_dir: context.project_dir.clone(),
+            config_path: if context.is_stdio_config() {
+                context.config_path().to_owned()
+            } else {
+                std::fs::canonicalize(context.config_path())
+                    .context(Message::CantResolveConfigPath.text())?
+            },
+            tasks_dir: context.project_dir.join("tasks"),
+            notes_dir: context.project_dir.join("notes"),
+            reviews_dir: context.project_dir.join("reviews"),
+            tasks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preparing::notes::FORMAT_HEADER;
+    use crate::testing::{project, TempDir};
+
+    #[test]
+    fn tasks_are_described_in_json() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        let notes = format!("{}\nOptional:\n0) Use const\n", FORMAT_HEADER);
+        std::fs::write(dir.join("notes/t.txt"), notes).unwrap();
+        std::fs::write(dir.join("reviews/t.txt"), format!("{}\n", FORMAT_HEADER)).unwrap();
+
+        let info = serde_json::to_value(ProjectInfo::collect(&mut context).unwrap()).unwrap();
+        assert_eq!(info["schema_version"], SCHEMA_VERSION);
+        assert_eq!(
+            info["reviews_dir"],
+            serde_json::json!(context.project_dir.join("reviews"))
+        );
+        let task = &info["tasks"][0];
+        assert_eq!(task["name"], "t");
+        assert_eq!(task["necessary_notes"], 0);
+        assert_eq!(task["optional_notes"], 1);
+        // The header is written by the review left without notes too
+        assert_eq!(task["reviewed"], false);
+        assert_eq!(task["last_reviewed"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn completed_review_is_reviewed() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        context.mark_reviewed("t").unwrap();
+        let info = serde_json::to_value(ProjectInfo::collect(&mut context).unwrap()).unwrap();
+        assert_eq!(info["tasks"][0]["reviewed"], true);
+        assert!(info["tasks"][0]["last_reviewed"].is_string());
+    }
+}
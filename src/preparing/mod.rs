@@ -2,3 +2,6 @@
 pub mod context;
 pub mod task;
 pub mod notes;
+pub mod encoding;
+pub mod info;
+pub mod archive;
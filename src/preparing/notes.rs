@@ -1,24 +1,59 @@
-use anyhow::{ensure, Context};
-use log::trace;
+use anyhow::{bail, ensure, Context};
+use log::{info, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fmt::Debug;
+use std::fs;
 use std::fs::File;
-use std::io::{BufRead, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::encoding::OutputEncoding;
+use crate::messages::Message;
+
+/// First line of the notes file in the current format
+pub(crate) const FORMAT_HEADER: &str = "Format: 2";
+pub(crate) const NECESSARY_HEADER: &str = "Necessary:";
+pub(crate) const OPTIONAL_HEADER: &str = "Optional:";
+
+pub(crate) trait Note: Debug + Clone {
+    /// Read the note back from its text in the notes file
+    fn parse(text: String, format: &NotesFormat) -> anyhow::Result<Self>;
 
-pub trait Note: Debug + Clone + From<String> {
     fn text(&self) -> String;
+
+    /// Text written to the notes file, it may keep more than the shown text to read the note back
+    fn stored_text(&self) -> String {
+        self.text()
+    }
+
+    /// Append the content of the other note to this one
+    fn merge(&mut self, other: Self);
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(try_from = "String", into = "String")]
-pub struct FileNotesStorage<N: Note, O: Note> {
+#[serde(from = "String", into = "String")]
+pub(crate) struct FileNotesStorage<N: Note, O: Note> {
     /// Path to the display and store notes
     file_name: PathBuf,
 
     necessary_notes: Vec<N>,
 
     optional_notes: Vec<O>,
+
+    /// Skip notes which are already in the storage
+    deduplicate: bool,
+
+    /// Encoding of the file with notes
+    encoding: OutputEncoding,
+
+    format: NotesFormat,
+
+    /// Only mark the notes as changed on save, they are written by `flush`
+    deferred: bool,
+
+    /// Notes were changed after the last write
+    dirty: Cell<bool>,
 }
 
 impl<N: Note, O: Note> From<FileNotesStorage<N, O>> for String {
@@ -28,33 +63,192 @@ impl<N: Note, O: Note> From<FileNotesStorage<N, O>> for String {
 }
 
 impl<N: Note, O: Note> FileNotesStorage<N, O> {
-    pub(crate) fn new(file_name: PathBuf) -> anyhow::Result<Self> {
-        file_name.try_into()
+    /// Open the file with notes, creating it if needed
+    pub(crate) fn new(file_name: PathBuf, format: NotesFormat) -> anyhow::Result<Self> {
+        Self::open(file_name, format, OutputEncoding::default())
+    }
+
+    /// Open the file with notes written in the encoding, creating it if needed
+    pub(crate) fn open(
+        file_name: PathBuf,
+        format: NotesFormat,
+        encoding: OutputEncoding,
+    ) -> anyhow::Result<Self> {
+        let mut storage = Self::with_notes(file_name, Vec::new(), Vec::new());
+        storage.format = format;
+        storage.encoding = encoding;
+        storage.load()?;
+        Ok(storage)
+    }
+
+    /// Read notes from the file in the given format
+    pub(crate) fn load_with_format(&mut self, format: NotesFormat) -> anyhow::Result<()> {
+        self.format = format;
+        self.load()
+    }
+
+    fn load(&mut self) -> anyhow::Result<()> {
+        trace!("Try to open file: {:?}", &self.file_name);
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.file_name)
+            .context(Message::CantOpenFileForNotes.text())?;
+        trace!("File opened: {:?}", file);
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .context(Message::CantReadFileForNotes.text())?;
+        let text = self.encoding.decode(&bytes)?;
+        let (necessary_notes, optional_notes) = Self::parse(text.as_bytes(), &self.format)?;
+        self.necessary_notes = necessary_notes;
+        self.optional_notes = optional_notes;
+        Ok(())
+    }
+
+    fn with_notes(file_name: PathBuf, necessary_notes: Vec<N>, optional_notes: Vec<O>) -> Self {
+        Self {
+            file_name,
+            necessary_notes,
+            optional_notes,
+            deduplicate: false,
+            encoding: OutputEncoding::default(),
+            format: NotesFormat::default(),
+            deferred: false,
+            dirty: Cell::new(false),
+        }
+    }
+
+    pub(crate) fn format(&self) -> &NotesFormat {
+        &self.format
+    }
+
+    pub(crate) fn file_name(&self) -> &Path {
+        &self.file_name
+    }
+
+    pub(crate) fn set_deduplicate(&mut self, deduplicate: bool) {
+        self.deduplicate = deduplicate;
+    }
+
+    fn contains<T: Note>(notes: &[T], note: &T) -> bool {
+        let text = note.text();
+        notes.iter().any(|other| other.text() == text)
     }
 
-    fn parse_line(line: &str) -> anyhow::Result<(usize, &str)> {
-        let index = line.find(')').context("Incorrect line")?;
-        let num_str = &line[..index];
-        let num = num_str.parse::<usize>().context("Incorrect number")?;
-        Ok((num, line[index + 1..].trim()))
+    /// Parse notes from the text of the notes file
+    pub(crate) fn parse_text(text: &str, format: &NotesFormat) -> anyhow::Result<(Vec<N>, Vec<O>)> {
+        Self::parse(text.as_bytes(), format)
     }
 
-    pub(crate) fn add_note(&mut self, note: N) {
+    /// Parse notes in the current format or in the legacy one, see `read_written_notes`
+    fn parse(reader: impl BufRead, format: &NotesFormat) -> anyhow::Result<(Vec<N>, Vec<O>)> {
+        let (necessary_notes, optional_notes) = read_written_notes(reader, format)?;
+        Self::from_written(necessary_notes, optional_notes, format)
+    }
+
+    /// Parse the notes read from the file, they are renumbered in their order
+    pub(crate) fn from_written(
+        necessary_notes: Vec<WrittenNote>,
+        optional_notes: Vec<WrittenNote>,
+        format: &NotesFormat,
+    ) -> anyhow::Result<(Vec<N>, Vec<O>)> {
+        let necessary_notes = necessary_notes
+            .into_iter()
+            .enumerate()
+            .map(|(index, note)| {
+                note.check_number(index, false, format)?;
+                N::parse(note.text, format)
+                    .with_context(|| Message::CantReadNote.format(&[&format.ids.id(index, false)]))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let optional_notes = optional_notes
+            .into_iter()
+            .enumerate()
+            .map(|(index, note)| {
+                note.check_number(index, true, format)?;
+                O::parse(note.text, format)
+                    .with_context(|| Message::CantReadNote.format(&[&format.ids.id(index, true)]))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok((necessary_notes, optional_notes))
+    }
+
+    /// Add necessary note, returns false if the note was skipped as a duplicate
+    pub(crate) fn add_note(&mut self, note: N) -> bool {
+        if self.deduplicate && Self::contains(&self.necessary_notes, &note) {
+            trace!("Duplicate note skipped: {:?}", note);
+            return false;
+        }
         self.necessary_notes.push(note);
         self.save().expect("Can't save notes");
+        true
     }
 
-    pub(crate) fn add_optional_note(&mut self, note: O) {
+    /// Add optional note, returns false if the note was skipped as a duplicate
+    pub(crate) fn add_optional_note(&mut self, note: O) -> bool {
+        if self.deduplicate && Self::contains(&self.optional_notes, &note) {
+            trace!("Duplicate note skipped: {:?}", note);
+            return false;
+        }
         self.optional_notes.push(note);
         self.save().expect("Can't save notes");
+        true
+    }
+
+    pub(crate) fn necessary_notes(&self) -> &[N] {
+        &self.necessary_notes
+    }
+
+    pub(crate) fn optional_notes(&self) -> &[O] {
+        &self.optional_notes
     }
 
     pub(crate) fn find_note(&self, num: usize) -> anyhow::Result<&N> {
-        self.necessary_notes.get(num).context("Note not found")
+        self.necessary_notes
+            .get(num)
+            .context(Message::NoteNotFound.text())
     }
 
     pub(crate) fn find_optional_note(&self, num: usize) -> anyhow::Result<&O> {
-        self.optional_notes.get(num).context("Note not found")
+        self.optional_notes
+            .get(num)
+            .context(Message::NoteNotFound.text())
+    }
+
+    fn merge_in<T: Note>(notes: &mut Vec<T>, first: usize, second: usize) -> anyhow::Result<()> {
+        ensure!(first != second, Message::CantMergeNoteWithItself.text());
+        ensure!(
+            first < notes.len(),
+            "{}",
+            Message::NumberedNoteNotFound.format(&[&first])
+        );
+        ensure!(
+            second < notes.len(),
+            "{}",
+            Message::NumberedNoteNotFound.format(&[&second])
+        );
+        let other = notes.remove(second);
+        let first = if second < first { first - 1 } else { first };
+        notes[first].merge(other);
+        Ok(())
+    }
+
+    /// Merge the second note into the first one and remove the second
+    pub(crate) fn merge_notes(
+        &mut self,
+        first: usize,
+        second: usize,
+        optional: bool,
+    ) -> anyhow::Result<()> {
+        if optional {
+            Self::merge_in(&mut self.optional_notes, first, second)?;
+        } else {
+            Self::merge_in(&mut self.necessary_notes, first, second)?;
+        }
+        self.save()
     }
 
     pub fn clear(&mut self) {
@@ -63,112 +257,734 @@ impl<N: Note, O: Note> FileNotesStorage<N, O> {
         self.save().expect("Can't save notes");
     }
 
+    pub(crate) fn set_deferred(&mut self, deferred: bool) {
+        self.deferred = deferred;
+    }
+
     pub(crate) fn save(&self) -> anyhow::Result<()> {
-        let file = File::create(&self.file_name).context("Can't create file")?;
+        if self.deferred {
+            self.dirty.set(true);
+            return Ok(());
+        }
+        self.write()
+    }
 
-        let mut writer = std::io::BufWriter::new(file);
+    /// Write the changes postponed by the deferred saving
+    pub(crate) fn flush(&self) -> anyhow::Result<()> {
+        if self.dirty.get() {
+            self.write()?;
+        }
+        Ok(())
+    }
 
-        self.save_with_writer(&mut writer)
+    fn write(&self) -> anyhow::Result<()> {
+        self.dirty.set(false);
+        let mut text = Vec::new();
+        writeln!(text, "{}", FORMAT_HEADER)?;
+        write_notes(
+            &mut text,
+            &self.format,
+            true,
+            &self.necessary_notes,
+            &self.optional_notes,
+        )?;
+        let text = String::from_utf8(text).context(Message::NotesNotUtf8.text())?;
+
+        fs::write(&self.file_name, self.encoding.encode(&text))
+            .context(Message::CantWriteNotesFile.text())
     }
 
     pub(crate) fn save_with_writer(&self, writer: &mut impl Write) -> anyhow::Result<()> {
-        if !self.necessary_notes.is_empty() {
-            writeln!(writer, "Necessary:")?;
-            for (num, note) in self.necessary_notes.iter().enumerate() {
-                writeln!(writer, "{}) {}", num, note.text())?;
+        write_notes(
+            writer,
+            &self.format,
+            false,
+            &self.necessary_notes,
+            &self.optional_notes,
+        )
+    }
+}
+
+impl<T: Note> FileNotesStorage<T, T> {
+    /// All notes, necessary first
+    pub(crate) fn notes(&self) -> impl Iterator<Item = &T> {
+        self.necessary_notes
+            .iter()
+            .chain(self.optional_notes.iter())
+    }
+
+    pub(crate) fn notes_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.necessary_notes
+            .iter_mut()
+            .chain(self.optional_notes.iter_mut())
+    }
+
+    /// Mutable note by its number in the necessary or optional list
+    pub(crate) fn note_mut(&mut self, num: usize, optional: bool) -> anyhow::Result<&mut T> {
+        let notes = if optional {
+            &mut self.optional_notes
+        } else {
+            &mut self.necessary_notes
+        };
+        notes
+            .get_mut(num)
+            .with_context(|| Message::NumberedNoteNotFound.format(&[&num]))
+    }
+
+    /// Move the optional note to the end of the necessary notes
+    pub(crate) fn promote_note(&mut self, num: usize) -> anyhow::Result<()> {
+        ensure!(
+            num < self.optional_notes.len(),
+            "{}",
+            Message::NumberedNoteNotFound.format(&[&num])
+        );
+        let note = self.optional_notes.remove(num);
+        self.necessary_notes.push(note);
+        self.save()
+    }
+
+    /// Move the necessary note to the end of the optional notes
+    pub(crate) fn demote_note(&mut self, num: usize) -> anyhow::Result<()> {
+        ensure!(
+            num < self.necessary_notes.len(),
+            "{}",
+            Message::NumberedNoteNotFound.format(&[&num])
+        );
+        let note = self.necessary_notes.remove(num);
+        self.optional_notes.push(note);
+        self.save()
+    }
+}
+
+/// Note as it is written in the notes file, before it's renumbered and parsed
+#[derive(Debug, Clone)]
+pub(crate) struct WrittenNote {
+    /// Number of the note in the file
+    pub num: usize,
+    /// Line of the file the note starts at
+    pub line: usize,
+    pub text: String,
+}
+
+impl WrittenNote {
+    /// Hand-edited files may have gaps or the wrong order, the notes are renumbered unless the format is strict
+    fn check_number(
+        &self,
+        index: usize,
+        optional: bool,
+        format: &NotesFormat,
+    ) -> anyhow::Result<()> {
+        if self.num != index {
+            ensure!(
+                !format.strict,
+                "{}",
+                Message::IncorrectNoteNumberAtLine.format(&[
+                    &self.line,
+                    &format.ids.id(self.num, optional),
+                    &format.ids.id(index, optional)
+                ])
+            );
+            warn!(
+                "{}",
+                Message::NoteRenumbered.format(&[
+                    &format.ids.id(self.num, optional),
+                    &self.line,
+                    &format.ids.id(index, optional)
+                ])
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Read the notes in the current format or in the legacy one as they are written, without renumbering.
+///
+/// The current format starts with the format header. In both formats the lines which don't start a new note
+/// continue the previous one, e.g. the reference blocks, but the legacy format allows the indented notes.
+pub(crate) fn read_written_notes(
+    reader: impl BufRead,
+    format: &NotesFormat,
+) -> anyhow::Result<(Vec<WrittenNote>, Vec<WrittenNote>)> {
+    let mut lines = reader.lines();
+    let first_line = match lines.next() {
+        None => {
+            trace!("Empty file");
+            return Ok((Vec::new(), Vec::new()));
+        }
+        Some(first_line) => first_line.context(Message::FirstLineReadProblem.text())?,
+    };
+    let multiline = first_line == FORMAT_HEADER;
+    if !multiline {
+        info!("Notes are in the legacy format, they will be upgraded on the next save");
+    }
+
+    let mut necessary_notes = Vec::<WrittenNote>::new();
+    let mut optional_notes = Vec::<WrittenNote>::new();
+    let mut optional = None;
+    let legacy_first_line = (!multiline).then_some(Ok(first_line));
+    for (index, res) in legacy_first_line.into_iter().chain(lines).enumerate() {
+        let line_number = index + if multiline { 2 } else { 1 };
+        let raw_line = res?;
+        let line = if multiline {
+            raw_line.as_str()
+        } else {
+            raw_line.trim()
+        };
+        match line.trim() {
+            NECESSARY_HEADER => {
+                optional = Some(false);
+                continue;
             }
+            OPTIONAL_HEADER => {
+                optional = Some(true);
+                continue;
+            }
+            _ => {}
         }
-        if !self.optional_notes.is_empty() {
-            writeln!(writer, "Optional:")?;
-            for (num, note) in self.optional_notes.iter().enumerate() {
-                writeln!(writer, "{}) {}", num, note.text())?;
+        let notes = match optional {
+            None => bail!(
+                "{}",
+                Message::IncorrectSectionHeader.format(&[
+                    &line_number,
+                    &NECESSARY_HEADER,
+                    &OPTIONAL_HEADER
+                ])
+            ),
+            Some(false) => &mut necessary_notes,
+            Some(true) => &mut optional_notes,
+        };
+        // Notes written before the note format was changed are still readable
+        let is_optional = optional == Some(true);
+        let parsed = format.parse_line(line, is_optional).or_else(|err| {
+            NotesFormat::default()
+                .parse_line(line, is_optional)
+                .map_err(|_| err)
+        });
+        match (parsed, notes.last_mut()) {
+            (Ok((num, text)), _) => notes.push(WrittenNote {
+                num,
+                line: line_number,
+                text: text.to_string(),
+            }),
+            (Err(_), Some(last)) => {
+                last.text.push('\n');
+                last.text.push_str(if multiline {
+                    &raw_line
+                } else {
+                    raw_line.trim_end()
+                });
+            }
+            (Err(_), None) if line.trim().is_empty() => {}
+            (Err(err), _) => {
+                return Err(err.context(Message::CantParseLine.format(&[&line_number])))
             }
         }
-        writer.flush()?;
-        Ok(())
     }
+    trace!("Notes read");
+    Ok((necessary_notes, optional_notes))
 }
 
-impl<N: Note, O: Note> TryFrom<String> for FileNotesStorage<N, O> {
-    type Error = anyhow::Error;
+/// Write the necessary and optional sections of notes without the format header,
+/// `stored` notes are written with their `stored_text`
+pub(crate) fn write_notes<N: Note, O: Note>(
+    writer: &mut impl Write,
+    format: &NotesFormat,
+    stored: bool,
+    necessary_notes: &[N],
+    optional_notes: &[O],
+) -> anyhow::Result<()> {
+    for optional in format.section_order() {
+        if optional {
+            write_section(
+                writer,
+                format,
+                OPTIONAL_HEADER,
+                true,
+                stored,
+                optional_notes,
+            )?;
+        } else {
+            write_section(
+                writer,
+                format,
+                NECESSARY_HEADER,
+                false,
+                stored,
+                necessary_notes,
+            )?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the header and the numbered notes, nothing if there are no notes
+pub(crate) fn write_section<T: Note>(
+    writer: &mut impl Write,
+    format: &NotesFormat,
+    header: &str,
+    optional: bool,
+    stored: bool,
+    notes: &[T],
+) -> anyhow::Result<()> {
+    if !notes.is_empty() {
+        writeln!(writer, "{}", header)?;
+        for (num, note) in notes.iter().enumerate() {
+            let text = if stored {
+                note.stored_text()
+            } else {
+                note.text()
+            };
+            writeln!(writer, "{}", format.render_line(num, optional, &text))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write notes split into groups in the order of their first appearance, keeping numbers
+pub(crate) fn write_grouped_notes<T: Note>(
+    writer: &mut impl Write,
+    format: &NotesFormat,
+    necessary_notes: &[T],
+    optional_notes: &[T],
+    group: impl Fn(&T) -> String,
+) -> anyhow::Result<()> {
+    let groups = necessary_notes
+        .iter()
+        .chain(optional_notes)
+        .map(&group)
+        .fold(Vec::new(), |mut groups, name| {
+            if !groups.contains(&name) {
+                groups.push(name);
+            }
+            groups
+        });
+    for name in groups {
+        writeln!(writer, "{}:", name)?;
+        for optional in format.section_order() {
+            let (header, notes) = if optional {
+                (OPTIONAL_HEADER, optional_notes)
+            } else {
+                (NECESSARY_HEADER, necessary_notes)
+            };
+            let mut notes = notes
+                .iter()
+                .enumerate()
+                .filter(|(_, note)| group(note) == name)
+                .peekable();
+            if notes.peek().is_none() {
+                continue;
+            }
+            writeln!(writer, "{}", header)?;
+            for (num, note) in notes {
+                writeln!(
+                    writer,
+                    "{}",
+                    format.render_line(num, optional, &note.text())
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+impl<N: Note, O: Note> From<String> for FileNotesStorage<N, O> {
+    /// Notes are not read here, call `load` after the format is known
+    fn from(file_name: String) -> Self {
+        Self::with_notes(PathBuf::from(file_name), Vec::new(), Vec::new())
+    }
+}
 
-    fn try_from(file_name: String) -> Result<Self, Self::Error> {
-        let file_name = PathBuf::from(file_name);
-        FileNotesStorage::try_from(file_name)
+/// Template of a note line with `{n}` and `{text}` placeholders, e.g. `{n}) {text}`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct NoteTemplate {
+    prefix: String,
+    separator: String,
+    suffix: String,
+}
+
+impl Default for NoteTemplate {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            separator: ") ".to_string(),
+            suffix: String::new(),
+        }
     }
 }
 
-impl<N: Note, O: Note> TryFrom<PathBuf> for FileNotesStorage<N, O> {
+impl NoteTemplate {
+    fn render(&self, id: &str, text: &str) -> String {
+        format!(
+            "{}{}{}{}{}",
+            self.prefix, id, self.separator, text, self.suffix
+        )
+    }
+
+    /// Id of the note and its text
+    fn parse<'a>(&self, line: &'a str) -> anyhow::Result<(&'a str, &'a str)> {
+        let rest = line
+            .strip_prefix(&self.prefix)
+            .context(Message::IncorrectLineStart.text())?;
+        let id_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        ensure!(id_len > 0, Message::NoNoteNumber.text());
+        let id = &rest[..id_len];
+        let rest = &rest[id_len..];
+        // Trailing spaces of the separator may be trimmed together with the empty text
+        let separator = self.separator.trim_end();
+        let rest = rest
+            .strip_prefix(separator)
+            .context(Message::IncorrectLine.text())?;
+        let rest = rest
+            .strip_prefix(&self.separator[separator.len()..])
+            .unwrap_or(rest);
+        let text = rest.strip_suffix(&self.suffix).unwrap_or(rest);
+        Ok((id, text.trim()))
+    }
+}
+
+impl TryFrom<String> for NoteTemplate {
     type Error = anyhow::Error;
 
-    fn try_from(file_name: PathBuf) -> Result<Self, Self::Error> {
-        trace!("Try to open file: {:?}", &file_name);
-        let file = File::options()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(&file_name)
-            .context("Can't open file for notes")?;
-        trace!("File opened: {:?}", file);
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        let (prefix, rest) = template
+            .split_once("{n}")
+            .context(Message::NoteFormatWithoutNumber.text())?;
+        let (separator, suffix) = rest
+            .split_once("{text}")
+            .context(Message::NoteFormatWithoutText.text())?;
+        ensure!(
+            !separator.trim().is_empty(),
+            Message::NoteFormatWithoutSeparator.text()
+        );
+        Ok(Self {
+            prefix: prefix.to_string(),
+            separator: separator.to_string(),
+            suffix: suffix.to_string(),
+        })
+    }
+}
 
-        let mut necessary_notes = Vec::new();
-        let mut optional_notes = Vec::new();
-
-        let mut lines = std::io::BufReader::new(file).lines();
-        match lines.next() {
-            None => {
-                trace!("Empty file");
-                return Ok(FileNotesStorage {
-                    file_name,
-                    necessary_notes,
-                    optional_notes,
-                });
-            }
-            Some(first_line) => {
-                ensure!(
-                    first_line.context("First line read problem")? == "Necessary:",
-                    "First line should be 'Necessary:'"
-                );
-            }
+impl From<NoteTemplate> for String {
+    fn from(value: NoteTemplate) -> Self {
+        format!(
+            "{}{{n}}{}{{text}}{}",
+            value.prefix, value.separator, value.suffix
+        )
+    }
+}
+
+/// Line numbers before the quoted code
+#[derive(Debug, Clone)]
+pub(crate) struct Gutter {
+    /// Text between the line number and the code
+    pub separator: String,
+    pub line_numbers: bool,
+    /// Directory to print the `file:line` links before references relative to, no links if not set
+    pub link_base: Option<PathBuf>,
+}
+
+impl Default for Gutter {
+    fn default() -> Self {
+        Self {
+            separator: ": ".to_string(),
+            line_numbers: true,
+            link_base: None,
+        }
+    }
+}
+
+impl Gutter {
+    /// Text before the quoted line, empty without the line numbers
+    pub fn prefix(&self, row: usize) -> String {
+        if self.line_numbers {
+            self.number(row)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Line number with the separator, even if the line numbers are not quoted
+    pub fn number(&self, row: usize) -> String {
+        format!("{:4}{}", row, self.separator)
+    }
+}
+
+/// How notes are written to and read from the files
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NotesFormat {
+    pub template: NoteTemplate,
+    pub ids: NoteIds,
+    /// Fail on wrong numbers of notes instead of renumbering them
+    pub strict: bool,
+    /// Write the optional notes before the necessary ones, both orders are read
+    pub optional_first: bool,
+    /// Line numbers of the code quoted in the notes
+    pub gutter: Gutter,
+}
+
+impl NotesFormat {
+    /// Whether each written section is optional, in the order of writing
+    pub fn section_order(&self) -> [bool; 2] {
+        if self.optional_first {
+            [true, false]
+        } else {
+            [false, true]
+        }
+    }
+
+    fn render_line(&self, index: usize, optional: bool, text: &str) -> String {
+        self.template.render(&self.ids.id(index, optional), text)
+    }
+
+    /// Index of the note and its text
+    fn parse_line<'a>(&self, line: &'a str, optional: bool) -> anyhow::Result<(usize, &'a str)> {
+        let (id, text) = self.template.parse(line)?;
+        Ok((self.ids.index(id, optional)?, text))
+    }
+}
+
+/// How the notes are numbered, e.g. `0`, `1` or `N1`, `O1`
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct NoteIds {
+    /// Write `N` before the numbers of necessary notes and `O` before the optional ones
+    pub prefixed: bool,
+    /// Number of the first note
+    pub first: usize,
+}
+
+impl NoteIds {
+    pub fn id(&self, index: usize, optional: bool) -> String {
+        match (self.prefixed, optional) {
+            (false, _) => (self.first + index).to_string(),
+            (true, false) => format!("N{}", self.first + index),
+            (true, true) => format!("O{}", self.first + index),
+        }
+    }
+
+    /// Bucket of the prefixed id: `Some(true)` for optional, `None` if there is no prefix
+    pub fn optional(&self, id: &str) -> Option<bool> {
+        match id.chars().next()?.to_ascii_uppercase() {
+            'N' if self.prefixed => Some(false),
+            'O' if self.prefixed => Some(true),
+            _ => None,
+        }
+    }
+
+    /// The word looks like a note id rather than a text
+    pub fn is_id(&self, word: &str) -> bool {
+        let num = match self.optional(word) {
+            Some(_) => &word[1..],
+            None => word,
         };
-        trace!("First line checked");
-
-        let mut optional = false;
-        for (num, res) in (&mut lines).enumerate() {
-            let line = res?.trim().to_string();
-            if line == "Optional:" {
-                optional = true;
-                break;
+        !num.is_empty() && num.chars().all(|c| c.is_ascii_digit())
+    }
+
+    /// Index of the note in its list by the id, the prefix may be omitted
+    pub fn index(&self, id: &str, optional: bool) -> anyhow::Result<usize> {
+        let num = match self.optional(id) {
+            Some(id_optional) => {
+                let message = if id_optional {
+                    Message::NoteIsOptional
+                } else {
+                    Message::NoteIsNecessary
+                };
+                ensure!(id_optional == optional, "{}", message.format(&[&id]));
+                &id[1..]
             }
-            let (parsed_num, line) = Self::parse_line(&line)?;
-            ensure!(parsed_num == num, "Incorrect number of line");
+            None => id,
+        };
+        let num = num
+            .parse::<usize>()
+            .with_context(|| Message::IncorrectNoteNumber.format(&[&id]))?;
+        ensure!(
+            num >= self.first,
+            "{}",
+            Message::NotesAreNumberedFrom.format(&[&self.first])
+        );
+        Ok(num - self.first)
+    }
+}
 
-            necessary_notes.push(line.to_string().into());
-        }
-        trace!("Not optional notes read");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preparing::task::TaskNode;
+    use crate::testing::TempDir;
 
-        if !optional {
-            return Ok(FileNotesStorage {
-                file_name,
-                necessary_notes,
-                optional_notes,
-            });
+    fn storage(dir: &TempDir) -> FileNotesStorage<TaskNode, TaskNode> {
+        FileNotesStorage::new(dir.join("notes.txt"), NotesFormat::default()).unwrap()
+    }
+
+    fn note(text: &str) -> TaskNode {
+        TaskNode::from(text.to_string())
+    }
+
+    #[test]
+    fn duplicate_is_skipped_when_deduplicating() {
+        let dir = TempDir::new();
+        let mut notes = storage(&dir);
+        notes.set_deduplicate(true);
+        assert!(notes.add_note(note("Use const")));
+        assert!(!notes.add_note(note("Use const")));
+        assert_eq!(notes.necessary_notes().len(), 1);
+    }
+
+    #[test]
+    fn near_duplicate_is_kept() {
+        let dir = TempDir::new();
+        let mut notes = storage(&dir);
+        notes.set_deduplicate(true);
+        assert!(notes.add_note(note("Use const")));
+        assert!(notes.add_note(note("Use const.")));
+        assert!(notes.add_note(note("use const")));
+        assert_eq!(notes.necessary_notes().len(), 3);
+    }
+
+    #[test]
+    fn duplicates_are_kept_by_default_and_across_sections() {
+        let dir = TempDir::new();
+        let mut notes = storage(&dir);
+        assert!(notes.add_note(note("Use const")));
+        assert!(notes.add_note(note("Use const")));
+        notes.set_deduplicate(true);
+        assert!(notes.add_optional_note(note("Use const")));
+        assert_eq!(notes.necessary_notes().len(), 2);
+        assert_eq!(notes.optional_notes().len(), 1);
+    }
+
+    #[test]
+    fn notes_round_trip_in_output_encoding() {
+        for label in ["utf-8", "utf-8-bom", "windows-1251", "koi8-r"] {
+            let dir = TempDir::new();
+            let encoding = OutputEncoding::try_from(label.to_string()).unwrap();
+            let file_name = dir.join("notes.txt");
+            let mut notes: FileNotesStorage<TaskNode, TaskNode> =
+                FileNotesStorage::open(file_name.clone(), NotesFormat::default(), encoding)
+                    .unwrap();
+            notes.add_note(note("Имя переменной"));
+            notes.add_optional_note(note("Лишняя копия"));
+            notes.save().unwrap();
+
+            let notes: FileNotesStorage<TaskNode, TaskNode> =
+                FileNotesStorage::open(file_name, NotesFormat::default(), encoding).unwrap();
+            assert_eq!(
+                notes.necessary_notes()[0].text(),
+                "Имя переменной",
+                "{}",
+                label
+            );
+            assert_eq!(
+                notes.optional_notes()[0].text(),
+                "Лишняя копия",
+                "{}",
+                label
+            );
         }
+    }
 
-        for (num, res) in lines.enumerate() {
-            let line = res?;
-            let (parsed_num, line) = Self::parse_line(&line)?;
-            ensure!(parsed_num == num, "Incorrect number of line");
+    #[test]
+    fn note_template_parses_what_it_renders() {
+        let template = NoteTemplate::try_from("[{n}] {text};".to_string()).unwrap();
+        let line = template.render("N2", "Use const");
+        assert_eq!(line, "[N2] Use const;");
+        assert_eq!(template.parse(&line).unwrap(), ("N2", "Use const"));
+        assert_eq!(String::from(template), "[{n}] {text};");
+    }
 
-            optional_notes.push(line.to_string().into());
+    #[test]
+    fn note_template_needs_number_text_and_separator() {
+        for template in ["{text}", "{n}) ", "{n}{text}"] {
+            assert!(NoteTemplate::try_from(template.to_string()).is_err());
         }
-        trace!("Optional notes read");
+    }
 
-        Ok(FileNotesStorage {
-            file_name,
-            necessary_notes,
-            optional_notes,
-        })
+    #[test]
+    fn notes_round_trip_with_note_template() {
+        let dir = TempDir::new();
+        let format = NotesFormat {
+            template: NoteTemplate::try_from("#{n}: {text}".to_string()).unwrap(),
+            ..NotesFormat::default()
+        };
+        let mut notes: FileNotesStorage<TaskNode, TaskNode> =
+            FileNotesStorage::new(dir.join("notes.txt"), format.clone()).unwrap();
+        notes.add_note(note("Use const"));
+        notes.add_optional_note(note("Name the constant"));
+        let mut text = Vec::new();
+        notes.save_with_writer(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("#0: Use const"));
+        let (necessary, optional) =
+            FileNotesStorage::<TaskNode, TaskNode>::parse_text(&text, &format).unwrap();
+        assert_eq!(necessary[0].text(), "Use const");
+        assert_eq!(optional[0].text(), "Name the constant");
+    }
+
+    #[test]
+    fn prefixed_ids_from_one_round_trip() {
+        let dir = TempDir::new();
+        let format = NotesFormat {
+            ids: NoteIds {
+                prefixed: true,
+                first: 1,
+            },
+            ..NotesFormat::default()
+        };
+        let mut notes: FileNotesStorage<TaskNode, TaskNode> =
+            FileNotesStorage::new(dir.join("notes.txt"), format.clone()).unwrap();
+        notes.add_note(note("Use const"));
+        notes.add_optional_note(note("Name the constant"));
+        let mut text = Vec::new();
+        notes.save_with_writer(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("N1) Use const\n"), "{}", text);
+        assert!(text.contains("O1) Name the constant\n"), "{}", text);
+        let (necessary, optional) =
+            FileNotesStorage::<TaskNode, TaskNode>::parse_text(&text, &format).unwrap();
+        assert_eq!(necessary[0].text(), "Use const");
+        assert_eq!(optional[0].text(), "Name the constant");
+    }
+
+    #[test]
+    fn note_index_is_found_by_id() {
+        let ids = NoteIds {
+            prefixed: true,
+            first: 1,
+        };
+        assert_eq!(ids.index("N2", false).unwrap(), 1);
+        assert_eq!(ids.index("o1", true).unwrap(), 0);
+        assert_eq!(ids.index("3", false).unwrap(), 2);
+        assert!(ids.index("O1", false).is_err());
+        assert!(ids.index("0", false).is_err());
+        assert!(ids.is_id("N12"));
+        assert!(!ids.is_id("Name"));
+        assert_eq!(NoteIds::default().index("0", true).unwrap(), 0);
+    }
+
+    #[test]
+    fn optional_notes_are_written_first_when_asked() {
+        let dir = TempDir::new();
+        let format = NotesFormat {
+            optional_first: true,
+            ..NotesFormat::default()
+        };
+        let mut notes: FileNotesStorage<TaskNode, TaskNode> =
+            FileNotesStorage::new(dir.join("notes.txt"), format.clone()).unwrap();
+        notes.add_note(note("Use const"));
+        notes.add_optional_note(note("Name the constant"));
+        let mut text = Vec::new();
+        notes.save_with_writer(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.ends_with("Optional:\n0) Name the constant\nNecessary:\n0) Use const\n"));
+        for format in [format, NotesFormat::default()] {
+            let (necessary, optional) =
+                FileNotesStorage::<TaskNode, TaskNode>::parse_text(&text, &format).unwrap();
+            assert_eq!(necessary[0].text(), "Use const");
+            assert_eq!(optional[0].text(), "Name the constant");
+        }
     }
 }
@@ -1,27 +1,165 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs;
 use std::fs::File;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use anyhow::{ensure, Context};
 use log::trace;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::preparing::notes::{FileNotesStorage, Note};
+use crate::messages::Message;
+use crate::preparing::archive::extract_code_file;
+use crate::preparing::notes::{FileNotesStorage, Note, NotesFormat};
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
+/// The newest task format this version of the tool understands
+pub(crate) const SUPPORTED_TASK_SCHEMA: u32 = 0;
+
+fn is_first_schema(schema: &u32) -> bool {
+    *schema == 0
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub(crate) struct Task {
     pub name: String,
     pub code_file_name: String,
+    /// Path of the code file inside the task directory if it is nested, e.g. `src/task1/main.cpp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_file_path: Option<PathBuf>,
+    /// Other files of the task to reference, relative to the task directory, e.g. headers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_code_files: Vec<PathBuf>,
     pub show_method: ShowMethod,
+    /// File with the notes of the task
+    #[schemars(with = "PathBuf")]
     notes: FileNotesStorage<TaskNode, TaskNode>,
+    /// Human-readable title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Deadline in the `YYYY-MM-DD` format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_points: Option<u32>,
+    /// Name or initials of the reviewer the task is assigned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assigned_to: Option<String>,
+    /// Time of the last completed review in RFC 3339, e.g. `2024-05-01T18:30:00Z`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reviewed: Option<String>,
+    /// Version of the task format, tasks using newer features have the bigger one
+    #[serde(default, skip_serializing_if = "is_first_schema")]
+    pub schema: u32,
+    /// Fields of newer versions of the tool, kept as they are to be written back
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Optional description of the new task
+#[derive(Debug, Default)]
+pub(crate) struct TaskInfo {
+    pub title: Option<String>,
+    pub deadline: Option<String>,
+    pub max_points: Option<u32>,
+    /// Nested path of the code file, the code file is right in the task directory without it
+    pub code_file_path: Option<PathBuf>,
+    pub assigned_to: Option<String>,
+    pub extra_code_files: Vec<PathBuf>,
+}
+
+impl TaskInfo {
+    /// Check that the deadline is a `YYYY-MM-DD` date
+    pub fn check_deadline(&self) -> anyhow::Result<()> {
+        let Some(deadline) = &self.deadline else {
+            return Ok(());
+        };
+        let parts = deadline.split('-').collect::<Vec<_>>();
+        ensure!(
+            parts.len() == 3
+                && [4, 2, 2]
+                    .iter()
+                    .zip(&parts)
+                    .all(|(len, part)| part.len() == *len
+                        && part.chars().all(|c| c.is_ascii_digit())),
+            "{}",
+            Message::IncorrectDeadline.format(&[&deadline])
+        );
+        Ok(())
+    }
+}
+
+/// Check that the path inside the task directory doesn't leave it
+fn check_task_relative_path(path: &Path) -> anyhow::Result<()> {
+    ensure!(
+        path.components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir)),
+        "{}",
+        Message::CodeFilePathOutsideTask.format(&[&path.display()])
+    );
+    Ok(())
+}
+
+/// Check that the nested path of the code file doesn't leave the task directory and ends with the code file name
+fn check_code_file_path(code_file_path: &Path, code_file_name: &str) -> anyhow::Result<()> {
+    check_task_relative_path(code_file_path)?;
+    ensure!(
+        code_file_path
+            .file_name()
+            .is_some_and(|file_name| file_name == code_file_name),
+        "{}",
+        Message::CodeFilePathWithoutName.format(&[&code_file_path.display(), &code_file_name])
+    );
+    Ok(())
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Display for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(title) = &self.title {
+            write!(f, ": {}", title)?;
+        }
+        if let Some(deadline) = &self.deadline {
+            write!(f, ", deadline {}", deadline)?;
+        }
+        if let Some(max_points) = self.max_points {
+            write!(f, ", {} points", max_points)?;
+        }
+        if let Some(assigned_to) = &self.assigned_to {
+            write!(f, ", assigned to {}", assigned_to)?;
+        }
+        if let Some(last_reviewed) = &self.last_reviewed {
+            write!(f, ", last reviewed {}", last_reviewed)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub(crate) enum ShowMethod {
     Console,
-    File { file_name: PathBuf },
+    File {
+        file_name: PathBuf,
+    },
+    /// LaTeX document, or a fragment to include if not `standalone`
+    Latex {
+        file_name: PathBuf,
+        standalone: bool,
+    },
+}
+
+impl ShowMethod {
+    /// Extension of the reviews file, the same as the shown file has (e.g. `md`), `txt` by default
+    pub fn reviews_extension(&self) -> &str {
+        match self {
+            ShowMethod::File { file_name } => file_name
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("txt"),
+            // Notes in the reviews file are not LaTeX
+            ShowMethod::Console | ShowMethod::Latex { .. } => "txt",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -41,10 +179,50 @@ impl Display for TaskNode {
     }
 }
 
+impl TaskNode {
+    /// Names of the `{name}` placeholders in the order of their first appearance
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find('{') {
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let name = &rest[..end];
+            if !name.is_empty()
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                && !names.contains(&name)
+            {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Text with the placeholders replaced by the values
+    pub fn fill(&self, values: &[(String, String)]) -> String {
+        values
+            .iter()
+            .fold(self.text.clone(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+}
+
 impl Note for TaskNode {
+    fn parse(text: String, _format: &NotesFormat) -> anyhow::Result<Self> {
+        Ok(Self::from(text))
+    }
+
     fn text(&self) -> String {
         self.text.clone()
     }
+
+    fn merge(&mut self, other: Self) {
+        self.text.push(' ');
+        self.text.push_str(&other.text);
+    }
 }
 
 impl Task {
@@ -54,13 +232,27 @@ impl Task {
         task_name: String,
         code_file_name: String,
         show_method: ShowMethod,
+        notes_format: NotesFormat,
+        info: TaskInfo,
     ) -> anyhow::Result<Self> {
+        info.check_deadline()?;
+        if let Some(code_file_path) = &info.code_file_path {
+            check_code_file_path(code_file_path, &code_file_name)?;
+        }
+        for path in &info.extra_code_files {
+            check_task_relative_path(path)?;
+        }
         let task_dir = project_dir.join("tasks").join(&task_name);
-        fs::create_dir_all(task_dir.as_path()).context("Can't create task directory")?;
-        trace!("Task directory created {}", task_dir.display());
+        let code_file_path = task_dir.join(
+            info.code_file_path
+                .as_deref()
+                .unwrap_or(code_file_name.as_ref()),
+        );
+        let code_dir = code_file_path.parent().unwrap_or(&task_dir);
+        fs::create_dir_all(code_dir).context(Message::CantCreateTaskDirectory.text())?;
+        trace!("Task directory created {}", code_dir.display());
 
-        let code_file_path = task_dir.join(&code_file_name);
-        File::create_new(code_file_path).context("Can't create new file for code to task")?;
+        File::create_new(code_file_path).context(Message::CantCreateTaskCodeFile.text())?;
         trace!("File to code created");
 
         let notes = FileNotesStorage::new(
@@ -68,20 +260,65 @@ impl Task {
                 .join("notes")
                 .join(&task_name)
                 .with_extension("txt"),
+            notes_format,
         )?;
         Ok(Task {
             name: task_name,
             code_file_name,
+            code_file_path: info.code_file_path,
+            extra_code_files: info.extra_code_files,
             notes,
             show_method,
+            title: info.title,
+            deadline: info.deadline,
+            max_points: info.max_points,
+            assigned_to: info.assigned_to,
+            last_reviewed: None,
+            schema: 0,
+            unknown_fields: BTreeMap::new(),
         })
     }
 
-    pub fn add_note(&mut self, text: String, optional: bool) {
+    /// Add note to the task notes, returns false if the note was skipped as a duplicate
+    pub fn add_note(&mut self, text: String, optional: bool) -> bool {
         if optional {
-            self.notes.add_optional_note(TaskNode::from(text));
+            self.notes.add_optional_note(TaskNode::from(text))
         } else {
-            self.notes.add_note(TaskNode::from(text));
+            self.notes.add_note(TaskNode::from(text))
+        }
+    }
+
+    /// Read the notes of the task, they aren't read with the config
+    pub fn load_notes(&mut self, format: NotesFormat) -> anyhow::Result<()> {
+        self.notes.load_with_format(format)
+    }
+
+    pub fn set_deduplicate_notes(&mut self, deduplicate: bool) {
+        self.notes.set_deduplicate(deduplicate);
+    }
+
+    /// Numbers of necessary and optional template notes
+    pub fn notes_count(&self) -> (usize, usize) {
+        (
+            self.notes.necessary_notes().len(),
+            self.notes.optional_notes().len(),
+        )
+    }
+
+    /// Write all notes of the task with their numbers as in the notes file
+    pub fn write_notes(&self, writer: &mut impl std::io::Write) -> anyhow::Result<()> {
+        self.notes.save_with_writer(writer)
+    }
+
+    pub fn notes_file_path(&self) -> &Path {
+        self.notes.file_name()
+    }
+
+    pub fn has_notes(&self, optional: bool) -> bool {
+        if optional {
+            !self.notes.optional_notes().is_empty()
+        } else {
+            !self.notes.necessary_notes().is_empty()
         }
     }
 
@@ -91,39 +328,313 @@ impl Task {
         } else {
             self.notes.find_note(num)
         }
-        .context("Note not found")
+        .context(Message::NoteNotFound.text())
     }
 
-    pub fn get_file(&self) -> anyhow::Result<File> {
-        let file_path = Path::new("tasks")
+    pub fn code_file_path(&self, project_dir: &Path) -> PathBuf {
+        let task_dir = project_dir.join("tasks").join(&self.name);
+        match &self.code_file_path {
+            Some(code_file_path) => task_dir.join(code_file_path),
+            None => task_dir.join(&self.code_file_name),
+        }
+    }
+
+    /// File with the notes of the current review
+    pub fn reviews_file_path(&self, project_dir: &Path) -> PathBuf {
+        project_dir
+            .join("reviews")
             .join(&self.name)
-            .join(&self.code_file_name);
-        File::open(file_path).context("Can't open file with code")
+            .with_extension(self.show_method.reviews_extension())
+    }
+
+    /// Replace the code file with the one from the zip archive of the submission
+    pub fn extract_code(&self, project_dir: &Path, archive: &Path) -> anyhow::Result<PathBuf> {
+        let code_file = self.code_file_path(project_dir);
+        extract_code_file(
+            archive,
+            &self.code_file_name,
+            self.code_file_path.as_deref(),
+            &code_file,
+        )?;
+        Ok(code_file)
+    }
+
+    /// Files of the task which can be referenced in notes
+    /// The code file and the extra code files
+    pub fn code_files(&self, project_dir: &Path) -> Vec<PathBuf> {
+        let task_dir = project_dir.join("tasks").join(&self.name);
+        std::iter::once(self.code_file_path(project_dir))
+            .chain(self.extra_code_files.iter().map(|path| task_dir.join(path)))
+            .collect()
+    }
+
+    /// Create missing directory and files of the task, returns the created paths
+    pub(super) fn repair_environment(&self, project_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut created = Vec::new();
+        let task_dir = project_dir.join("tasks").join(&self.name);
+        if !task_dir.exists() {
+            fs::create_dir_all(&task_dir).context(Message::CantCreateTaskDirectory.text())?;
+            created.push(task_dir);
+        }
+
+        if let Some(code_file_path) = &self.code_file_path {
+            check_code_file_path(code_file_path, &self.code_file_name)?;
+        }
+        let code_file = self.code_file_path(project_dir);
+        if let Some(code_dir) = code_file.parent().filter(|dir| !dir.exists()) {
+            fs::create_dir_all(code_dir).context(Message::CantCreateDirectoryForCode.text())?;
+            created.push(code_dir.to_path_buf());
+        }
+        if !code_file.exists() {
+            File::create_new(&code_file).context(Message::CantCreateFileForCode.text())?;
+            created.push(code_file);
+        }
+
+        let notes_file = project_dir
+            .join("notes")
+            .join(&self.name)
+            .with_extension("txt");
+        if !notes_file.exists() {
+            File::create_new(&notes_file).context(Message::CantCreateNotesFile.text())?;
+            created.push(notes_file);
+        }
+        Ok(created)
     }
 
     pub(super) fn check_environment(&self, project_dir: &Path) -> anyhow::Result<()> {
         let tasks_dir = project_dir.join("tasks").join(&self.name);
         trace!("Check task directory: {}", tasks_dir.display());
-        ensure!(tasks_dir.exists(), "Task directory doesn't exist");
-        ensure!(tasks_dir.is_dir(), "Task directory is not a directory");
+        ensure!(tasks_dir.exists(), Message::TaskDirectoryDoesntExist.text());
+        ensure!(
+            tasks_dir.is_dir(),
+            Message::TaskDirectoryIsNotADirectory.text()
+        );
 
         let notes_dir = project_dir.join("notes");
         trace!("Check notes directory: {}", notes_dir.display());
-        ensure!(notes_dir.exists(), "Notes directory doesn't exist");
-        ensure!(notes_dir.is_dir(), "Notes directory is not a directory");
+        ensure!(
+            notes_dir.exists(),
+            Message::NotesDirectoryDoesntExist.text()
+        );
+        ensure!(
+            notes_dir.is_dir(),
+            Message::NotesDirectoryIsNotADirectory.text()
+        );
 
-        let task_code_file = tasks_dir
-            .join(&self.name)
-            .with_file_name(&self.code_file_name);
+        if let Some(code_file_path) = &self.code_file_path {
+            check_code_file_path(code_file_path, &self.code_file_name)?;
+        }
+        let task_code_file = self.code_file_path(project_dir);
         trace!("Check task code file: {}", task_code_file.display());
-        ensure!(task_code_file.exists(), "Task code file doesn't exist");
-        ensure!(task_code_file.is_file(), "Task code file is not a file");
+        ensure!(
+            task_code_file.exists(),
+            Message::TaskCodeFileDoesntExist.text()
+        );
+        ensure!(
+            task_code_file.is_file(),
+            Message::TaskCodeFileIsNotAFile.text()
+        );
 
         let notes_file = notes_dir.join(&self.name).with_extension("txt");
         trace!("Check notes file: {}", notes_file.display());
-        ensure!(notes_file.exists(), "Notes file doesn't exist");
-        ensure!(notes_file.is_file(), "Notes file is not a file");
+        ensure!(notes_file.exists(), Message::NotesFileDoesntExist.text());
+        ensure!(notes_file.is_file(), Message::NotesFileIsNotAFile.text());
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn new_task(dir: &TempDir) -> Task {
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        Task::new(
+            &dir.join(""),
+            "t".to_string(),
+            "main.cpp".to_string(),
+            ShowMethod::Console,
+            NotesFormat::default(),
+            TaskInfo::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn notes_file_is_in_notes_directory() {
+        let dir = TempDir::new();
+        let task = new_task(&dir);
+        assert_eq!(task.notes_file_path(), dir.join("notes").join("t.txt"));
+        assert!(dir.join("tasks/t/main.cpp").exists());
+    }
+
+    #[test]
+    fn missing_files_are_recreated() {
+        let dir = TempDir::new();
+        let task = new_task(&dir);
+        fs::remove_file(dir.join("tasks/t/main.cpp")).unwrap();
+        fs::remove_file(dir.join("notes/t.txt")).unwrap();
+        let created = task.repair_environment(&dir.join("")).unwrap();
+        assert_eq!(
+            created,
+            [dir.join("tasks/t/main.cpp"), dir.join("notes/t.txt")]
+        );
+        task.check_environment(&dir.join("")).unwrap();
+        assert!(task.repair_environment(&dir.join("")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn code_file_is_checked_in_the_task_directory() {
+        let dir = TempDir::new();
+        let task = new_task(&dir);
+        task.check_environment(&dir.join("")).unwrap();
+        fs::rename(dir.join("tasks/t/main.cpp"), dir.join("tasks/main.cpp")).unwrap();
+        assert!(task.check_environment(&dir.join("")).is_err());
+    }
+
+    #[test]
+    fn reviews_file_has_extension_of_shown_file() {
+        let mut task = new_task(&TempDir::new());
+        let project = Path::new("project");
+        assert_eq!(
+            task.reviews_file_path(project),
+            project.join("reviews/t.txt")
+        );
+        task.show_method = ShowMethod::File {
+            file_name: PathBuf::from("out.md"),
+        };
+        assert_eq!(
+            task.reviews_file_path(project),
+            project.join("reviews/t.md")
+        );
+        task.show_method = ShowMethod::Latex {
+            file_name: PathBuf::from("out.tex"),
+            standalone: true,
+        };
+        assert_eq!(
+            task.reviews_file_path(project),
+            project.join("reviews/t.txt")
+        );
+    }
+
+    #[test]
+    fn task_info_is_shown_and_saved_when_set() {
+        let mut task = new_task(&TempDir::new());
+        assert_eq!(task.to_string(), "t");
+        let saved = serde_json::to_value(&task).unwrap();
+        assert!(saved.get("title").is_none());
+        assert!(saved.get("deadline").is_none());
+
+        task.title = Some("Linked list".to_string());
+        task.deadline = Some("2024-03-01".to_string());
+        task.max_points = Some(10);
+        assert_eq!(
+            task.to_string(),
+            "t: Linked list, deadline 2024-03-01, 10 points"
+        );
+        let saved = serde_json::to_value(&task).unwrap();
+        assert_eq!(saved["deadline"], "2024-03-01");
+        assert_eq!(saved["max_points"], 10);
+    }
+
+    #[test]
+    fn deadline_is_checked() {
+        let info = |deadline: &str| TaskInfo {
+            deadline: Some(deadline.to_string()),
+            ..TaskInfo::default()
+        };
+        TaskInfo::default().check_deadline().unwrap();
+        info("2024-03-01").check_deadline().unwrap();
+        for deadline in ["2024-3-1", "01-03-2024", "2024-03-01-", "2024-0a-01"] {
+            assert!(info(deadline).check_deadline().is_err(), "{}", deadline);
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled() {
+        let note = TaskNode::from(
+            "Rename {old} to {new}, {old} is unclear, {} and {a b} stay".to_string(),
+        );
+        assert_eq!(note.placeholders(), ["old", "new"]);
+        let values = [
+            ("old".to_string(), "x".to_string()),
+            ("new".to_string(), "count".to_string()),
+        ];
+        assert_eq!(
+            note.fill(&values),
+            "Rename x to count, x is unclear, {} and {a b} stay"
+        );
+    }
+
+    #[test]
+    fn nested_code_file_is_created_in_the_task_directory() {
+        let dir = TempDir::new();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        let new_task = |code_file_path: &str| {
+            Task::new(
+                &dir.join(""),
+                "t".to_string(),
+                "main.cpp".to_string(),
+                ShowMethod::Console,
+                NotesFormat::default(),
+                TaskInfo {
+                    code_file_path: Some(PathBuf::from(code_file_path)),
+                    ..TaskInfo::default()
+                },
+            )
+        };
+        for path in ["../main.cpp", "/src/main.cpp", "src/other.cpp"] {
+            assert!(new_task(path).is_err(), "{}", path);
+        }
+        let task = new_task("src/task1/main.cpp").unwrap();
+        let code_file = dir.join("tasks/t/src/task1/main.cpp");
+        assert_eq!(task.code_file_path(&dir.join("")), code_file);
+        assert!(code_file.exists());
+    }
+
+    #[test]
+    fn task_notes_are_written_with_numbers() {
+        let dir = TempDir::new();
+        let mut task = new_task(&dir);
+        task.add_note("Unused variable".to_string(), false);
+        task.add_note("Magic number".to_string(), false);
+        task.add_note("Use const".to_string(), true);
+        let mut text = Vec::new();
+        task.write_notes(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.ends_with(
+            "Necessary:\n0) Unused variable\n1) Magic number\nOptional:\n0) Use const\n"
+        ));
+    }
+
+    #[test]
+    fn extra_code_files_stay_in_the_task_directory() {
+        let dir = TempDir::new();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        let new_task = |extra_code_files: &[&str]| {
+            Task::new(
+                &dir.join(""),
+                "t".to_string(),
+                "main.cpp".to_string(),
+                ShowMethod::Console,
+                NotesFormat::default(),
+                TaskInfo {
+                    extra_code_files: extra_code_files.iter().map(PathBuf::from).collect(),
+                    ..TaskInfo::default()
+                },
+            )
+        };
+        assert!(new_task(&["../other/main.h"]).is_err());
+        let task = new_task(&["main.h", "include/util.h"]).unwrap();
+        assert_eq!(
+            task.code_files(&dir.join("")),
+            [
+                dir.join("tasks/t/main.cpp"),
+                dir.join("tasks/t/main.h"),
+                dir.join("tasks/t/include/util.h")
+            ]
+        );
+    }
+}
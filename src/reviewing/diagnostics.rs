@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::messages::Message;
+
+/// Output of `clang-tidy -export-fixes`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedFixes {
+    #[serde(default)]
+    diagnostics: Vec<ExportedDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedDiagnostic {
+    diagnostic_name: String,
+    diagnostic_message: DiagnosticMessage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiagnosticMessage {
+    message: String,
+    file_path: PathBuf,
+    file_offset: usize,
+}
+
+/// Finding of clang-tidy in the code file
+#[derive(Debug)]
+pub(crate) struct Diagnostic {
+    pub name: String,
+    pub message: String,
+    pub file: PathBuf,
+    /// Line of the finding, numbered from 1
+    pub line: usize,
+}
+
+impl Diagnostic {
+    pub(crate) fn note_text(&self) -> String {
+        format!("{} [{}]", self.message, self.name)
+    }
+}
+
+/// Parse the fixes exported by clang-tidy, relative paths are resolved against `base_dir`
+pub(crate) fn parse_diagnostics(yaml: &str, base_dir: &Path) -> anyhow::Result<Vec<Diagnostic>> {
+    let fixes: ExportedFixes =
+        serde_yaml::from_str(yaml).context(Message::CantParseClangTidyDiagnostics.text())?;
+    fixes
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let message = diagnostic.diagnostic_message;
+            let file = base_dir.join(&message.file_path);
+            let code = std::fs::read(&file)
+                .with_context(|| Message::CantReadNamedFile.format(&[&file.display()]))?;
+            let offset = message.file_offset.min(code.len());
+            let line = code[..offset].iter().filter(|&&byte| byte == b'\n').count() + 1;
+            Ok(Diagnostic {
+                name: diagnostic.diagnostic_name,
+                message: message.message,
+                file,
+                line,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    const FIXES: &str = "\
+MainSourceFile: main.cpp
+Diagnostics:
+  - DiagnosticName: clang-diagnostic-unused-variable
+    DiagnosticMessage:
+      Message: unused variable 'x'
+      FilePath: main.cpp
+      FileOffset: 21
+      Replacements: []
+";
+
+    #[test]
+    fn offset_of_the_finding_is_turned_into_the_line() {
+        let dir = TempDir::new();
+        fs::write(
+            dir.join("main.cpp"),
+            "int main() {\n    int x;\n    return 0;\n}\n",
+        )
+        .unwrap();
+        let diagnostics = parse_diagnostics(FIXES, &dir.join("")).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 2);
+        assert_eq!(diagnostics[0].file, dir.join("main.cpp"));
+        assert_eq!(
+            diagnostics[0].note_text(),
+            "unused variable 'x' [clang-diagnostic-unused-variable]"
+        );
+    }
+
+    #[test]
+    fn fixes_without_diagnostics_are_empty() {
+        let diagnostics = parse_diagnostics("MainSourceFile: main.cpp\n", Path::new("")).unwrap();
+        assert!(diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{bail, ensure, Context};
+
+use crate::messages::Message;
+
+/// Hunk of the unified diff
+#[derive(Debug, Clone)]
+pub(crate) struct Hunk {
+    /// File changed by the hunk, without the `b/` prefix
+    pub file: String,
+    /// First line of the hunk in the new version of the file
+    pub new_start: usize,
+    pub new_len: usize,
+    /// Header and lines of the hunk as they are in the diff
+    pub text: String,
+}
+
+impl Hunk {
+    /// Lines of the new version of the file changed by the hunk, the first one if it only removes lines
+    pub fn new_rows(&self) -> (usize, usize) {
+        new_rows(self.new_start, self.new_len)
+    }
+}
+
+fn new_rows(new_start: usize, new_len: usize) -> (usize, usize) {
+    (new_start, new_start + new_len.max(1) - 1)
+}
+
+/// Lines of the new version of the file from the hunk header, as `Hunk::new_rows`
+pub(crate) fn header_rows(header: &str) -> anyhow::Result<(usize, usize)> {
+    let (_, new_start, new_len) = parse_header(header)?;
+    Ok(new_rows(new_start, new_len))
+}
+
+/// Parse `@@ -a,b +c,d @@` into the line counts and the start of the new version
+fn parse_header(header: &str) -> anyhow::Result<(usize, usize, usize)> {
+    let ranges = header
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .context(Message::IncorrectHunkHeader.text())?;
+    let (old, new) = ranges
+        .split_once(' ')
+        .context(Message::HunkHeaderHasNoNewRange.text())?;
+    let parse_range = |range: &str, sign: char| -> anyhow::Result<(usize, usize)> {
+        let range = range
+            .strip_prefix(sign)
+            .with_context(|| Message::RangeDoesntStartWith.format(&[&range, &sign]))?;
+        let (start, len) = range.split_once(',').unwrap_or((range, "1"));
+        Ok((
+            start.parse().context(Message::IncorrectHunkStart.text())?,
+            len.parse().context(Message::IncorrectHunkLength.text())?,
+        ))
+    };
+    let (_, old_len) = parse_range(old, '-')?;
+    let (new_start, new_len) = parse_range(new, '+')?;
+    Ok((old_len, new_start, new_len))
+}
+
+/// Split the unified diff (like the one from `git diff`) into hunks
+pub(crate) fn parse_hunks(diff: &str) -> anyhow::Result<Vec<Hunk>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut file = None;
+    let mut remaining = (0, 0);
+    for (num, line) in diff.lines().enumerate() {
+        let num = num + 1;
+        if remaining != (0, 0) {
+            let hunk = hunks.last_mut().expect("Lines remain only inside a hunk");
+            match line.chars().next() {
+                Some(' ') | None => {
+                    ensure!(
+                        remaining.0 > 0 && remaining.1 > 0,
+                        "{}",
+                        Message::HunkLongerThanHeader.format(&[&num])
+                    );
+                    remaining = (remaining.0 - 1, remaining.1 - 1);
+                }
+                Some('-') if remaining.0 > 0 => remaining.0 -= 1,
+                Some('+') if remaining.1 > 0 => remaining.1 -= 1,
+                Some('\\') => {}
+                _ => bail!("{}", Message::MalformedHunkLine.format(&[&num, &line])),
+            }
+            hunk.text.push('\n');
+            hunk.text.push_str(line);
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.split('\t').next().unwrap_or(path);
+            file = Some(path.strip_prefix("b/").unwrap_or(path).to_string());
+        } else if line.starts_with("@@") {
+            let (old_len, new_start, new_len) = parse_header(line)
+                .with_context(|| Message::MalformedHunkHeaderAtLine.format(&[&num]))?;
+            hunks.push(Hunk {
+                file: file
+                    .clone()
+                    .with_context(|| Message::HunkWithoutFileHeader.format(&[&num]))?,
+                new_start,
+                new_len,
+                text: line.to_string(),
+            });
+            remaining = (old_len, new_len);
+        } else if !hunks.is_empty()
+            && line.starts_with([' ', '+', '-'])
+            && !line.starts_with("--- ")
+        {
+            // Only the file headers are between the hunks
+            bail!("{}", Message::HunkLongerThanHeader.format(&[&num]));
+        } else if line.starts_with('\\') && !hunks.is_empty() {
+            let hunk = hunks.last_mut().expect("Checked above");
+            hunk.text.push('\n');
+            hunk.text.push_str(line);
+        }
+    }
+    ensure!(remaining == (0, 0), Message::DiffEndsInHunk.text());
+    ensure!(!hunks.is_empty(), Message::DiffHasNoHunks.text());
+    Ok(hunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/main.cpp b/main.cpp
+--- a/main.cpp
++++ b/main.cpp
+@@ -1,3 +1,4 @@
+ int main() {
+-    int x;
++    int x = 0;
++    x++;
+     return 0;
+@@ -10 +11,0 @@ void f() {
+-    // unused
+diff --git a/util.h b/util.h
+--- a/util.h	2024-05-01
++++ b/util.h	2024-05-01
+@@ -1 +1 @@
+-#pragma once
++#ifndef UTIL_H
+\\ No newline at end of file
+";
+
+    fn error(diff: &str) -> String {
+        parse_hunks(diff).unwrap_err().root_cause().to_string()
+    }
+
+    #[test]
+    fn hunks_of_several_files_are_parsed() {
+        let hunks = parse_hunks(DIFF).unwrap();
+        let files = hunks.iter().map(|hunk| hunk.file.as_str());
+        assert_eq!(
+            files.collect::<Vec<_>>(),
+            ["main.cpp", "main.cpp", "util.h"]
+        );
+        let rows = hunks.iter().map(Hunk::new_rows);
+        assert_eq!(rows.collect::<Vec<_>>(), [(1, 4), (11, 11), (1, 1)]);
+        assert!(hunks[0].text.starts_with("@@ -1,3 +1,4 @@\n int main() {"));
+        assert!(hunks[2].text.ends_with("\\ No newline at end of file"));
+        assert_eq!(header_rows("@@ -1,3 +1,4 @@").unwrap(), (1, 4));
+    }
+
+    #[test]
+    fn hunk_longer_than_its_header_is_an_error() {
+        let diff = DIFF.replace("     return 0;\n", "     return 0;\n }\n");
+        assert_eq!(error(&diff), Message::HunkLongerThanHeader.format(&[&10]));
+        let diff = DIFF.replace("+    x++;\n", "+    x++;\n+    x--;\n");
+        // The context line comes when only the removed lines remain
+        assert_eq!(error(&diff), Message::HunkLongerThanHeader.format(&[&10]));
+    }
+
+    #[test]
+    fn hunk_shorter_than_its_header_is_an_error() {
+        let diff = DIFF.replace("+    x++;\n", "");
+        assert_eq!(
+            error(&diff),
+            Message::MalformedHunkLine.format(&[&9, &"@@ -10 +11,0 @@ void f() {"])
+        );
+        assert_eq!(
+            error("+++ b/main.cpp\n@@ -1,2 +1,2 @@\n int main() {\n"),
+            Message::DiffEndsInHunk.text()
+        );
+    }
+
+    #[test]
+    fn hunk_without_file_header_is_an_error() {
+        assert_eq!(
+            error("--- a/main.cpp\n@@ -1 +1 @@\n-a\n+b\n"),
+            Message::HunkWithoutFileHeader.format(&[&2])
+        );
+    }
+
+    #[test]
+    fn diff_without_hunks_is_an_error() {
+        let diff = "diff --git a/main.cpp b/main.cpp\n--- a/main.cpp\n+++ b/main.cpp\n";
+        assert_eq!(error(diff), Message::DiffHasNoHunks.text());
+        assert_eq!(error(""), Message::DiffHasNoHunks.text());
+    }
+}
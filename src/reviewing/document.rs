@@ -0,0 +1,406 @@
+use std::io::{Read, Write};
+
+use anyhow::{ensure, Context};
+
+use crate::messages::Message;
+use crate::preparing::notes::{
+    read_written_notes, write_grouped_notes, write_notes, write_section, FileNotesStorage, Note,
+    NoteIds, NotesFormat, WrittenNote, FORMAT_HEADER, NECESSARY_HEADER, OPTIONAL_HEADER,
+};
+use crate::reviewing::notes::ReviewNote;
+use crate::reviewing::review::GroupBy;
+use crate::separator;
+
+/// The whole review as it is shown to the student.
+///
+/// The text is the header (authors, source and task lines), the `+` separator, optionally the table of contents
+/// starting with `Contents:` and one more separator, the notes in the notes file format without the format header,
+/// and optionally one more separator with the footer. A notes file without separators is read as notes only.
+/// The notes grouped by category are the sections of the notes file after the name line of each group.
+#[derive(Debug, Default)]
+pub(crate) struct ReviewDocument {
+    pub header: Vec<String>,
+    /// Write the table of contents before the notes
+    pub contents: bool,
+    pub group_by: GroupBy,
+    pub necessary_notes: Vec<ReviewNote>,
+    pub optional_notes: Vec<ReviewNote>,
+    /// Notes fixed in the resubmission, written after the others in the `Resolved:` section
+    pub resolved_notes: Vec<ReviewNote>,
+    pub footer: Option<String>,
+}
+
+/// Header of the section of the resolved notes
+const RESOLVED_HEADER: &str = "Resolved:";
+
+fn is_separator(line: &str) -> bool {
+    line.len() >= 3 && line.chars().all(|c| c == '+')
+}
+
+fn is_section_header(line: &str) -> bool {
+    matches!(line.trim(), NECESSARY_HEADER | OPTIONAL_HEADER)
+}
+
+impl ReviewDocument {
+    /// Separator after the header and the table of contents and before the footer
+    pub const AUTHOR_SEPARATOR: &'static str = separator!("+");
+
+    const TOC_LINE_WIDTH: usize = 60;
+
+    /// Group of the notes without category
+    pub const DEFAULT_CATEGORY: &'static str = "General";
+
+    /// Write the review in the same format as it is parsed back
+    pub fn render(&self, writer: &mut impl Write, format: &NotesFormat) -> anyhow::Result<()> {
+        write!(writer, "{}", self.header.join("\n"))?;
+        write!(writer, "{}", Self::AUTHOR_SEPARATOR)?;
+        if self.contents {
+            write!(writer, "Contents:")?;
+            for optional in format.section_order() {
+                let notes = if optional {
+                    &self.optional_notes
+                } else {
+                    &self.necessary_notes
+                };
+                Self::write_toc_section(writer, format.ids, optional, notes)?;
+            }
+            write!(writer, "{}", Self::AUTHOR_SEPARATOR)?;
+        }
+        match self.group_by {
+            GroupBy::Section => write_notes(
+                writer,
+                format,
+                false,
+                &self.necessary_notes,
+                &self.optional_notes,
+            )?,
+            GroupBy::Category => write_grouped_notes(
+                writer,
+                format,
+                &self.necessary_notes,
+                &self.optional_notes,
+                |note| {
+                    note.category()
+                        .unwrap_or(Self::DEFAULT_CATEGORY)
+                        .to_string()
+                },
+            )?,
+        }
+        // Numbered as the necessary notes, so the section is read back as them
+        write_section(
+            writer,
+            format,
+            RESOLVED_HEADER,
+            false,
+            false,
+            &self.resolved_notes,
+        )?;
+        if let Some(footer) = &self.footer {
+            writeln!(
+                writer,
+                "{}{}",
+                Self::AUTHOR_SEPARATOR.trim_start(),
+                footer.trim_end()
+            )?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// First lines of the notes of one section
+    pub fn write_toc_section(
+        writer: &mut impl Write,
+        ids: NoteIds,
+        optional: bool,
+        notes: &[ReviewNote],
+    ) -> std::io::Result<()> {
+        if notes.is_empty() {
+            return Ok(());
+        }
+        write!(
+            writer,
+            "\n{}",
+            if optional { "Optional:" } else { "Necessary:" }
+        )?;
+        for (num, note) in notes.iter().enumerate() {
+            let id = ids.id(num, optional);
+            let text = note.text();
+            let first_line = text.lines().next().unwrap_or_default();
+            if first_line.chars().count() > Self::TOC_LINE_WIDTH {
+                let truncated = first_line
+                    .chars()
+                    .take(Self::TOC_LINE_WIDTH)
+                    .collect::<String>();
+                write!(writer, "\n  {}) {}...", id, truncated)?;
+            } else {
+                write!(writer, "\n  {}) {}", id, first_line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the shown review or the reviews file
+    pub fn parse(mut reader: impl Read, format: &NotesFormat) -> anyhow::Result<Self> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .context(Message::CantReadReview.text())?;
+        let lines = text.lines().collect::<Vec<_>>();
+        let mut chunks = lines.split(|line| is_separator(line));
+        let mut review = Self::default();
+        let notes = if lines.iter().any(|line| is_separator(line)) {
+            review.header = chunks
+                .next()
+                .context(Message::ReviewHasNoHeader.text())?
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            let mut notes = chunks.next().context(Message::ReviewHasNoNotes.text())?;
+            if notes.first().is_some_and(|line| line.trim() == "Contents:") {
+                review.contents = true;
+                notes = chunks
+                    .next()
+                    .context(Message::ReviewHasNoNotesAfterContents.text())?;
+            }
+            if let Some(footer) = chunks.next() {
+                review.footer = Some(footer.join("\n"));
+            }
+            ensure!(
+                chunks.next().is_none(),
+                Message::ReviewHasTooManySeparators.text()
+            );
+            let notes = notes.join("\n");
+            let notes = notes.trim_start_matches('\n');
+            if notes.starts_with(FORMAT_HEADER) {
+                notes.to_string()
+            } else {
+                format!("{}\n{}", FORMAT_HEADER, notes)
+            }
+        } else {
+            text
+        };
+        let (notes, resolved) = match notes.split_once(&format!("\n{}\n", RESOLVED_HEADER)) {
+            Some((notes, resolved)) => (notes.to_string(), Some(resolved)),
+            None => (notes, None),
+        };
+        let (necessary_notes, optional_notes) = match Self::split_groups(&notes) {
+            Some(groups) => {
+                review.group_by = GroupBy::Category;
+                Self::parse_groups(groups, format)?
+            }
+            None => FileNotesStorage::<ReviewNote, ReviewNote>::parse_text(&notes, format)?,
+        };
+        review.necessary_notes = necessary_notes;
+        review.optional_notes = optional_notes;
+        if let Some(resolved) = resolved {
+            let resolved = format!("{}\n{}\n{}", FORMAT_HEADER, NECESSARY_HEADER, resolved);
+            let (resolved_notes, _) =
+                FileNotesStorage::<ReviewNote, ReviewNote>::parse_text(&resolved, format)
+                    .context(Message::CantReadResolvedNotes.text())?;
+            review.resolved_notes = resolved_notes
+                .into_iter()
+                .map(|mut note| {
+                    note.set_resolved(true);
+                    note
+                })
+                .collect();
+        }
+        Ok(review)
+    }
+
+    /// Names and texts of the groups of the notes grouped by category, `None` if they are not grouped.
+    ///
+    /// The group starts with its name line, e.g. `Style:`, followed by the section header.
+    fn split_groups(notes: &str) -> Option<Vec<(String, String)>> {
+        let lines = notes
+            .lines()
+            .skip_while(|line| line.trim().is_empty() || *line == FORMAT_HEADER)
+            .collect::<Vec<_>>();
+        let is_group = |index: usize| {
+            let line = lines[index].trim_end();
+            line.ends_with(':')
+                && !is_section_header(line)
+                && lines
+                    .get(index + 1)
+                    .is_some_and(|next| is_section_header(next))
+        };
+        if lines.is_empty() || !is_group(0) {
+            return None;
+        }
+        let mut groups: Vec<(String, String)> = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if is_group(index) {
+                let name = line.trim_end().trim_end_matches(':').to_string();
+                groups.push((name, FORMAT_HEADER.to_string()));
+            } else if let Some((_, text)) = groups.last_mut() {
+                text.push('\n');
+                text.push_str(line);
+            }
+        }
+        Some(groups)
+    }
+
+    /// Notes of the groups in the order of their numbers with the categories of the groups
+    fn parse_groups(
+        groups: Vec<(String, String)>,
+        format: &NotesFormat,
+    ) -> anyhow::Result<(Vec<ReviewNote>, Vec<ReviewNote>)> {
+        let mut necessary = Vec::<(WrittenNote, Option<String>)>::new();
+        let mut optional = Vec::<(WrittenNote, Option<String>)>::new();
+        for (name, text) in groups {
+            let (group_necessary, group_optional) = read_written_notes(text.as_bytes(), format)
+                .with_context(|| Message::CantReadNotesOf.format(&[&name]))?;
+            let category = (name != Self::DEFAULT_CATEGORY).then_some(name);
+            necessary.extend(
+                group_necessary
+                    .into_iter()
+                    .map(|note| (note, category.clone())),
+            );
+            optional.extend(
+                group_optional
+                    .into_iter()
+                    .map(|note| (note, category.clone())),
+            );
+        }
+        necessary.sort_by_key(|(note, _)| note.num);
+        optional.sort_by_key(|(note, _)| note.num);
+        let (necessary, necessary_categories): (Vec<_>, Vec<_>) = necessary.into_iter().unzip();
+        let (optional, optional_categories): (Vec<_>, Vec<_>) = optional.into_iter().unzip();
+        let (mut necessary, mut optional) =
+            FileNotesStorage::<ReviewNote, ReviewNote>::from_written(necessary, optional, format)?;
+        let notes = necessary.iter_mut().chain(optional.iter_mut());
+        for (note, category) in
+            notes.zip(necessary_categories.into_iter().chain(optional_categories))
+        {
+            if let Some(category) = category {
+                note.set_category(category);
+            }
+        }
+        Ok((necessary, optional))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(text: &str, category: Option<&str>) -> ReviewNote {
+        let mut note = ReviewNote::new(text.to_string());
+        if let Some(category) = category {
+            note.set_category(category.to_string());
+        }
+        note
+    }
+
+    fn document(group_by: GroupBy) -> ReviewDocument {
+        ReviewDocument {
+            header: vec!["Author: Anton Kopanov(tg: @ak)".to_string()],
+            group_by,
+            necessary_notes: vec![
+                note("Unused variable", Some("Style")),
+                note("Off-by-one", None),
+                note("Magic number", Some("Style")),
+            ],
+            optional_notes: vec![note("Use const", Some("Style"))],
+            footer: Some("Good luck".to_string()),
+            ..ReviewDocument::default()
+        }
+    }
+
+    fn round_trip(group_by: GroupBy) -> (String, ReviewDocument) {
+        let format = NotesFormat::default();
+        let mut text = Vec::new();
+        document(group_by).render(&mut text, &format).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let parsed = ReviewDocument::parse(text.as_bytes(), &format).unwrap();
+        (text, parsed)
+    }
+
+    fn summary(notes: &[ReviewNote]) -> Vec<(String, Option<&str>)> {
+        notes
+            .iter()
+            .map(|note| (note.text(), note.category()))
+            .collect()
+    }
+
+    #[test]
+    fn review_grouped_by_category_is_read_back() {
+        let (text, parsed) = round_trip(GroupBy::Category);
+        assert!(text.contains("Style:\nNecessary:\n0) Unused variable\n2) Magic number\n"));
+        assert_eq!(
+            summary(&parsed.necessary_notes),
+            summary(&document(GroupBy::Category).necessary_notes)
+        );
+        assert_eq!(
+            summary(&parsed.optional_notes),
+            summary(&document(GroupBy::Category).optional_notes)
+        );
+        assert_eq!(parsed.footer.as_deref(), Some("Good luck"));
+    }
+
+    #[test]
+    fn review_grouped_by_section_is_read_back() {
+        let (_, parsed) = round_trip(GroupBy::Section);
+        let texts = parsed
+            .necessary_notes
+            .iter()
+            .map(|note| note.text())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["Unused variable", "Off-by-one", "Magic number"]);
+        assert_eq!(parsed.header, document(GroupBy::Section).header);
+    }
+
+    #[test]
+    fn shown_review_with_contents_is_read_back() {
+        let separator = ReviewDocument::AUTHOR_SEPARATOR;
+        let text = format!(
+            "Author: Anton Kopanov(tg: @ak){separator}Contents:\nNecessary:\n  0) Unused variable\
+             {separator}Necessary:\n0) Unused variable\nOptional:\n0) Use const\n\
+             {separator}Good luck\n"
+        );
+        let parsed = ReviewDocument::parse(text.as_bytes(), &NotesFormat::default()).unwrap();
+        assert_eq!(parsed.header, ["Author: Anton Kopanov(tg: @ak)"]);
+        assert!(parsed.contents);
+        assert_eq!(parsed.necessary_notes[0].text(), "Unused variable");
+        assert_eq!(parsed.optional_notes[0].text(), "Use const");
+        assert_eq!(parsed.footer.as_deref(), Some("Good luck"));
+
+        let text = format!("{text}{separator}More\n");
+        assert!(ReviewDocument::parse(text.as_bytes(), &NotesFormat::default()).is_err());
+    }
+
+    #[test]
+    fn reviews_file_is_read_as_notes() {
+        let text = format!("{}\nNecessary:\n0) Unused variable\n", FORMAT_HEADER);
+        let parsed = ReviewDocument::parse(text.as_bytes(), &NotesFormat::default()).unwrap();
+        assert!(parsed.header.is_empty());
+        assert_eq!(parsed.necessary_notes[0].text(), "Unused variable");
+        assert!(parsed.optional_notes.is_empty());
+        assert_eq!(parsed.footer, None);
+    }
+
+    #[test]
+    fn rendered_contents_are_read_back() {
+        let format = NotesFormat::default();
+        let document = ReviewDocument {
+            contents: true,
+            ..document(GroupBy::Category)
+        };
+        let mut text = Vec::new();
+        document.render(&mut text, &format).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("Contents:\nNecessary:\n  0) Unused variable\n"));
+        let parsed = ReviewDocument::parse(text.as_bytes(), &format).unwrap();
+        assert!(parsed.contents);
+        assert_eq!(parsed.header, document.header);
+        assert_eq!(
+            summary(&parsed.necessary_notes),
+            summary(&document.necessary_notes)
+        );
+        assert_eq!(
+            summary(&parsed.optional_notes),
+            summary(&document.optional_notes)
+        );
+        assert_eq!(parsed.footer, document.footer);
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use log::trace;
+
+use crate::messages::Message;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+fn read_all(source: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut source) = source {
+            let _ = source.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Run the external command, killing it if it doesn't finish in time
+pub(crate) fn run_with_timeout(command: &mut Command, timeout: Duration) -> anyhow::Result<Output> {
+    trace!("Run external command: {:?}", command);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            Message::CantRunExternalCommand.format(&[&format!("{:?}", command.get_program())])
+        })?;
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .context(Message::CantWaitExternalCommand.text())?
+        {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "{}",
+                Message::ExternalCommandTimedOut
+                    .format(&[&timeout.as_secs(), &format!("{:?}", command.get_program())])
+            );
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    trace!("External command finished with {}", status);
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_of_the_command_is_collected() {
+        let output = run_with_timeout(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn slow_command_is_killed() {
+        let start = Instant::now();
+        let result = run_with_timeout(Command::new("sleep").arg("10"), Duration::from_millis(100));
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Context;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+
+use crate::messages::Message;
+
+/// Commands without arguments which run right after the key press
+const INSTANT_KEYS: [(char, &str); 6] = [
+    ('s', "show"),
+    ('c', "complete"),
+    ('o', "open"),
+    ('k', "check"),
+    ('q', "quit"),
+    ('!', "repeat"),
+];
+
+/// Commands which need arguments, the key types the command and the rest of the line is read as usual
+const PREFIX_KEYS: [(char, &str); 4] = [
+    ('n', "new "),
+    ('a', "add "),
+    ('p', "peek "),
+    ('m', "merge "),
+];
+
+/// What the pressed key means
+pub(super) enum KeyInput {
+    /// Complete command
+    Command(&'static str),
+    /// Beginning of the command, the rest of the line is typed in the line mode
+    Line(String),
+}
+
+/// Wait for a key press in the raw mode of the terminal
+pub(super) fn read_key() -> anyhow::Result<KeyInput> {
+    terminal::enable_raw_mode().context(Message::CantSwitchTerminalToRawMode.text())?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(key),
+            Ok(_) => continue,
+            Err(err) => break Err(err),
+        }
+    };
+    // The terminal is returned to the line mode even if the key wasn't read
+    terminal::disable_raw_mode().context(Message::CantRestoreTerminal.text())?;
+    let key = key.context(Message::CantReadKey.text())?;
+    Ok(match key.code {
+        // Signals are not sent in the raw mode
+        KeyCode::Char('c' | 'd') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyInput::Command("quit")
+        }
+        KeyCode::Char(c) => match INSTANT_KEYS.iter().find(|(key, _)| *key == c) {
+            Some((_, command)) => KeyInput::Command(command),
+            None => KeyInput::Line(
+                PREFIX_KEYS
+                    .iter()
+                    .find(|(key, _)| *key == c)
+                    .map_or_else(|| c.to_string(), |(_, command)| command.to_string()),
+            ),
+        },
+        _ => KeyInput::Line(String::new()),
+    })
+}
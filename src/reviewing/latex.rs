@@ -0,0 +1,227 @@
+use std::io::Write;
+
+use crate::preparing::notes::NoteIds;
+use crate::reviewing::notes::ReviewNote;
+
+/// Escape characters which have special meaning in LaTeX
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\newline\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Review rendered with the `listings` package for code references
+pub(super) struct LatexReview<'a> {
+    pub header: Vec<String>,
+    pub necessary_notes: &'a [ReviewNote],
+    pub optional_notes: &'a [ReviewNote],
+    /// Notes fixed in the resubmission, written after the others
+    pub resolved_notes: &'a [ReviewNote],
+    /// Language of the code for `listings`
+    pub language: &'a str,
+    /// Text after the notes, omitted if empty
+    pub footer: &'a str,
+    pub ids: NoteIds,
+    /// Write the optional notes before the necessary ones
+    pub optional_first: bool,
+    /// Write the whole document instead of a fragment to include
+    pub standalone: bool,
+}
+
+impl LatexReview<'_> {
+    /// Cyrillic text needs its font encoding and hyphenation, the rest is written in English
+    fn is_cyrillic(&self) -> bool {
+        let texts = self
+            .header
+            .iter()
+            .cloned()
+            .chain([self.footer.to_string()])
+            .chain(
+                [
+                    self.necessary_notes,
+                    self.optional_notes,
+                    self.resolved_notes,
+                ]
+                .into_iter()
+                .flatten()
+                .map(|note| note.body()),
+            )
+            .collect::<Vec<_>>();
+        texts
+            .iter()
+            .flat_map(|text| text.chars())
+            .any(|c| matches!(c, '\u{0400}'..='\u{04FF}'))
+    }
+
+    pub(super) fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        if self.standalone {
+            writeln!(writer, "\\documentclass{{article}}")?;
+            writeln!(writer, "\\usepackage[utf8]{{inputenc}}")?;
+            if self.is_cyrillic() {
+                writeln!(writer, "\\usepackage[T2A]{{fontenc}}")?;
+                writeln!(writer, "\\usepackage[english,russian]{{babel}}")?;
+            } else {
+                writeln!(writer, "\\usepackage[T1]{{fontenc}}")?;
+                writeln!(writer, "\\usepackage[english]{{babel}}")?;
+            }
+            writeln!(writer, "\\usepackage{{listings}}")?;
+            writeln!(writer, "\\begin{{document}}")?;
+        }
+        writeln!(
+            writer,
+            "\\lstset{{language={{{}}}, basicstyle=\\ttfamily\\small, numbers=left, breaklines=true}}",
+            self.language
+        )?;
+        for line in &self.header {
+            writeln!(writer, "{}\\\\", escape(line))?;
+        }
+        if self.optional_first {
+            self.write_section(writer, "Optional", true, self.optional_notes)?;
+            self.write_section(writer, "Necessary", false, self.necessary_notes)?;
+        } else {
+            self.write_section(writer, "Necessary", false, self.necessary_notes)?;
+            self.write_section(writer, "Optional", true, self.optional_notes)?;
+        }
+        // Numbered as the necessary notes, as in the text review
+        self.write_section(writer, "Resolved", false, self.resolved_notes)?;
+        if !self.footer.is_empty() {
+            writeln!(writer, "\\bigskip")?;
+            writeln!(writer, "{}", escape(self.footer.trim_end()))?;
+        }
+        if self.standalone {
+            writeln!(writer, "\\end{{document}}")?;
+        }
+        writer.flush()
+    }
+
+    fn write_section(
+        &self,
+        writer: &mut impl Write,
+        title: &str,
+        optional: bool,
+        notes: &[ReviewNote],
+    ) -> std::io::Result<()> {
+        if notes.is_empty() {
+            return Ok(());
+        }
+        writeln!(writer, "\\section*{{{}}}", title)?;
+        writeln!(writer, "\\begin{{enumerate}}")?;
+        writeln!(
+            writer,
+            "\\setcounter{{enumi}}{{{}}}",
+            self.ids.first as i64 - 1
+        )?;
+        for (num, note) in notes.iter().enumerate() {
+            if self.ids.prefixed {
+                writeln!(
+                    writer,
+                    "\\item[{}] {}",
+                    self.ids.id(num, optional),
+                    escape(&note.body())
+                )?;
+            } else {
+                writeln!(writer, "\\item {}", escape(&note.body()))?;
+            }
+            for reference in note.references() {
+                if reference.inline {
+                    writeln!(
+                        writer,
+                        "\\emph{{Line {}:}} \\texttt{{{}}}\\\\",
+                        reference.rows.0,
+                        escape(reference.code().trim())
+                    )?;
+                    continue;
+                }
+                if reference.from_diff {
+                    writeln!(writer, "\\begin{{lstlisting}}[language={{}}, numbers=none]")?;
+                } else {
+                    writeln!(
+                        writer,
+                        "\\begin{{lstlisting}}[firstnumber={}]",
+                        reference.rows.0
+                    )?;
+                }
+                writeln!(writer, "{}", reference.code())?;
+                writeln!(writer, "\\end{{lstlisting}}")?;
+                for (line, comment) in &reference.marks {
+                    writeln!(writer, "\\emph{{Line {}: {}}}\\\\", line, escape(comment))?;
+                }
+            }
+            if let Some(suggestion) = note.suggestion() {
+                writeln!(writer, "Suggested:")?;
+                writeln!(writer, "\\begin{{lstlisting}}[numbers=none]")?;
+                writeln!(writer, "{}", suggestion)?;
+                writeln!(writer, "\\end{{lstlisting}}")?;
+            }
+        }
+        writeln!(writer, "\\end{{enumerate}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(notes: &[ReviewNote], resolved: &[ReviewNote]) -> String {
+        let mut text = Vec::new();
+        LatexReview {
+            header: vec!["Author: Anton Kopanov".to_string()],
+            necessary_notes: notes,
+            optional_notes: &[],
+            resolved_notes: resolved,
+            language: "C++",
+            footer: "",
+            ids: NoteIds::default(),
+            optional_first: false,
+            standalone: true,
+        }
+        .write(&mut text)
+        .unwrap();
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("50% of {x}_1"), "50\\% of \\{x\\}\\_1");
+        assert_eq!(escape("a\\b"), "a\\textbackslash{}b");
+    }
+
+    #[test]
+    fn english_review_has_no_cyrillic_packages() {
+        let text = render(&[ReviewNote::new("Unused variable".to_string())], &[]);
+        assert!(text.contains("\\usepackage[T1]{fontenc}"));
+        assert!(text.contains("\\usepackage[english]{babel}"));
+        assert!(!text.contains("russian"));
+    }
+
+    #[test]
+    fn russian_review_has_cyrillic_packages() {
+        let text = render(&[ReviewNote::new("Лишняя переменная".to_string())], &[]);
+        assert!(text.contains("\\usepackage[T2A]{fontenc}"));
+        assert!(text.contains("\\usepackage[english,russian]{babel}"));
+    }
+
+    #[test]
+    fn resolved_notes_have_their_section() {
+        let text = render(
+            &[ReviewNote::new("Unused variable".to_string())],
+            &[ReviewNote::new("Off-by-one".to_string())],
+        );
+        let necessary = text.find("\\section*{Necessary}").unwrap();
+        let resolved = text.find("\\section*{Resolved}").unwrap();
+        assert!(necessary < resolved);
+        assert!(text[resolved..].contains("\\item Off-by-one"));
+    }
+}
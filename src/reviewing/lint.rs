@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::messages::Message;
+use crate::preparing::context::ProjectContext;
+use crate::preparing::encoding::OutputEncoding;
+use crate::preparing::notes::{FileNotesStorage, Note, NotesFormat};
+use crate::preparing::task::TaskNode;
+use crate::reviewing::notes::ReviewNote;
+use crate::reviewing::session::SESSION_EXTENSION;
+
+/// Result of checking all notes and reviews files of the project
+pub(crate) struct LintReport {
+    pub checked: usize,
+    pub failures: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Parse every notes file and every reviews file the way the review does, without changing them.
+/// The numbers of notes are checked strictly, so a renumbered note is a failure here
+pub(crate) fn lint_project(context: &ProjectContext) -> anyhow::Result<LintReport> {
+    let mut format = context.settings.notes_format();
+    format.strict = true;
+    let mut report = LintReport {
+        checked: 0,
+        failures: Vec::new(),
+    };
+    for path in files(&context.project_dir.join("notes"))? {
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            report.check::<TaskNode>(path, &format, OutputEncoding::default());
+        }
+    }
+    for path in files(&context.project_dir.join("reviews"))? {
+        if !path.to_string_lossy().ends_with(SESSION_EXTENSION) {
+            report.check::<ReviewNote>(path, &format, context.settings.output_encoding);
+        }
+    }
+    Ok(report)
+}
+
+impl LintReport {
+    fn check<T: Note>(&mut self, path: PathBuf, format: &NotesFormat, encoding: OutputEncoding) {
+        self.checked += 1;
+        let result = fs::read(&path)
+            .context(Message::CantReadFile.text())
+            .and_then(|bytes| encoding.decode(&bytes))
+            .and_then(|text| FileNotesStorage::<T, T>::parse_text(&text, format));
+        if let Err(err) = result {
+            self.failures.push((path, err));
+        }
+    }
+}
+
+/// Files of the directory in the stable order
+fn files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| Message::CantReadPath.format(&[&dir.display()]))?
+    {
+        let path = entry
+            .with_context(|| Message::CantReadPath.format(&[&dir.display()]))?
+            .path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preparing::notes::FORMAT_HEADER;
+    use crate::testing::{project, TempDir};
+
+    #[test]
+    fn renumbered_notes_are_reported() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let notes = format!("{}\nNecessary:\n0) Use const\n", FORMAT_HEADER);
+        fs::write(dir.join("notes/t.txt"), notes).unwrap();
+        let review = format!("{}\nNecessary:\n1) Unused variable\n", FORMAT_HEADER);
+        fs::write(dir.join("reviews/t.txt"), review).unwrap();
+        fs::write(dir.join("reviews/t.session.json"), "not checked").unwrap();
+
+        let report = lint_project(&context).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.failures.len(), 1);
+        let (path, err) = &report.failures[0];
+        assert_eq!(path, &context.project_dir.join("reviews/t.txt"));
+        assert_eq!(
+            err.to_string(),
+            Message::IncorrectNoteNumberAtLine.format(&[&3, &1, &0])
+        );
+    }
+
+    #[test]
+    fn descending_reference_is_reported() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let separator = ReviewNote::NOTE_SEPARATOR;
+        let review = format!(
+            "{FORMAT_HEADER}\nNecessary:\n0) Unused variable{separator}   3:     return 0;\n   2:     int x;{separator}"
+        );
+        fs::write(dir.join("reviews/t.txt"), review).unwrap();
+
+        let report = lint_project(&context).unwrap();
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(
+            report.failures[0].1.root_cause().to_string(),
+            Message::ReferenceRowsNotInOrder.format(&[&"3, 2"])
+        );
+    }
+}
@@ -1,2 +1,13 @@
 pub mod review;
-mod notes;
\ No newline at end of file
+mod notes;
+pub mod external;
+mod diagnostics;
+mod diff;
+mod latex;
+pub mod stats;
+pub mod lint;
+mod document;
+mod tokens;
+mod session;
+mod keys;
+mod resubmission;
\ No newline at end of file
@@ -1,57 +1,497 @@
-use anyhow::Context;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use anyhow::{bail, ensure, Context};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use itertools::Itertools;
 use log::trace;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-use crate::preparing::notes::Note;
+use crate::messages::Message;
+use crate::preparing::notes::{Gutter, Note, NotesFormat};
+use crate::reviewing::diff::{header_rows, Hunk};
+use crate::reviewing::tokens::Tokens;
 
 use crate::separator;
-use const_format::{concatcp, str_repeat};
-
 
 #[derive(Debug, Clone)]
 pub(super) struct ReviewNote {
     text: String,
-    references: Vec<String>,
+    author: Option<String>,
+    /// Show the author tag in the text
+    show_author: bool,
+    /// Rubric category to group the shown review by
+    category: Option<String>,
+    references: Vec<CodeReference>,
+    /// Code proposed by the reviewer as the fix
+    suggestion: Option<String>,
+    /// Number of the task note the note was added from and whether that one is optional
+    template: Option<(usize, bool)>,
+    /// The student fixed the problem of the note in the resubmission
+    resolved: bool,
+    gutter: Gutter,
+}
+
+/// Lines of the code file quoted in the note
+#[derive(Debug, Clone)]
+pub(super) struct CodeReference {
+    pub file: PathBuf,
+    pub rows: (usize, usize),
+    /// The reference quotes a hunk of the diff `file` instead of the code
+    pub from_diff: bool,
+    /// All quoted lines are blank or comments
+    pub blank: bool,
+    /// Quoted lines as they are in the file
+    code: String,
+    /// Marked lines with the comments
+    pub marks: Vec<(usize, String)>,
+    /// First and last rows of the ignored lines, each run is quoted as one `...` line
+    pub collapsed: Vec<(usize, usize)>,
+    /// One line quoted after the text of the note as `(line 42: code)` instead of the block
+    pub inline: bool,
+    /// File name of the label read back, the file itself is known only from the link
+    label_file: Option<String>,
 }
 
+impl CodeReference {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Quoted lines with the gutter
+    fn text(&self, gutter: &Gutter) -> String {
+        if self.from_diff {
+            return self.code.clone();
+        }
+        let link = match &gutter.link_base {
+            Some(base) if !self.file.as_os_str().is_empty() => {
+                let path = self.file.strip_prefix(base).unwrap_or(&self.file);
+                format!("{}:{}\n", path.display(), self.rows.0)
+            }
+            _ => String::new(),
+        };
+        let mut next_row = self.rows.0;
+        let code = self
+            .code
+            .lines()
+            .map(|line| {
+                let row = next_row;
+                next_row = match self.collapsed.iter().find(|(start, _)| *start == row) {
+                    Some((_, end)) => end + 1,
+                    None => row + 1,
+                };
+                let prefix = gutter.prefix(row);
+                let mut text = format!("{}{}", prefix, line);
+                let indent = line.len() - line.trim_start().len();
+                for (_, comment) in self.marks.iter().filter(|(mark, _)| *mark == row) {
+                    text.push_str(&format!(
+                        "\n{}{}^ {}",
+                        " ".repeat(prefix.chars().count()),
+                        &line[..indent],
+                        comment
+                    ));
+                }
+                text
+            })
+            .join("\n");
+        link + &code
+    }
+}
+
+impl CodeReference {
+    /// Reference written after the text of the note, e.g. ` (line 42: return 0;)`
+    fn inline_text(&self) -> String {
+        format!(" ({} {}: {})", INLINE_PREFIX, self.rows.0, self.code.trim())
+    }
+
+    /// Read the inline references from the end of the text of the note, returns the rest of the text
+    fn parse_inline(mut text: &str) -> (&str, Vec<Self>) {
+        let mut references = Vec::new();
+        while let Some(start) = text
+            .strip_suffix(')')
+            .and_then(|text| text.rfind(&format!(" ({} ", INLINE_PREFIX)))
+        {
+            let inner = &text[start + INLINE_PREFIX.len() + 3..text.len() - 1];
+            let Some((row, code)) = inner.split_once(": ") else {
+                break;
+            };
+            let Ok(row) = row.parse() else {
+                break;
+            };
+            references.insert(
+                0,
+                Self {
+                    file: PathBuf::new(),
+                    rows: (row, row),
+                    from_diff: false,
+                    blank: false,
+                    code: code.to_string(),
+                    marks: Vec::new(),
+                    collapsed: Vec::new(),
+                    inline: true,
+                    label_file: None,
+                },
+            );
+            text = &text[..start];
+        }
+        (text, references)
+    }
+
+    /// Line before the reference in the notes with several references, e.g. `Reference 2 (main.cpp):`
+    fn label(&self, num: usize) -> String {
+        let name = self
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .or_else(|| self.label_file.clone());
+        match name {
+            Some(name) if !self.from_diff => {
+                format!("{} {} ({}):", REFERENCE_LABEL, num, name)
+            }
+            _ => format!("{} {}:", REFERENCE_LABEL, num),
+        }
+    }
+
+    /// Read the reference back from its text. The references written with the default gutter are also read, as
+    /// the gutter setting could be changed after they were written.
+    fn parse(text: &str, gutter: &Gutter) -> anyhow::Result<Self> {
+        // The code without line numbers matches any text, so the numbered rows are tried first
+        let default = Gutter::default();
+        let (first, second) = if gutter.line_numbers {
+            (gutter, &default)
+        } else {
+            (&default, gutter)
+        };
+        Self::parse_with_gutter(text, first)
+            .or_else(|err| Self::parse_with_gutter(text, second).map_err(|_| err))
+    }
+
+    fn parse_with_gutter(text: &str, gutter: &Gutter) -> anyhow::Result<Self> {
+        let mut label_file = None;
+        let text = match text.trim_start_matches('\n').split_once('\n') {
+            Some((first, rest)) if first.starts_with(REFERENCE_LABEL) && first.ends_with(':') => {
+                label_file = first
+                    .strip_suffix("):")
+                    .and_then(|label| label.split_once(" ("))
+                    .map(|(_, name)| name.to_string());
+                rest
+            }
+            _ => text,
+        };
+        let mut lines = text.lines().peekable();
+        let first = *lines.peek().context(Message::EmptyReference.text())?;
+        if let Some(header) = text.lines().nth(1).filter(|line| line.starts_with("@@")) {
+            let rows = header_rows(header)?;
+            trace!("Hunk reference of {} read back", first);
+            return Ok(Self {
+                file: PathBuf::new(),
+                rows,
+                from_diff: true,
+                blank: false,
+                code: text.to_string(),
+                marks: Vec::new(),
+                collapsed: Vec::new(),
+                inline: false,
+                label_file: None,
+            });
+        }
+        let mut rows = Vec::new();
+        let mut code = Vec::new();
+        let mut marks = Vec::new();
+        // The `file:line` link gives the file relative to the project
+        let mut file = PathBuf::new();
+        let mut link_row = None;
+        if let Some((path, row)) = first.rsplit_once(':') {
+            if let Ok(row) = row.parse::<usize>() {
+                file = PathBuf::from(path);
+                link_row = Some(row);
+                lines.next();
+            }
+        }
+        let separator = gutter.separator.trim_end();
+        for (index, line) in lines.enumerate() {
+            let trimmed = line.trim_start();
+            if let (Some(comment), Some(row)) = (trimmed.strip_prefix("^ "), rows.last()) {
+                marks.push((*row, comment.to_string()));
+                continue;
+            }
+            if !gutter.line_numbers {
+                // Without the numbers the rows go one after another from the link
+                rows.push(link_row.unwrap_or(1) + index - marks.len());
+                code.push(line);
+                continue;
+            }
+            let digits = trimmed
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(trimmed.len());
+            let numbered = trimmed[..digits]
+                .parse::<usize>()
+                .ok()
+                .zip(trimmed[digits..].strip_prefix(separator));
+            match numbered {
+                Some((row, rest)) => {
+                    rows.push(row);
+                    code.push(rest.strip_prefix(' ').unwrap_or(rest));
+                }
+                None => bail!("{}", Message::ReferenceLineWithoutNumber.format(&[&line])),
+            }
+        }
+        ensure!(!rows.is_empty(), Message::ReferenceHasNoQuotedLines.text());
+        // The hand-edited numbers going down would make the reference end before its start
+        ensure!(
+            rows.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{}",
+            Message::ReferenceRowsNotInOrder.format(&[&rows.iter().join(", ")])
+        );
+        let blank = code.iter().all(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with("//")
+        });
+        // The ignored lines are known by the gap in the numbers after the marker
+        let collapsed = rows
+            .iter()
+            .zip(&code)
+            .zip(rows.iter().skip(1))
+            .filter(|((row, line), next)| **line == COLLAPSED_MARKER && **next > **row + 1)
+            .map(|((row, _), next)| (*row, next - 1))
+            .collect();
+        Ok(Self {
+            file,
+            rows: (rows[0], rows[rows.len() - 1]),
+            from_diff: false,
+            blank,
+            code: code.join("\n"),
+            marks,
+            collapsed,
+            inline: false,
+            label_file,
+        })
+    }
+}
+
+/// Data of the note which is not shown, kept in the reviews file after the note
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct NoteMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<(usize, bool)>,
+}
+
+impl NoteMeta {
+    fn is_empty(&self) -> bool {
+        self.author.is_none() && self.category.is_none() && self.template.is_none()
+    }
+
+    /// Split the meta line off the end of the stored note
+    fn split(text: &str) -> (&str, Option<Self>) {
+        let meta = text.trim_end().rsplit_once('\n').and_then(|(rest, line)| {
+            let meta = serde_json::from_str(line.strip_prefix(META_PREFIX)?).ok()?;
+            Some((rest, meta))
+        });
+        match meta {
+            Some((rest, meta)) => (rest, Some(meta)),
+            None => (text, None),
+        }
+    }
+}
+
+/// Start of the last line of the stored note with its `NoteMeta`
+const META_PREFIX: &str = "Meta: ";
+
+/// Author of the `[author] ` tag before the text and the rest of the text
+fn split_author_tag(body: &str) -> Option<(&str, &str)> {
+    let (author, text) = body.strip_prefix('[')?.split_once("] ")?;
+    let plain = !author.is_empty() && !author.contains(['[', ']', '\n']);
+    plain.then_some((author, text))
+}
+
+/// Word before the line number of the inline reference
+const INLINE_PREFIX: &str = "line";
+
+/// Beginning of the label of each reference when the note has several of them
+const REFERENCE_LABEL: &str = "Reference";
+
+/// Line quoted instead of the ignored lines of the reference
+const COLLAPSED_MARKER: &str = "...";
+
 pub(super) enum NoteType {
     Necessary,
     Optional,
-    NecessaryWithReference((usize, usize)),
-    OptionalWithReference((usize, usize)),
-}
-
-pub(super) fn parse_type<'a>(
-    tokens: impl Iterator<Item = &'a str>,
-) -> anyhow::Result<(NoteType, impl Iterator<Item = &'a str>)> {
-    let mut tokens = tokens.peekable();
-    let optional = matches!(*tokens.peek().context("No text in note")?, "optional" | "o");
-    if optional {
-        tokens.next();
-    }
-    let reference = matches!(
-        *tokens.peek().context("No text in note")?,
-        "reference" | "r"
-    );
-    if reference {
-        tokens.next();
+    NecessaryWithReference(CodeRange),
+    OptionalWithReference(CodeRange),
+    /// Reference to the same lines as the previous one
+    NecessaryWithSameReference,
+    OptionalWithSameReference,
+    /// Reference to the lines of the named bookmark
+    NecessaryWithBookmark(String),
+    OptionalWithBookmark(String),
+    NecessaryWithHunk((PathBuf, usize)),
+    OptionalWithHunk((PathBuf, usize)),
+}
+
+impl NoteType {
+    /// The same type for the optional note
+    pub fn into_optional(self) -> Self {
+        match self {
+            NoteType::Necessary => NoteType::Optional,
+            NoteType::NecessaryWithReference(range) => NoteType::OptionalWithReference(range),
+            NoteType::NecessaryWithSameReference => NoteType::OptionalWithSameReference,
+            NoteType::NecessaryWithBookmark(name) => NoteType::OptionalWithBookmark(name),
+            NoteType::NecessaryWithHunk(hunk) => NoteType::OptionalWithHunk(hunk),
+            optional => optional,
+        }
+    }
+}
+
+/// Rows of the code to quote with marks on some of them
+#[derive(Debug, Clone)]
+pub(crate) struct CodeRange {
+    pub rows: (LineNumber, LineNumber),
+    /// Lines to point at with the caret and their comments
+    pub marks: Vec<(LineNumber, String)>,
+    /// Quote the line after the text of the note, see `CodeReference::inline`
+    pub inline: bool,
+}
+
+impl From<(LineNumber, LineNumber)> for CodeRange {
+    fn from(rows: (LineNumber, LineNumber)) -> Self {
+        Self {
+            rows,
+            marks: Vec::new(),
+            inline: false,
+        }
+    }
+}
+
+/// Line of the code file, `-N` means the N-th line from the end
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub(crate) enum LineNumber {
+    FromStart(usize),
+    FromEnd(usize),
+}
+
+impl LineNumber {
+    /// Number of the line from the start of the file with `lines_count` lines
+    fn resolve(self, lines_count: usize) -> anyhow::Result<usize> {
+        match self {
+            LineNumber::FromStart(num) => Ok(num),
+            LineNumber::FromEnd(num) => {
+                ensure!(
+                    num <= lines_count,
+                    "{}",
+                    Message::LineFromEndOutOfFile.format(&[&num, &lines_count])
+                );
+                Ok(lines_count + 1 - num)
+            }
+        }
+    }
+}
+
+impl Display for LineNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineNumber::FromStart(num) => write!(f, "{}", num),
+            LineNumber::FromEnd(num) => write!(f, "-{}", num),
+        }
+    }
+}
+
+impl FromStr for LineNumber {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (num, from_end) = match s.strip_prefix('-') {
+            Some(num) => (num, true),
+            None => (s, false),
+        };
+        let num = num.parse::<usize>()?;
+        ensure!(num > 0, Message::LinesNumberedFromOne.text());
+        if from_end {
+            Ok(LineNumber::FromEnd(num))
+        } else {
+            Ok(LineNumber::FromStart(num))
+        }
+    }
+}
+
+pub(super) fn parse_type(mut tokens: Tokens<'_>) -> anyhow::Result<(NoteType, Tokens<'_>)> {
+    tokens.peek().context(Message::NoTextInNote.text())?;
+    let optional = tokens.next_if_any(&["optional", "o"]);
+    tokens.peek().context(Message::NoTextInNote.text())?;
+    if tokens.next_if_any(&["hunk", "h"]) {
+        let diff = tokens
+            .next()
+            .context(Message::NoDiffFileInReference.text())?
+            .into();
+        let index = tokens
+            .next()
+            .context(Message::NoHunkNumberInReference.text())?
+            .parse()
+            .context(Message::IncorrectHunkNumberInReference.text())?;
+        if optional {
+            Ok((NoteType::OptionalWithHunk((diff, index)), tokens))
+        } else {
+            Ok((NoteType::NecessaryWithHunk((diff, index)), tokens))
+        }
+    } else if tokens.next_if_any(&["inline", "ri"]) {
+        let line = tokens
+            .next()
+            .context(Message::NoLineNumberInReference.text())?
+            .parse()
+            .context(Message::IncorrectLineNumberInReference.text())?;
+        let mut range = CodeRange::from((line, line));
+        range.inline = true;
+        if optional {
+            Ok((NoteType::OptionalWithReference(range), tokens))
+        } else {
+            Ok((NoteType::NecessaryWithReference(range), tokens))
+        }
+    } else if tokens.next_if_any(&["reference", "r"]) {
+        if tokens.next_if_any(&["same"]) {
+            if optional {
+                return Ok((NoteType::OptionalWithSameReference, tokens));
+            } else {
+                return Ok((NoteType::NecessaryWithSameReference, tokens));
+            }
+        }
+        if tokens.next_if_any(&["bookmark", "bm"]) {
+            let name = tokens
+                .next()
+                .context(Message::NoBookmarkNameInReference.text())?;
+            if optional {
+                return Ok((NoteType::OptionalWithBookmark(name), tokens));
+            } else {
+                return Ok((NoteType::NecessaryWithBookmark(name), tokens));
+            }
+        }
         let first = tokens
             .next()
-            .context("No first number in reference")?
+            .context(Message::NoFirstNumberInReference.text())?
             .parse()
-            .context("Incorrect first number in reference")?;
+            .context(Message::IncorrectFirstNumberInReference.text())?;
         let second = tokens
             .next()
-            .context("No second number in reference")?
+            .context(Message::NoSecondNumberInReference.text())?
             .parse()
-            .context("Incorrect second number in reference")?;
+            .context(Message::IncorrectSecondNumberInReference.text())?;
+        let mut range = CodeRange::from((first, second));
+        while tokens.next_if_any(&["mark"]) {
+            let line = tokens
+                .next()
+                .context(Message::NoLineNumberInMark.text())?
+                .parse()
+                .context(Message::IncorrectLineNumberInMark.text())?;
+            let comment = tokens.next().context(Message::NoCommentInMark.text())?;
+            range.marks.push((line, comment));
+        }
         if optional {
-            Ok((NoteType::OptionalWithReference((first, second)), tokens))
+            Ok((NoteType::OptionalWithReference(range), tokens))
         } else {
-            Ok((NoteType::NecessaryWithReference((first, second)), tokens))
+            Ok((NoteType::NecessaryWithReference(range), tokens))
         }
     } else if optional {
         Ok((NoteType::Optional, tokens))
@@ -64,26 +504,234 @@ impl ReviewNote {
     pub fn new(text: String) -> Self {
         Self {
             text,
+            author: None,
+            show_author: false,
+            category: None,
             references: Vec::new(),
+            suggestion: None,
+            template: None,
+            resolved: false,
+            gutter: Gutter::default(),
         }
     }
 
-    const NOTE_SEPARATOR: &'static str = separator!("-", 50);
+    pub fn set_author(&mut self, author: String) {
+        self.author = Some(author);
+    }
+
+    /// Forget the author, the shown tag is kept in the text
+    pub fn unset_author(&mut self) {
+        self.text = self.body();
+        self.author = None;
+        self.show_author = false;
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn set_category(&mut self, category: String) {
+        self.category = Some(category);
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    pub fn set_gutter(&mut self, gutter: Gutter) {
+        self.gutter = gutter;
+    }
+
+    pub fn set_show_author(&mut self, show_author: bool) {
+        self.show_author = show_author;
+    }
+
+    pub fn set_suggestion(&mut self, suggestion: String) {
+        self.suggestion = Some(suggestion);
+    }
+
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    pub fn set_resolved(&mut self, resolved: bool) {
+        self.resolved = resolved;
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+    }
+
+    pub fn set_template(&mut self, num: usize, optional: bool) {
+        self.template = Some((num, optional));
+    }
+
+    /// Task note the note was added from, `None` for the notes written in the review
+    pub fn template(&self) -> Option<(usize, bool)> {
+        self.template
+    }
+
+    /// Separator around the references of the note
+    pub const NOTE_SEPARATOR: &'static str = separator!("-");
+    const SUGGESTION_HEADER: &'static str = "\nSuggested:\n```\n";
+    const SUGGESTION_FOOTER: &'static str = "\n```";
+    /// Tag before the text of the resolved note in the reviews file
+    const RESOLVED_TAG: &'static str = "[resolved] ";
+    /// Comment of the last line of the reference cut at the end of the file
+    const CLAMPED_MARK: &'static str = "(clamped to EOF)";
 
-    pub fn add_code_reference(&mut self, file: File, row_numbers: (usize, usize)) {
-        let text = BufReader::new(file)
+    /// Quote the rows of the code from the file, with `clamp` the end past the file is moved to its last line.
+    ///
+    /// Runs of unmarked lines matching one of the `ignore` patterns are quoted as one `...` line.
+    pub fn add_code_reference(
+        &mut self,
+        file_path: PathBuf,
+        code: &str,
+        range: &CodeRange,
+        clamp: bool,
+        ignore: &[Regex],
+    ) -> anyhow::Result<()> {
+        let lines_count = code.lines().count();
+        ensure!(lines_count > 0, Message::EmptyCodeFile.text());
+        let rows = range.rows;
+        let mut row_numbers = (rows.0.resolve(lines_count)?, rows.1.resolve(lines_count)?);
+        let clamped = clamp && row_numbers.1 > lines_count;
+        if clamped {
+            row_numbers.1 = lines_count;
+        }
+        ensure!(
+            row_numbers.0 <= row_numbers.1,
+            "{}",
+            Message::ReferenceStartAfterEnd.format(&[&row_numbers.0, &row_numbers.1])
+        );
+        ensure!(
+            row_numbers.1 <= lines_count,
+            "{}",
+            Message::ReferenceOutOfFile.format(&[&row_numbers.0, &row_numbers.1, &lines_count])
+        );
+        let mut marks = range
+            .marks
+            .iter()
+            .map(|(line, comment)| {
+                let line = line.resolve(lines_count)?;
+                ensure!(
+                    (row_numbers.0..=row_numbers.1).contains(&line),
+                    "{}",
+                    Message::MarkOutOfReference.format(&[&line, &row_numbers.0, &row_numbers.1])
+                );
+                Ok((line, comment.clone()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if clamped {
+            marks.push((row_numbers.1, Self::CLAMPED_MARK.to_string()));
+        }
+        ensure!(
+            !range.inline || (row_numbers.0 == row_numbers.1 && marks.is_empty()),
+            Message::IncorrectInlineReference.text()
+        );
+        let lines = code
             .lines()
             .enumerate()
             .skip(row_numbers.0 - 1)
             .take(row_numbers.1 - row_numbers.0 + 1)
-            .map(|(i, line)| format!("{:4}: {}", i + 1, line.unwrap()))
-            .join("\n");
+            .collect::<Vec<_>>();
+        let blank = lines.iter().all(|(_, line)| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with("//")
+        });
+        let mut collapsed: Vec<(usize, usize)> = Vec::new();
+        let mut quoted = Vec::new();
+        for (index, line) in &lines {
+            let row = index + 1;
+            let ignored = ignore.iter().any(|pattern| pattern.is_match(line))
+                && !marks.iter().any(|(mark, _)| *mark == row);
+            if !ignored {
+                quoted.push(*line);
+                continue;
+            }
+            match collapsed.last_mut() {
+                Some((_, end)) if *end + 1 == row && quoted.last() == Some(&COLLAPSED_MARKER) => {
+                    *end = row
+                }
+                _ => {
+                    collapsed.push((row, row));
+                    quoted.push(COLLAPSED_MARKER);
+                }
+            }
+        }
+        let code = quoted.join("\n");
         trace!(
             "Reference added by rows: {}, {}",
             row_numbers.0,
             row_numbers.1
         );
-        self.references.push(text);
+        self.references.push(CodeReference {
+            file: file_path,
+            rows: row_numbers,
+            from_diff: false,
+            blank,
+            code,
+            marks,
+            collapsed,
+            inline: range.inline,
+            label_file: None,
+        });
+        Ok(())
+    }
+
+    /// Quote the hunk of the diff from the file
+    pub fn add_hunk_reference(&mut self, diff_path: PathBuf, hunk: Hunk) {
+        trace!("Reference added by hunk of {}", hunk.file);
+        self.references.push(CodeReference {
+            file: diff_path,
+            rows: hunk.new_rows(),
+            from_diff: true,
+            blank: false,
+            marks: Vec::new(),
+            collapsed: Vec::new(),
+            inline: false,
+            label_file: None,
+            code: format!("{}\n{}", hunk.file, hunk.text),
+        });
+    }
+
+    pub fn references(&self) -> &[CodeReference] {
+        &self.references
+    }
+
+    /// Drop the reference, e.g. the one declined by the reviewer
+    pub fn remove_reference(&mut self, index: usize) {
+        self.references.remove(index);
+    }
+
+    /// Quote the references of one line without marks after the text, see `CodeReference::inline`
+    pub fn inline_single_line_references(&mut self) {
+        for reference in &mut self.references {
+            if reference.rows.0 == reference.rows.1
+                && !reference.from_diff
+                && reference.marks.is_empty()
+                && reference.collapsed.is_empty()
+            {
+                reference.inline = true;
+            }
+        }
+    }
+
+    /// Make the relative files of the references read back from the text absolute
+    pub fn resolve_reference_files(&mut self, base: &Path) {
+        for reference in &mut self.references {
+            if reference.file.is_relative() && !reference.file.as_os_str().is_empty() {
+                reference.file = base.join(&reference.file);
+            }
+        }
+    }
+
+    /// Text of the note with the author tag, without references
+    pub fn body(&self) -> String {
+        match &self.author {
+            Some(author) if self.show_author => format!("[{}] {}", author, self.text),
+            _ => self.text.clone(),
+        }
     }
 
     pub fn text_to_storage(self) -> String {
@@ -91,21 +739,492 @@ impl ReviewNote {
     }
 }
 
-impl From<String> for ReviewNote {
-    fn from(text: String) -> Self {
-        Self::new(text)
+impl Note for ReviewNote {
+    /// Read the note back from its text, references are recognized by the separators
+    fn parse(mut text: String, format: &NotesFormat) -> anyhow::Result<Self> {
+        let (rest, meta) = NoteMeta::split(&text);
+        let meta = meta.unwrap_or_default();
+        text.truncate(rest.len());
+        // The line break after the last separator may be lost with the trailing empty line
+        if text.ends_with(Self::NOTE_SEPARATOR.trim_end()) {
+            text.push('\n');
+        }
+        let mut suggestion = None;
+        if let Some(start) = text.rfind(Self::SUGGESTION_HEADER) {
+            let block = &text[start + Self::SUGGESTION_HEADER.len()..];
+            if let Some(code) = block.trim_end().strip_suffix(Self::SUGGESTION_FOOTER) {
+                suggestion = Some(code.to_string());
+                text.truncate(start);
+                if text.ends_with(Self::NOTE_SEPARATOR.trim_end()) {
+                    text.push('\n');
+                }
+            }
+        }
+        let mut parts = text.split(Self::NOTE_SEPARATOR);
+        let (body, inline_references) =
+            CodeReference::parse_inline(parts.next().unwrap_or_default());
+        let (body, resolved) = match body.strip_prefix(Self::RESOLVED_TAG) {
+            Some(body) => (body, true),
+            None => (body, false),
+        };
+        // The shown review has no meta, any tag there is taken for the author
+        let tag = split_author_tag(body)
+            .filter(|(author, _)| meta.author.as_deref().is_none_or(|name| name == *author));
+        let mut note = match tag {
+            Some((author, body)) => {
+                let mut note = Self::new(body.to_string());
+                note.author = Some(author.to_string());
+                note.show_author = true;
+                note
+            }
+            None => {
+                let mut note = Self::new(body.to_string());
+                note.author = meta.author;
+                note
+            }
+        };
+        note.resolved = resolved;
+        note.category = meta.category;
+        note.template = meta.template;
+        note.suggestion = suggestion;
+        note.references = inline_references;
+        for (index, part) in parts.filter(|part| !part.trim().is_empty()).enumerate() {
+            let reference = CodeReference::parse(part, &format.gutter)
+                .with_context(|| Message::CantReadReference.format(&[&(index + 1)]))?;
+            note.references.push(reference);
+        }
+        note.gutter = format.gutter.clone();
+        Ok(note)
     }
-}
 
-impl Note for ReviewNote {
     fn text(&self) -> String {
-        let mut text = self.text.clone();
-        let references = self.references.join(Self::NOTE_SEPARATOR);
+        let mut text = self.body();
+        if self.resolved {
+            text.insert_str(0, Self::RESOLVED_TAG);
+        }
+        for reference in self.references.iter().filter(|reference| reference.inline) {
+            text.push_str(&reference.inline_text());
+        }
+        let blocks = self
+            .references
+            .iter()
+            .filter(|reference| !reference.inline)
+            .collect::<Vec<_>>();
+        let labeled = blocks.len() > 1;
+        let references = blocks
+            .into_iter()
+            .enumerate()
+            .map(|(index, reference)| {
+                let text = reference.text(&self.gutter);
+                if labeled {
+                    format!("{}\n{}", reference.label(index + 1), text)
+                } else {
+                    text
+                }
+            })
+            .join(Self::NOTE_SEPARATOR);
         if !references.is_empty() {
             text.push_str(Self::NOTE_SEPARATOR);
             text.push_str(&references);
             text.push_str(Self::NOTE_SEPARATOR);
         }
+        if let Some(suggestion) = &self.suggestion {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&Self::SUGGESTION_HEADER[1..]);
+            text.push_str(suggestion);
+            text.push_str(Self::SUGGESTION_FOOTER);
+        }
+        text
+    }
+
+    fn stored_text(&self) -> String {
+        let mut text = self.text();
+        let meta = NoteMeta {
+            author: self.author.clone(),
+            category: self.category.clone(),
+            template: self.template,
+        };
+        if !meta.is_empty() {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(META_PREFIX);
+            text.push_str(&serde_json::to_string(&meta).expect("Meta is always serializable"));
+        }
         text
     }
+
+    fn merge(&mut self, other: Self) {
+        self.text.push(' ');
+        self.text.push_str(&other.text);
+        self.references.extend(other.references);
+        if self.suggestion.is_none() {
+            self.suggestion = other.suggestion;
+        }
+        if self.template.is_none() {
+            self.template = other.template;
+        }
+        if self.category.is_none() {
+            self.category = other.category;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preparing::notes::{FileNotesStorage, NotesFormat};
+
+    fn parse(text: &str) -> (Vec<ReviewNote>, Vec<ReviewNote>) {
+        FileNotesStorage::<ReviewNote, ReviewNote>::parse_text(text, &NotesFormat::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn legacy_example_with_references_is_parsed() {
+        let (necessary, optional) =
+            parse(include_str!("../../project_example/reviews/example.txt"));
+        assert_eq!(necessary.len(), 1);
+        assert_eq!(necessary[0].body(), "Не используйте using namespace std;");
+        assert_eq!(necessary[0].references[0].rows, (1, 1));
+        assert_eq!(necessary[0].references[0].code(), "using namespace std;");
+        assert_eq!(optional.len(), 1);
+        assert_eq!(optional[0].references[0].rows, (11, 11));
+    }
+
+    const CODE: &str = "int main() {\n    int x;\n    return 0;\n}\n";
+
+    fn referenced_note(format: &NotesFormat) -> ReviewNote {
+        let mut note = ReviewNote::new("Unused variable".to_string());
+        note.set_gutter(format.gutter.clone());
+        let mut range = CodeRange::from((LineNumber::FromStart(2), LineNumber::FromStart(3)));
+        range
+            .marks
+            .push((LineNumber::FromStart(2), "never read".to_string()));
+        note.add_code_reference(PathBuf::from("main.cpp"), CODE, &range, false, &[])
+            .unwrap();
+        note
+    }
+
+    fn round_trip(format: &NotesFormat) {
+        let note = referenced_note(format);
+        let parsed = ReviewNote::parse(note.text(), format).unwrap();
+        assert_eq!(parsed.text(), note.text());
+        let reference = &parsed.references()[0];
+        assert_eq!(reference.rows, (2, 3));
+        assert_eq!(reference.code(), "    int x;\n    return 0;");
+        assert_eq!(reference.marks, [(2, "never read".to_string())]);
+    }
+
+    #[test]
+    fn references_round_trip_with_default_gutter() {
+        round_trip(&NotesFormat::default());
+    }
+
+    #[test]
+    fn references_round_trip_with_configured_gutter() {
+        let mut format = NotesFormat::default();
+        format.gutter.separator = " | ".to_string();
+        round_trip(&format);
+    }
+
+    #[test]
+    fn references_without_line_numbers_are_read_from_the_link() {
+        let mut format = NotesFormat::default();
+        format.gutter.line_numbers = false;
+        format.gutter.link_base = Some(PathBuf::new());
+        round_trip(&format);
+    }
+
+    #[test]
+    fn references_of_the_default_gutter_are_read_after_the_change() {
+        let note = referenced_note(&NotesFormat::default());
+        let mut format = NotesFormat::default();
+        format.gutter.separator = " | ".to_string();
+        let parsed = ReviewNote::parse(note.text(), &format).unwrap();
+        assert_eq!(parsed.references()[0].rows, (2, 3));
+    }
+
+    #[test]
+    fn unreadable_reference_is_an_error() {
+        let text = format!(
+            "Unused variable{}   2: int x;\nnot a quoted line{}",
+            ReviewNote::NOTE_SEPARATOR,
+            ReviewNote::NOTE_SEPARATOR
+        );
+        assert!(ReviewNote::parse(text, &NotesFormat::default()).is_err());
+    }
+
+    #[test]
+    fn descending_line_numbers_are_an_error() {
+        let text = format!(
+            "Unused variable{}   3:     return 0;\n   2:     int x;{}",
+            ReviewNote::NOTE_SEPARATOR,
+            ReviewNote::NOTE_SEPARATOR
+        );
+        let err = ReviewNote::parse(text, &NotesFormat::default()).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            Message::ReferenceRowsNotInOrder.format(&[&"3, 2"])
+        );
+    }
+
+    #[test]
+    fn author_round_trips_through_the_reviews_file() {
+        for show_author in [false, true] {
+            let mut note = ReviewNote::new("Unused variable".to_string());
+            note.set_author("Ivan Ivanov".to_string());
+            note.set_show_author(show_author);
+            let parsed = ReviewNote::parse(note.stored_text(), &NotesFormat::default()).unwrap();
+            assert_eq!(parsed.author(), Some("Ivan Ivanov"));
+            assert_eq!(parsed.show_author, show_author);
+            assert_eq!(parsed.text(), note.text());
+            assert_eq!(parsed.stored_text(), note.stored_text());
+        }
+    }
+
+    #[test]
+    fn meta_follows_references() {
+        let format = NotesFormat::default();
+        let mut note = referenced_note(&format);
+        note.set_author("Ivan Ivanov".to_string());
+        let parsed = ReviewNote::parse(note.stored_text(), &format).unwrap();
+        assert_eq!(parsed.author(), Some("Ivan Ivanov"));
+        assert_eq!(parsed.text(), note.text());
+    }
+
+    #[test]
+    fn author_tag_of_the_shown_review_is_read() {
+        let parsed = ReviewNote::parse(
+            "[Ivan Ivanov] Unused variable".to_string(),
+            &NotesFormat::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed.author(), Some("Ivan Ivanov"));
+        assert_eq!(parsed.body(), "[Ivan Ivanov] Unused variable");
+
+        let parsed = ReviewNote::parse(
+            "[[nodiscard]] is missing".to_string(),
+            &NotesFormat::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed.author(), None);
+        assert_eq!(parsed.body(), "[[nodiscard]] is missing");
+    }
+
+    #[test]
+    fn unset_author_keeps_the_tag() {
+        let mut parsed = ReviewNote::parse(
+            "[TODO] Unused variable".to_string(),
+            &NotesFormat::default(),
+        )
+        .unwrap();
+        parsed.unset_author();
+        assert_eq!(parsed.author(), None);
+        assert_eq!(parsed.text(), "[TODO] Unused variable");
+    }
+
+    #[test]
+    fn category_round_trips_through_the_reviews_file() {
+        let mut note = ReviewNote::new("Unused variable".to_string());
+        note.set_category("Style".to_string());
+        let parsed = ReviewNote::parse(note.stored_text(), &NotesFormat::default()).unwrap();
+        assert_eq!(parsed.category(), Some("Style"));
+        assert_eq!(parsed.text(), "Unused variable");
+    }
+
+    #[test]
+    fn template_round_trips_through_the_reviews_file() {
+        let mut note = ReviewNote::new("Unused variable".to_string());
+        note.set_template(2, true);
+        let parsed = ReviewNote::parse(note.stored_text(), &NotesFormat::default()).unwrap();
+        assert_eq!(parsed.template(), Some((2, true)));
+        assert_eq!(parsed.text(), "Unused variable");
+    }
+
+    fn reference(range: CodeRange) -> anyhow::Result<CodeReference> {
+        let mut note = ReviewNote::new("Note".to_string());
+        note.add_code_reference(PathBuf::from("main.cpp"), CODE, &range, false, &[])?;
+        Ok(note.references()[0].clone())
+    }
+
+    fn rows(first: &str, second: &str) -> CodeRange {
+        CodeRange::from((first.parse().unwrap(), second.parse().unwrap()))
+    }
+
+    #[test]
+    fn lines_are_counted_from_the_end() {
+        assert_eq!(reference(rows("-2", "-1")).unwrap().rows, (3, 4));
+        assert_eq!(reference(rows("2", "-3")).unwrap().rows, (2, 2));
+        assert!(reference(rows("-5", "-1")).is_err());
+        assert!("0".parse::<LineNumber>().is_err());
+        assert!("-0".parse::<LineNumber>().is_err());
+    }
+
+    #[test]
+    fn marks_are_parsed_and_shown_under_the_line() {
+        let tokens = Tokens::new("r 2 3 mark 2 \"never read\" mark -2 Ok Unused variable");
+        let (note_type, tokens) = parse_type(tokens).unwrap();
+        let NoteType::NecessaryWithReference(range) = note_type else {
+            panic!("Reference is not parsed");
+        };
+        assert_eq!(
+            format!("{:?}", range.marks),
+            format!(
+                "{:?}",
+                [
+                    (LineNumber::FromStart(2), "never read"),
+                    (LineNumber::FromEnd(2), "Ok")
+                ]
+            )
+        );
+        assert_eq!(tokens.rest(), "Unused variable");
+
+        let reference = reference(range).unwrap();
+        assert_eq!(
+            reference.marks,
+            [(2, "never read".to_string()), (3, "Ok".to_string())]
+        );
+        assert_eq!(
+            reference.text(&Gutter::default()),
+            "   2:     int x;\n          ^ never read\n   3:     return 0;\n          ^ Ok"
+        );
+    }
+
+    #[test]
+    fn empty_code_file_is_not_referenced() {
+        let mut note = ReviewNote::new("Note".to_string());
+        let err = note
+            .add_code_reference(PathBuf::from("main.cpp"), "", &rows("1", "1"), false, &[])
+            .unwrap_err();
+        assert_eq!(err.to_string(), Message::EmptyCodeFile.text());
+        assert!(note.references().is_empty());
+    }
+
+    #[test]
+    fn suggestion_round_trips_through_the_reviews_file() {
+        let format = NotesFormat::default();
+        for mut note in [
+            ReviewNote::new("Unused variable".to_string()),
+            referenced_note(&format),
+        ] {
+            note.set_suggestion("int main() {\n    return 0;\n}".to_string());
+            let parsed = ReviewNote::parse(note.stored_text(), &format).unwrap();
+            assert_eq!(parsed.suggestion(), note.suggestion());
+            assert_eq!(parsed.references().len(), note.references().len());
+            assert_eq!(parsed.text(), note.text());
+        }
+    }
+
+    #[test]
+    fn reference_past_the_end_is_clamped_when_asked() {
+        assert!(reference(rows("3", "10")).is_err());
+        let mut note = ReviewNote::new("Note".to_string());
+        note.add_code_reference(PathBuf::from("main.cpp"), CODE, &rows("3", "10"), true, &[])
+            .unwrap();
+        let reference = &note.references()[0];
+        assert_eq!(reference.rows, (3, 4));
+        assert_eq!(reference.marks, [(4, ReviewNote::CLAMPED_MARK.to_string())]);
+        assert!(note
+            .add_code_reference(PathBuf::from("main.cpp"), CODE, &rows("5", "10"), true, &[])
+            .is_err());
+    }
+
+    #[test]
+    fn ignored_lines_are_collapsed_and_read_back() {
+        let code = "#pragma once\n#include <a>\n#include <b>\nint f();\n#include <c>\nint g();\n";
+        let ignore = [Regex::new("^#include").unwrap()];
+        let mut note = ReviewNote::new("Note".to_string());
+        note.add_code_reference(PathBuf::from("f.h"), code, &rows("1", "6"), false, &ignore)
+            .unwrap();
+        let reference = &note.references()[0];
+        assert_eq!(reference.collapsed, [(2, 3), (5, 5)]);
+        assert_eq!(
+            reference.text(&Gutter::default()),
+            "   1: #pragma once\n   2: ...\n   4: int f();\n   5: ...\n   6: int g();"
+        );
+        let parsed = ReviewNote::parse(note.text(), &NotesFormat::default()).unwrap();
+        // A single collapsed line leaves no gap in the numbers, it is read as the quoted `...`
+        assert_eq!(parsed.references()[0].collapsed, [(2, 3)]);
+        assert_eq!(parsed.text(), note.text());
+
+        let mut range = rows("1", "6");
+        range
+            .marks
+            .push((LineNumber::FromStart(3), "unused".to_string()));
+        let mut note = ReviewNote::new("Note".to_string());
+        note.add_code_reference(PathBuf::from("f.h"), code, &range, false, &ignore)
+            .unwrap();
+        assert_eq!(note.references()[0].collapsed, [(2, 2), (5, 5)]);
+    }
+
+    #[test]
+    fn several_references_are_labeled() {
+        let format = NotesFormat::default();
+        let mut note = referenced_note(&format);
+        let range = CodeRange::from((LineNumber::FromStart(1), LineNumber::FromStart(1)));
+        note.add_code_reference(PathBuf::from("src/util.h"), CODE, &range, false, &[])
+            .unwrap();
+        let text = note.text();
+        assert!(text.contains("Reference 1 (main.cpp):\n"), "{}", text);
+        assert!(text.contains("Reference 2 (util.h):\n"), "{}", text);
+        let parsed = ReviewNote::parse(text.clone(), &format).unwrap();
+        assert_eq!(parsed.text(), text);
+        let rows = parsed.references().iter().map(|reference| reference.rows);
+        assert_eq!(rows.collect::<Vec<_>>(), [(2, 3), (1, 1)]);
+        assert!(!referenced_note(&format).text().contains("Reference 1"));
+    }
+
+    #[test]
+    fn inline_references_round_trip() {
+        let format = NotesFormat::default();
+        let mut note = ReviewNote::new("Unused variable".to_string());
+        let mut range = CodeRange::from((LineNumber::FromStart(2), LineNumber::FromStart(2)));
+        range.inline = true;
+        note.add_code_reference(PathBuf::from("main.cpp"), CODE, &range, false, &[])
+            .unwrap();
+        assert_eq!(note.text(), "Unused variable (line 2: int x;)");
+        let parsed = ReviewNote::parse(note.text(), &format).unwrap();
+        assert_eq!(parsed.body(), "Unused variable");
+        assert_eq!(parsed.text(), note.text());
+        let reference = &parsed.references()[0];
+        assert!(reference.inline);
+        assert_eq!((reference.rows, reference.code()), ((2, 2), "int x;"));
+        let mut range = CodeRange::from((LineNumber::FromStart(2), LineNumber::FromStart(3)));
+        range.inline = true;
+        assert!(note
+            .add_code_reference(PathBuf::from("main.cpp"), CODE, &range, false, &[])
+            .is_err());
+    }
+
+    #[test]
+    fn single_line_references_are_inlined() {
+        let mut note = referenced_note(&NotesFormat::default());
+        let range = CodeRange::from((LineNumber::FromStart(3), LineNumber::FromStart(3)));
+        note.add_code_reference(PathBuf::from("main.cpp"), CODE, &range, false, &[])
+            .unwrap();
+        note.inline_single_line_references();
+        let inline = note.references().iter().map(|reference| reference.inline);
+        // The reference with the mark is kept as the block
+        assert_eq!(inline.collect::<Vec<_>>(), [false, true]);
+    }
+
+    #[test]
+    fn hunk_reference_rows_are_read_back() {
+        let format = NotesFormat::default();
+        let mut note = ReviewNote::new("Initialize the variable".to_string());
+        let hunk = Hunk {
+            file: "main.cpp".to_string(),
+            new_start: 2,
+            new_len: 2,
+            text: "@@ -2 +2,2 @@\n-    int x;\n+    int x = 0;\n+    x++;".to_string(),
+        };
+        note.add_hunk_reference(PathBuf::from("fix.diff"), hunk);
+        let parsed = ReviewNote::parse(note.text(), &format).unwrap();
+        assert_eq!(parsed.text(), note.text());
+        assert!(parsed.references()[0].from_diff);
+        assert_eq!(parsed.references()[0].rows, (2, 3));
+    }
 }
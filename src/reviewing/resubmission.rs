@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use crate::messages::Message;
+
+/// Notes with at least this share of common words are the same note reworded
+const SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// How the notes changed since the previous review of the resubmitted task
+#[derive(Debug, Default)]
+pub(super) struct ReviewChanges {
+    /// Notes which weren't in the previous review
+    pub added: usize,
+    /// Notes of the previous review given again
+    pub kept: usize,
+    /// Notes of the previous review which are not given this time, probably fixed
+    pub dropped: Vec<String>,
+}
+
+/// Lowercase words of the note, punctuation around the words is ignored
+fn words(text: &str) -> BTreeSet<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Share of the common words of the notes, from 0 to 1
+fn similarity(first: &BTreeSet<String>, second: &BTreeSet<String>) -> f64 {
+    let union = first.union(second).count();
+    if union == 0 {
+        return 1.0;
+    }
+    first.intersection(second).count() as f64 / union as f64
+}
+
+/// Match the current notes with the previous ones, each previous note matches at most one current note
+pub(super) fn compare_notes(previous: &[String], current: &[String]) -> ReviewChanges {
+    let previous_words = previous.iter().map(|text| words(text)).collect::<Vec<_>>();
+    let mut matched = vec![false; previous.len()];
+    let mut changes = ReviewChanges::default();
+    for text in current {
+        let words = words(text);
+        let best = previous_words
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !matched[*index])
+            .map(|(index, previous)| (index, similarity(previous, &words)))
+            .filter(|(_, similarity)| *similarity >= SIMILARITY_THRESHOLD)
+            .max_by(|first, second| first.1.total_cmp(&second.1));
+        match best {
+            Some((index, _)) => {
+                matched[index] = true;
+                changes.kept += 1;
+            }
+            None => changes.added += 1,
+        }
+    }
+    changes.dropped = previous
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(text, _)| text.clone())
+        .collect();
+    changes
+}
+
+impl Display for ReviewChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            Message::ComparedToPrevious.format(&[&self.added, &self.kept, &self.dropped.len()])
+        )?;
+        for text in &self.dropped {
+            write!(f, "\n  - {}", text.lines().next().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn reworded_notes_are_kept() {
+        let previous = texts(&["Unused variable x.", "Magic number 42", "Use const"]);
+        let current = texts(&["unused variable X", "Off-by-one in the loop", "Use const"]);
+        let changes = compare_notes(&previous, &current);
+        assert_eq!(changes.added, 1);
+        assert_eq!(changes.kept, 2);
+        assert_eq!(changes.dropped, ["Magic number 42"]);
+    }
+
+    #[test]
+    fn previous_note_matches_once() {
+        let previous = texts(&["Use const"]);
+        let current = texts(&["Use const", "Use const"]);
+        let changes = compare_notes(&previous, &current);
+        assert_eq!(changes.kept, 1);
+        assert_eq!(changes.added, 1);
+        assert!(changes.dropped.is_empty());
+    }
+}
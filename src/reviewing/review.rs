@@ -1,22 +1,185 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt::Debug;
+use std::fs;
 use std::fs::File;
-use std::io::{stdin, stdout, BufRead, BufReader, StdinLock};
+use std::io::{stderr, stdin, stdout, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 use anyhow::{ensure, Context};
-use const_format::{concatcp, str_repeat};
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use log::warn;
+use regex::Regex;
 
+use crate::color;
+use crate::messages::Message;
 use crate::preparing::context::{Author, ProjectContext};
-use crate::preparing::notes::FileNotesStorage;
+use crate::preparing::encoding::OutputEncoding;
+use crate::preparing::notes::{FileNotesStorage, Gutter, Note, NoteIds};
 use crate::preparing::task::ShowMethod;
 use crate::preparing::task::Task;
-use crate::reviewing::notes::{parse_type, NoteType, ReviewNote};
+use crate::reviewing::diagnostics::parse_diagnostics;
+use crate::reviewing::diff::parse_hunks;
+use crate::reviewing::document::ReviewDocument;
+use crate::reviewing::external::run_with_timeout;
+use crate::reviewing::keys::{read_key, KeyInput};
+use crate::reviewing::latex::LatexReview;
+use crate::reviewing::notes::{parse_type, CodeRange, LineNumber, NoteType, ReviewNote};
+use crate::reviewing::resubmission::compare_notes;
+use crate::reviewing::session::{Session, SESSION_EXTENSION};
+use crate::reviewing::tokens::{CommandAliases, Tokens};
 
 #[derive(Debug)]
 pub(crate) struct Review {
     task: Task,
-    author: Author,
+    /// All reviewers, the first one is the author from config
+    authors: Vec<Author>,
+    active_author: usize,
+    project_dir: PathBuf,
+    check_commands: Vec<String>,
+    external_command_timeout: Duration,
+    output_encoding: OutputEncoding,
+    table_of_contents: bool,
+    note_ids: NoteIds,
+    print_renumbered_notes: bool,
+    latex_language: String,
+    gutter: Gutter,
+    /// Text after the notes
+    footer: String,
+    /// Items to acknowledge before the review
+    checklist: Vec<String>,
+    confirm_complete: bool,
+    revision: Option<String>,
+    source_url: Option<String>,
+    group_by: GroupBy,
+    quiet: bool,
+    output: Option<PathBuf>,
+    /// Commands are started by single key presses, see `keys`
+    single_keys: bool,
+    /// Code file the new references quote, the code file of the task at the start
+    active_file: PathBuf,
+    /// Category of the notes being added
+    category: Option<String>,
+    /// Last repeatable command
+    last_command: Option<String>,
+    /// Command of the current action, it becomes the last one if the action succeeds
+    pending_command: Option<String>,
+    /// File and rows of the last added reference
+    last_reference: Option<(PathBuf, (usize, usize))>,
+    /// Cut references at the end of the file instead of failing
+    clamp_references: bool,
+    /// Longer references are reported
+    max_reference_lines: Option<usize>,
+    /// Lines collapsed in references
+    reference_ignore_patterns: Vec<Regex>,
+    /// References of one line are quoted after the text of the note
+    inline_single_line_references: bool,
+    /// Other names of the keywords from the settings
+    command_aliases: CommandAliases,
+    /// Fail instead of warning, e.g. on too long references
+    strict: bool,
+    /// Named line ranges of the code file
+    bookmarks: BTreeMap<String, (LineNumber, LineNumber)>,
+    /// Every necessary task note must be added or waived to complete the review
+    require_all_necessary: bool,
+    /// Numbers of the necessary task notes deliberately left out, they are kept only in the session.
+    /// The added task notes are known from the templates of the review notes.
+    waived: BTreeSet<usize>,
+    /// Notes of the previous finished review, the resubmission is compared with them
+    previous_notes: Vec<String>,
     state: ReviewState,
+    /// File with the state of the unfinished review
+    session_file: PathBuf,
+    clock: Box<dyn Clock>,
+    /// When the review was started, to report its duration
+    started: Instant,
+    /// Interval of writing the notes, `None` writes every change
+    autosave: Option<Duration>,
+    last_flush: Instant,
     current_notes: FileNotesStorage<ReviewNote, ReviewNote>,
-    buf_reader: BufReader<StdinLock<'static>>,
+    buf_reader: InputReader,
+    /// The reviewer asked to stop reviewing the rest of the tasks
+    aborted: bool,
+    /// The review was finished with `complete`, not left unfinished
+    completed: bool,
+    /// Only the actions which don't change the review are allowed
+    read_only: bool,
+}
+
+/// Input of the reviewer shared by the reviews of several tasks
+pub(crate) struct InputReader(Box<dyn BufRead>);
+
+impl InputReader {
+    fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        self.0.read_line(buf)
+    }
+}
+
+impl Debug for InputReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("InputReader")
+    }
+}
+
+/// Source of the current time of the review
+trait Clock: Debug {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug)]
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Duration of the review, e.g. `1:02:05`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// New reader of the standard input
+pub(crate) fn input_reader() -> InputReader {
+    InputReader(Box::new(BufReader::new(stdin().lock())))
+}
+
+/// Options of the review from the command line
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ReviewOptions {
+    /// Git revision to take the code from instead of the working tree
+    pub revision: Option<String>,
+
+    /// Link to the reviewed code, overrides the one from config
+    pub source_url: Option<String>,
+
+    pub group_by: GroupBy,
+
+    /// Don't print `Ok` after successful actions
+    pub quiet: bool,
+
+    /// File to show the review to instead of the one of the show method, `-` is stdout
+    pub output: Option<PathBuf>,
+
+    /// Run commands without arguments by a single key press
+    pub interactive_keys: bool,
+    /// Only view the existing review, it's not changed
+    pub read_only: bool,
+}
+
+/// How notes are grouped in the shown review
+#[derive(Debug, Default, Clone, Copy, clap::ValueEnum)]
+pub(crate) enum GroupBy {
+    /// Necessary and optional notes
+    #[default]
+    Section,
+    /// Rubric categories of the notes
+    Category,
 }
 
 #[derive(Debug)]
@@ -28,44 +191,235 @@ enum ReviewState {
 
 enum ReviewAction {
     NewNote(ReviewNote, bool),
-    AddNote(ReviewNote, bool),
-    Show,
+    AddNote(NoteText, bool, PathBuf, Vec<CodeRange>),
+    AddHunkNote(NoteText, bool, PathBuf, usize),
+    Merge(usize, usize, bool),
+    Promote(usize),
+    Demote(usize),
+    /// Attach the proposed code to the note
+    Suggest(usize, bool, String),
+    Check,
+    Bookmark(String, (LineNumber, LineNumber)),
+    /// Leave out the necessary task note on purpose
+    Waive(usize),
+    ListBookmarks,
+    /// Print the notes of the task to choose the ones to add
+    Template,
+    /// Mark the note of the review as fixed or open it again
+    Resolve(usize, bool),
+    /// Show the whole code file in the pager
+    Pager,
+    ImportDiagnostics(PathBuf),
+    /// Show the note of the add action without adding it
+    Peek(Box<ReviewAction>),
+    SwitchAuthor(String),
+    SetCategory(Option<String>),
+    /// Switch the code file of the references, the code file of the task if the name is not given
+    UseFile(Option<String>),
+    /// Show the review, to the file if it is given
+    Show(Option<PathBuf>),
     Drop,
     Complete,
+    /// Leave the review unfinished, `true` also skips the rest of the tasks
+    Quit(bool),
     Incorrect(String),
 }
 
+/// Text of the added note and the number of the task note it is taken from
+#[derive(Debug)]
+pub(crate) struct NoteText {
+    text: String,
+    template: Option<usize>,
+}
+
+impl NoteText {
+    pub(crate) fn new(text: String, template: Option<usize>) -> Self {
+        Self { text, template }
+    }
+}
+
+impl ReviewAction {
+    /// The action doesn't change the review or the task, so it's allowed in the read-only mode
+    fn is_viewing(&self) -> bool {
+        matches!(
+            self,
+            ReviewAction::Show(_)
+                | ReviewAction::Template
+                | ReviewAction::Pager
+                | ReviewAction::Peek(_)
+                | ReviewAction::Check
+                | ReviewAction::UseFile(_)
+                | ReviewAction::SwitchAuthor(_)
+                | ReviewAction::Quit(_)
+                | ReviewAction::Incorrect(_)
+        )
+    }
+
+    /// Actions which change notes and can be repeated with `!!`
+    fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            ReviewAction::NewNote(..)
+                | ReviewAction::AddNote(..)
+                | ReviewAction::AddHunkNote(..)
+                | ReviewAction::Merge(..)
+                | ReviewAction::Promote(_)
+                | ReviewAction::Demote(_)
+                | ReviewAction::Suggest(..)
+                | ReviewAction::ImportDiagnostics(_)
+        )
+    }
+}
+
+/// Length of the separator lines when `separator!` isn't given one
+pub(crate) const SEPARATOR_LENGTH: usize = 50;
+
+/// Line of the repeated `$c` between two line breaks
 #[macro_export]
 macro_rules! separator {
-    ($c:literal, $n:literal) => {
-        concatcp!('\n', str_repeat!($c, $n), '\n')
+    ($c:literal) => {
+        $crate::separator!($c, $crate::reviewing::review::SEPARATOR_LENGTH)
+    };
+    ($c:literal, $n:expr) => {
+        ::const_format::concatcp!('\n', ::const_format::str_repeat!($c, $n), '\n')
     };
 }
 
+/// Separator printed between the reviews of several tasks
+pub(crate) const TASK_SEPARATOR: &str = separator!("=");
+
 impl Review {
-    pub(crate) fn new(mut context: ProjectContext) -> anyhow::Result<Self> {
-        let task = context
+    pub(crate) fn new(
+        mut context: ProjectContext,
+        options: ReviewOptions,
+        buf_reader: InputReader,
+    ) -> anyhow::Result<Self> {
+        let mut task = context
             .tasks
-            .swap_remove(context.current_task.context("Task is not set")?);
-        let notes_file_name = context
-            .project_dir
-            .join("reviews")
-            .join(&task.name)
-            .with_extension("txt");
-        File::create(&notes_file_name).context("Can't create notes file in reviews directory")?;
+            .swap_remove(context.current_task.context(Message::TaskIsNotSet.text())?);
+        task.set_deduplicate_notes(context.settings.deduplicate_notes);
+        let notes_file_name = task.reviews_file_path(&context.project_dir);
+        let session_file = notes_file_name.with_extension(SESSION_EXTENSION);
+        if options.read_only {
+            ensure!(
+                notes_file_name.is_file(),
+                "{}",
+                Message::NoReviewToView.format(&[&task.name])
+            );
+        }
+        // Notes of the previous review are kept until the reviewer decides to resume or overwrite them
+        // The unreadable review is never overwritten, it may contain notes written by hand
+        let mut current_notes = FileNotesStorage::open(
+            notes_file_name.clone(),
+            context.settings.notes_format(),
+            context.settings.output_encoding,
+        )
+        .with_context(|| Message::CantReadPreviousReview.format(&[&notes_file_name.display()]))?;
+        current_notes.set_deduplicate(context.settings.deduplicate_notes);
+        let autosave = (context.settings.autosave_secs > 0)
+            .then(|| Duration::from_secs(context.settings.autosave_secs));
+        current_notes.set_deferred(autosave.is_some());
+        let single_keys = options.interactive_keys && stdin().is_terminal();
+        let strict = context.settings.is_strict();
+        if options.interactive_keys && !single_keys {
+            warn!("{}", Message::InputIsNotTerminal.text());
+        }
+        let reference_ignore_patterns = context
+            .settings
+            .reference_ignore_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| Message::InvalidReferenceIgnorePattern.format(&[&pattern]))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let note_ids = context.settings.notes_format().ids;
+        let active_file = task.code_file_path(&context.project_dir);
+        let gutter = Gutter {
+            link_base: context
+                .settings
+                .reference_links
+                .then(|| context.project_dir.clone()),
+            ..context.settings.notes_format().gutter
+        };
         Ok(Self {
             task,
-            author: context.author,
+            authors: std::iter::once(context.author)
+                .chain(context.co_authors)
+                .collect(),
+            active_author: 0,
+            project_dir: context.project_dir,
+            check_commands: context.settings.check_commands,
+            external_command_timeout: Duration::from_secs(
+                context.settings.external_command_timeout_secs,
+            ),
+            output_encoding: context.settings.output_encoding,
+            table_of_contents: context.settings.table_of_contents,
+            note_ids,
+            print_renumbered_notes: context.settings.print_renumbered_notes,
+            latex_language: context.settings.latex_language,
+            gutter,
+            footer: context.settings.footer,
+            checklist: context.settings.checklist,
+            confirm_complete: context.settings.confirm_complete,
+            source_url: options.source_url.or(context.settings.source_url),
+            revision: options.revision,
+            group_by: options.group_by,
+            quiet: options.quiet,
+            output: options.output,
+            single_keys,
+            active_file,
+            category: None,
+            last_reference: None,
+            clamp_references: context.settings.clamp_references,
+            max_reference_lines: context.settings.max_reference_lines,
+            reference_ignore_patterns,
+            inline_single_line_references: context.settings.inline_single_line_references,
+            command_aliases: CommandAliases::new(&context.settings.command_aliases),
+            strict,
+            bookmarks: BTreeMap::new(),
+            require_all_necessary: context.settings.require_all_necessary,
+            waived: BTreeSet::new(),
+            previous_notes: Vec::new(),
+            last_command: None,
+            pending_command: None,
             state: ReviewState::Start,
-            current_notes: FileNotesStorage::new(notes_file_name)?,
-            buf_reader: BufReader::new(stdin().lock()),
+            session_file,
+            clock: Box::new(SystemClock),
+            started: Instant::now(),
+            autosave,
+            last_flush: Instant::now(),
+            current_notes,
+            buf_reader,
+            aborted: false,
+            completed: false,
+            read_only: options.read_only,
         })
     }
 
     pub(crate) fn step(&mut self) -> anyhow::Result<()> {
         match self.state {
             ReviewState::Start => {
-                println!("Let's start new review:");
+                if self.read_only {
+                    self.prepare_resumed_notes();
+                    eprintln!(
+                        "{}",
+                        Message::OpenedReadOnly
+                            .format(&[&self.task.name, &self.current_notes.notes().count()])
+                    );
+                    self.state = ReviewState::Review;
+                    return Ok(());
+                }
+                if self.resume_unfinished()? {
+                    eprintln!(
+                        "{}",
+                        Message::ReviewResumed.format(&[&self.current_notes.notes().count()])
+                    );
+                    self.state = ReviewState::Review;
+                    return Ok(());
+                }
+                self.go_through_checklist()?;
+                eprintln!("{}", Message::NewReview.text());
                 // todo: Last versions
 
                 self.state = ReviewState::Review
@@ -74,33 +428,284 @@ impl Review {
                 let action = self
                     .ask_action()
                     .unwrap_or_else(|err| ReviewAction::Incorrect(format!("{:#}", err)));
+                if self.read_only && !action.is_viewing() {
+                    eprintln!("{}", Message::ReadOnlyMode.text());
+                    return Ok(());
+                }
                 match action {
                     ReviewAction::NewNote(note, optional) => {
-                        self.task.add_note(note.text_to_storage(), optional);
-                        println!("Ok");
+                        if self.task.add_note(note.text_to_storage(), optional) {
+                            self.remember_command();
+                            self.print_ok();
+                        } else {
+                            eprintln!("{}", Message::DuplicateSkipped.text());
+                        }
                     }
-                    ReviewAction::AddNote(note, optional) => {
-                        if optional {
-                            self.current_notes.add_optional_note(note);
+                    ReviewAction::AddNote(note, optional, file_path, ranges) => {
+                        match self.add_referenced_note(note, optional, file_path, &ranges) {
+                            Ok(true) => {
+                                self.remember_command();
+                                self.print_ok();
+                            }
+                            Ok(false) => eprintln!("{}", Message::DuplicateSkipped.text()),
+                            Err(err) => {
+                                eprintln!(
+                                    "{}",
+                                    Message::CantAddNote.format(&[&format!("{:#}", err)])
+                                )
+                            }
+                        }
+                    }
+                    ReviewAction::AddHunkNote(note, optional, diff, index) => {
+                        match self.add_hunk_note(note, optional, diff, index) {
+                            Ok(true) => {
+                                self.remember_command();
+                                self.print_ok();
+                            }
+                            Ok(false) => eprintln!("{}", Message::DuplicateSkipped.text()),
+                            Err(err) => {
+                                eprintln!(
+                                    "{}",
+                                    Message::CantAddNote.format(&[&format!("{:#}", err)])
+                                )
+                            }
+                        }
+                    }
+                    ReviewAction::Merge(first, second, optional) => {
+                        match self.current_notes.merge_notes(first, second, optional) {
+                            Ok(()) => {
+                                self.update_author_tags();
+                                self.current_notes
+                                    .save()
+                                    .context(Message::CantSaveNotes.text())?;
+                                self.remember_command();
+                                self.print_ok();
+                                self.print_renumbered(!optional, optional);
+                            }
+                            Err(err) => eprintln!(
+                                "{}",
+                                Message::CantMergeNotes.format(&[&format!("{:#}", err)])
+                            ),
+                        }
+                    }
+                    ReviewAction::Promote(num) => match self.current_notes.promote_note(num) {
+                        Ok(()) => {
+                            self.remember_command();
+                            self.print_ok();
+                            self.print_renumbered(true, true);
+                        }
+                        Err(err) => eprintln!(
+                            "{}",
+                            Message::CantPromoteNote.format(&[&format!("{:#}", err)])
+                        ),
+                    },
+                    ReviewAction::Demote(num) => match self.current_notes.demote_note(num) {
+                        Ok(()) => {
+                            self.remember_command();
+                            self.print_ok();
+                            self.print_renumbered(true, true);
+                        }
+                        Err(err) => eprintln!(
+                            "{}",
+                            Message::CantDemoteNote.format(&[&format!("{:#}", err)])
+                        ),
+                    },
+                    ReviewAction::Suggest(num, optional, code) => {
+                        match self.current_notes.note_mut(num, optional) {
+                            Ok(note) => {
+                                note.set_suggestion(code);
+                                self.current_notes
+                                    .save()
+                                    .context(Message::CantSaveNotes.text())?;
+                                self.remember_command();
+                                self.print_ok();
+                            }
+                            Err(err) => eprintln!(
+                                "{}",
+                                Message::CantSuggestFix.format(&[&format!("{:#}", err)])
+                            ),
+                        }
+                    }
+                    ReviewAction::Check => self.run_checks(),
+                    ReviewAction::Bookmark(name, rows) => {
+                        self.bookmarks.insert(name, rows);
+                        self.print_ok();
+                    }
+                    ReviewAction::Waive(num) => {
+                        if num < self.task.notes_count().0 {
+                            self.waived.insert(num);
+                            self.print_ok();
                         } else {
-                            self.current_notes.add_note(note);
+                            eprintln!("{}", Message::CantWaiveNote.format(&[&num]));
+                        }
+                    }
+                    ReviewAction::Resolve(num, optional) => {
+                        match self.current_notes.note_mut(num, optional) {
+                            Ok(note) => {
+                                let resolved = !note.is_resolved();
+                                note.set_resolved(resolved);
+                                self.current_notes
+                                    .save()
+                                    .context(Message::CantSaveNotes.text())?;
+                                let message = if resolved {
+                                    Message::NoteResolved
+                                } else {
+                                    Message::NoteReopened
+                                };
+                                eprintln!("{}", message.text());
+                            }
+                            Err(err) => eprintln!(
+                                "{}",
+                                Message::CantResolveNote.format(&[&format!("{:#}", err)])
+                            ),
                         }
-                        self.current_notes.save().context("Can't save notes")?;
-                        println!("Ok");
                     }
-                    ReviewAction::Show => {
-                        self.show();
-                        println!("Ok");
+                    ReviewAction::Template => {
+                        let (necessary, optional) = self.task.notes_count();
+                        if necessary + optional == 0 {
+                            eprintln!("{}", Message::NoTaskNotes.text());
+                        } else {
+                            self.task
+                                .write_notes(&mut stderr())
+                                .context(Message::CantPrintTaskNotes.text())?;
+                        }
                     }
+                    ReviewAction::ListBookmarks => {
+                        if self.bookmarks.is_empty() {
+                            eprintln!("{}", Message::NoBookmarks.text());
+                        }
+                        for (name, (first, second)) in &self.bookmarks {
+                            eprintln!("{}: {} {}", name, first, second);
+                        }
+                    }
+                    ReviewAction::Pager => {
+                        if let Err(err) = self.open_pager() {
+                            eprintln!("{}", Message::CantShowCode.format(&[&format!("{:#}", err)]));
+                        }
+                    }
+                    ReviewAction::Peek(action) => {
+                        let note = match *action {
+                            ReviewAction::AddNote(note, _, file_path, ranges) => {
+                                self.build_referenced_note(note.text, file_path, &ranges)
+                            }
+                            ReviewAction::AddHunkNote(note, _, diff, index) => {
+                                self.build_hunk_note(note.text, diff, index)
+                            }
+                            _ => unreachable!("Only add actions can be peeked"),
+                        };
+                        match note {
+                            Ok(note) => eprintln!("{}", note.text()),
+                            Err(err) => eprintln!(
+                                "{}",
+                                Message::CantBuildNote.format(&[&format!("{:#}", err)])
+                            ),
+                        }
+                    }
+                    ReviewAction::ImportDiagnostics(path) => match self.import_diagnostics(&path) {
+                        Ok(()) => self.remember_command(),
+                        Err(err) => eprintln!(
+                            "{}",
+                            Message::CantImportDiagnostics.format(&[&format!("{:#}", err)])
+                        ),
+                    },
+                    ReviewAction::SwitchAuthor(name) => {
+                        match self.authors.iter().position(|author| author.matches(&name)) {
+                            Some(index) => {
+                                self.active_author = index;
+                                eprintln!(
+                                    "{}",
+                                    Message::ActiveAuthor.format(&[&self.authors[index].name])
+                                );
+                            }
+                            None => eprintln!("{}", Message::UnknownAuthor.format(&[&name])),
+                        }
+                    }
+                    ReviewAction::SetCategory(category) => {
+                        match &category {
+                            Some(category) => {
+                                eprintln!("{}", Message::CategorySet.format(&[category]))
+                            }
+                            None => eprintln!("{}", Message::CategoryCleared.text()),
+                        }
+                        self.category = category;
+                    }
+                    ReviewAction::UseFile(name) => match self.find_code_file(name.as_deref()) {
+                        Ok(path) => {
+                            eprintln!(
+                                "{}",
+                                Message::ActiveFile.format(&[&self.relative_path(&path).display()])
+                            );
+                            self.active_file = path;
+                        }
+                        Err(err) => {
+                            eprintln!("{}", Message::CantUseFile.format(&[&format!("{:#}", err)]))
+                        }
+                    },
+                    ReviewAction::Show(path) => match path
+                        .or_else(|| self.output.clone())
+                        // The output file of the task is not overwritten either
+                        .or_else(|| self.read_only.then(|| PathBuf::from("-")))
+                    {
+                        Some(path) => match self.show_to(&path) {
+                            Ok(()) => self.print_ok(),
+                            Err(err) => eprintln!(
+                                "{}",
+                                Message::CantShowReview.format(&[&format!("{:#}", err)])
+                            ),
+                        },
+                        None => {
+                            self.show();
+                            self.print_ok();
+                        }
+                    },
                     ReviewAction::Drop => {
                         self.current_notes.clear();
-                        println!("Ok");
+                        self.waived.clear();
+                        self.print_ok();
                     }
                     ReviewAction::Complete => {
-                        self.finish_review()?;
+                        let unaddressed = self.unaddressed_notes();
+                        if self.require_all_necessary && !unaddressed.is_empty() {
+                            eprintln!("{}", Message::NotAddressed.text());
+                            for num in unaddressed {
+                                eprintln!(
+                                    "{}) {}",
+                                    self.note_ids.id(num, false),
+                                    self.task.find_note(num, false)?
+                                );
+                            }
+                            eprintln!("{}", Message::AddOrWaive.text());
+                        } else if !self.confirm_complete
+                            || self.confirm(
+                                &Message::FinalizeReview
+                                    .format(&[&self.current_notes.notes().count()]),
+                            )?
+                        {
+                            self.finish_review()?;
+                        }
+                    }
+                    ReviewAction::Quit(all) => {
+                        self.current_notes
+                            .flush()
+                            .context(Message::CantSaveNotes.text())?;
+                        if !self.read_only {
+                            eprintln!("{}", Message::LeftUnfinished.format(&[&self.task.name]));
+                        }
+                        self.aborted = all;
+                        self.state = ReviewState::Finish;
+                        return Ok(());
                     }
                     ReviewAction::Incorrect(msg) => {
-                        println!("{}", msg);
+                        eprintln!("{}", msg);
+                    }
+                }
+                self.autosave()?;
+                if !self.is_finished() && !self.read_only {
+                    if let Err(err) = self.save_session() {
+                        warn!(
+                            "{}",
+                            Message::CantSaveSession.format(&[&format!("{:#}", err)])
+                        );
                     }
                 }
             }
@@ -111,91 +716,1669 @@ impl Review {
         Ok(())
     }
 
+    /// Write the notes if the autosave interval has passed
+    fn autosave(&mut self) -> anyhow::Result<()> {
+        if let Some(interval) = self.autosave {
+            if self.clock.now() - self.last_flush >= interval {
+                self.current_notes
+                    .flush()
+                    .context(Message::CantSaveNotes.text())?;
+                self.last_flush = self.clock.now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Offer to continue the review left in the reviews file, otherwise start from scratch
+    fn resume_unfinished(&mut self) -> anyhow::Result<bool> {
+        let has_session = self.session_file.exists();
+        let notes_count = self.current_notes.notes().count();
+        if !has_session && notes_count == 0 {
+            return Ok(false);
+        }
+        let question = if has_session {
+            Message::ResumeSession
+        } else {
+            Message::ResumeReviews
+        }
+        .format(&[&self.task.name, &notes_count]);
+        if !self.confirm(&question)? {
+            // Without the session the notes are of the finished review, the task is resubmitted
+            if !has_session {
+                self.previous_notes = self.current_notes.notes().map(|note| note.body()).collect();
+            }
+            self.current_notes.clear();
+            if has_session {
+                fs::remove_file(&self.session_file)
+                    .context(Message::CantRemoveSessionFile.text())?;
+            }
+            return Ok(false);
+        }
+        self.prepare_resumed_notes();
+        if has_session {
+            match Session::load(&self.session_file) {
+                Ok(session) => self.restore_session(session),
+                Err(err) => warn!(
+                    "{}",
+                    Message::CantRestoreSession.format(&[&format!("{:#}", err)])
+                ),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Notes read back from the reviews file are shown as the new ones
+    fn prepare_resumed_notes(&mut self) {
+        for note in self.current_notes.notes_mut() {
+            note.resolve_reference_files(&self.project_dir);
+            note.set_gutter(self.gutter.clone());
+            // A tag of somebody else is a part of the text written by hand
+            if note
+                .author()
+                .is_some_and(|name| !self.authors.iter().any(|author| author.name == name))
+            {
+                note.unset_author();
+            }
+        }
+        self.update_author_tags();
+    }
+
+    fn restore_session(&mut self, session: Session) {
+        match self
+            .authors
+            .iter()
+            .position(|author| author.name == session.author)
+        {
+            Some(index) => self.active_author = index,
+            None => warn!(
+                "{}",
+                Message::UnknownSessionAuthor.format(&[&session.author])
+            ),
+        }
+        self.category = session.category;
+        self.last_reference = session.last_reference;
+        self.last_command = session.last_command;
+        self.bookmarks = session.bookmarks;
+        self.waived = session.waived;
+        self.previous_notes = session.previous_notes;
+    }
+
+    fn save_session(&self) -> anyhow::Result<()> {
+        Session {
+            author: self.authors[self.active_author].name.clone(),
+            category: self.category.clone(),
+            last_reference: self.last_reference.clone(),
+            last_command: self.last_command.clone(),
+            bookmarks: self.bookmarks.clone(),
+            waived: self.waived.clone(),
+            previous_notes: self.previous_notes.clone(),
+        }
+        .save(&self.session_file)
+    }
+
+    /// Report the success of the action unless the review is quiet
+    fn print_ok(&self) {
+        if !self.quiet {
+            eprintln!("{}", color::success(Message::Ok.text()));
+        }
+    }
+
+    /// Ask the reviewer to acknowledge each checklist item, `skip` skips the rest
+    fn go_through_checklist(&mut self) -> anyhow::Result<()> {
+        if self.checklist.is_empty() {
+            return Ok(());
+        }
+        eprintln!("{}", Message::ChecklistIntro.text());
+        for item in &self.checklist {
+            eprint!("- {} ", item);
+            stderr().flush().context(Message::CantFlushPrompt.text())?;
+            let mut input = String::new();
+            self.buf_reader
+                .read_line(&mut input)
+                .context(Message::ReadingLineFail.text())?;
+            if input.trim() == "skip" {
+                eprintln!("{}", Message::ChecklistSkipped.text());
+                break;
+            }
+        }
+        Ok(())
+    }
+
     fn ask_action(&mut self) -> anyhow::Result<ReviewAction> {
+        let mut prompt = Vec::new();
+        if self.authors.len() > 1 {
+            prompt.push(self.authors[self.active_author].initials());
+        }
+        if self.active_file != self.task.code_file_path(&self.project_dir) {
+            prompt.push(self.relative_path(&self.active_file).display().to_string());
+        }
+        if !prompt.is_empty() {
+            eprint!("{}> ", prompt.join(" "));
+            stderr().flush().context(Message::CantFlushPrompt.text())?;
+        }
         let mut input = String::new();
-        self.buf_reader
-            .read_line(&mut input)
-            .context("Reading line fail")?;
-        let mut tokens = input.split_whitespace();
-        match tokens.next() {
+        if !self.single_keys {
+            self.buf_reader
+                .read_line(&mut input)
+                .context(Message::ReadingLineFail.text())?;
+        } else {
+            match read_key()? {
+                KeyInput::Command(command) => {
+                    eprintln!("{}", command);
+                    input = command.to_string();
+                }
+                KeyInput::Line(start) => {
+                    eprint!("{}", start);
+                    stderr().flush().context(Message::CantFlushPrompt.text())?;
+                    self.buf_reader
+                        .read_line(&mut input)
+                        .context(Message::ReadingLineFail.text())?;
+                    input.insert_str(0, &start);
+                }
+            }
+        }
+        if matches!(
+            self.command_aliases.resolve(input.trim()).as_str(),
+            "!!" | "repeat"
+        ) {
+            input = self
+                .last_command
+                .clone()
+                .context(Message::NoCommandToRepeat.text())?;
+            eprintln!("{}", input.trim());
+        }
+        let action = self.parse_action(&input)?;
+        self.pending_command = action.is_repeatable().then_some(input);
+        Ok(action)
+    }
+
+    /// The current action succeeded, so `!!` repeats it
+    fn remember_command(&mut self) {
+        if let Some(command) = self.pending_command.take() {
+            self.last_command = Some(command);
+        }
+    }
+
+    fn parse_action(&mut self, input: &str) -> anyhow::Result<ReviewAction> {
+        // The tokens borrow the aliases while the action may change the review
+        let aliases = self.command_aliases.clone();
+        let mut tokens = Tokens::new(input).with_aliases(&aliases);
+        // Keywords are case-insensitive, the rest of the input is kept as is
+        let keyword = tokens.next_keyword();
+        match keyword.as_deref() {
             Some("new") | Some("n") => {
                 let (note_type, tokens) = parse_type(tokens)?;
                 ensure!(
                     matches!(note_type, NoteType::Necessary | NoteType::Optional),
-                    "Incorrect note type"
+                    Message::IncorrectNoteType.text()
                 );
                 Ok(ReviewAction::NewNote(
-                    tokens.collect::<Vec<_>>().join(" ").into(),
+                    ReviewNote::new(tokens.rest().to_string()),
                     matches!(note_type, NoteType::Optional),
                 ))
             }
-            Some("add") | Some("a") => {
-                let (note_type, tokens) = parse_type(tokens)?;
-                match note_type {
-                    NoteType::NecessaryWithReference((first, second)) => {
-                        let mut note = self.find_note(false, tokens)?;
-                        let file = self.task.get_file()?;
-                        note.add_code_reference(file, (first, second));
-                        Ok(ReviewAction::AddNote(note, false))
-                    }
-                    NoteType::OptionalWithReference((first, second)) => {
-                        let mut note = self.find_note(true, tokens)?;
-                        let file = self.task.get_file()?;
-                        note.add_code_reference(file, (first, second));
-                        Ok(ReviewAction::AddNote(note, true))
-                    }
-                    NoteType::Necessary => {
-                        Ok(ReviewAction::AddNote(self.find_note(false, tokens)?, false))
-                    }
-                    NoteType::Optional => {
-                        Ok(ReviewAction::AddNote(self.find_note(true, tokens)?, true))
-                    }
+            Some("add") | Some("a") => self.parse_add(tokens),
+            Some("peek") | Some("p") => Ok(ReviewAction::Peek(Box::new(self.parse_add(tokens)?))),
+            Some("merge") | Some("m") => {
+                let (note_type, mut tokens) = parse_type(tokens)?;
+                let note_type = self.apply_id_bucket(note_type, &tokens);
+                ensure!(
+                    matches!(note_type, NoteType::Necessary | NoteType::Optional),
+                    Message::IncorrectNoteType.text()
+                );
+                let optional = matches!(note_type, NoteType::Optional);
+                let first = self
+                    .parse_note_id(&mut tokens, optional)
+                    .context(Message::IncorrectFirstNoteNumber.text())?;
+                let second = self
+                    .parse_note_id(&mut tokens, optional)
+                    .context(Message::IncorrectSecondNoteNumber.text())?;
+                Ok(ReviewAction::Merge(first, second, optional))
+            }
+            Some("promote") => Ok(ReviewAction::Promote(
+                self.parse_note_id(&mut tokens, true)?,
+            )),
+            Some("demote") => Ok(ReviewAction::Demote(
+                self.parse_note_id(&mut tokens, false)?,
+            )),
+            Some("suggest") => {
+                let (note_type, mut tokens) = parse_type(tokens)?;
+                let note_type = self.apply_id_bucket(note_type, &tokens);
+                ensure!(
+                    matches!(note_type, NoteType::Necessary | NoteType::Optional),
+                    Message::IncorrectNoteType.text()
+                );
+                let num =
+                    self.parse_note_id(&mut tokens, matches!(note_type, NoteType::Optional))?;
+                let code = tokens.rest().replace("\\n", "\n");
+                ensure!(!code.is_empty(), Message::NoSuggestedCode.text());
+                Ok(ReviewAction::Suggest(
+                    num,
+                    matches!(note_type, NoteType::Optional),
+                    code,
+                ))
+            }
+            Some("check") => Ok(ReviewAction::Check),
+            Some("open") | Some("pager") => Ok(ReviewAction::Pager),
+            Some("waive") => Ok(ReviewAction::Waive(self.parse_note_id(&mut tokens, false)?)),
+            Some("template") | Some("tmpl") => Ok(ReviewAction::Template),
+            Some("resolve") => {
+                let (note_type, mut tokens) = parse_type(tokens)?;
+                let note_type = self.apply_id_bucket(note_type, &tokens);
+                ensure!(
+                    matches!(note_type, NoteType::Necessary | NoteType::Optional),
+                    Message::IncorrectNoteType.text()
+                );
+                let optional = matches!(note_type, NoteType::Optional);
+                Ok(ReviewAction::Resolve(
+                    self.parse_note_id(&mut tokens, optional)?,
+                    optional,
+                ))
+            }
+            Some("bookmark") | Some("bm") => {
+                let name = tokens.next().context(Message::NoBookmarkName.text())?;
+                if name == "list" {
+                    return Ok(ReviewAction::ListBookmarks);
                 }
+                let first = tokens
+                    .next()
+                    .context(Message::NoFirstNumberInBookmark.text())?
+                    .parse()
+                    .context(Message::IncorrectFirstNumberInBookmark.text())?;
+                let second = tokens
+                    .next()
+                    .context(Message::NoSecondNumberInBookmark.text())?
+                    .parse()
+                    .context(Message::IncorrectSecondNumberInBookmark.text())?;
+                Ok(ReviewAction::Bookmark(name, (first, second)))
             }
-            Some("show") | Some("s") => Ok(ReviewAction::Show),
+            Some("import") => Ok(ReviewAction::ImportDiagnostics(
+                tokens
+                    .next()
+                    .context(Message::NoDiagnosticsFile.text())?
+                    .into(),
+            )),
+            Some("who") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                ensure!(!name.is_empty(), Message::NoAuthorName.text());
+                Ok(ReviewAction::SwitchAuthor(name))
+            }
+            Some("category") => {
+                let category = tokens.collect::<Vec<_>>().join(" ");
+                Ok(ReviewAction::SetCategory(
+                    (!category.is_empty()).then_some(category),
+                ))
+            }
+            Some("usefile") => Ok(ReviewAction::UseFile(tokens.next())),
+            Some("show") | Some("s") => Ok(ReviewAction::Show(tokens.next().map(PathBuf::from))),
             Some("complete") | Some("c") => Ok(ReviewAction::Complete),
+            Some("quit") | Some("q") => {
+                Ok(ReviewAction::Quit(tokens.next().as_deref() == Some("all")))
+            }
             Some("drop") | Some("d") => Ok(ReviewAction::Drop),
-            _ => Ok(ReviewAction::Incorrect("Unknown action".to_string())),
+            _ => Ok(ReviewAction::Incorrect(
+                Message::UnknownAction.text().to_string(),
+            )),
+        }
+    }
+
+    /// Parse arguments of `add`: note type, references and the note number or text
+    fn parse_add(&mut self, tokens: Tokens<'_>) -> anyhow::Result<ReviewAction> {
+        let (note_type, tokens) = parse_type(tokens)?;
+        let note_type = self.apply_id_bucket(note_type, &tokens);
+        let (optional, ranges) = match note_type {
+            NoteType::NecessaryWithReference(range) => (false, vec![range]),
+            NoteType::OptionalWithReference(range) => (true, vec![range]),
+            NoteType::Necessary => (false, vec![]),
+            NoteType::Optional => (true, vec![]),
+            NoteType::NecessaryWithBookmark(name) => (false, vec![self.bookmark(&name)?.into()]),
+            NoteType::OptionalWithBookmark(name) => (true, vec![self.bookmark(&name)?.into()]),
+            NoteType::NecessaryWithSameReference | NoteType::OptionalWithSameReference => {
+                let optional = matches!(note_type, NoteType::OptionalWithSameReference);
+                let (file_path, rows) = self
+                    .last_reference
+                    .clone()
+                    .context(Message::NoPreviousReference.text())?;
+                let rows = (LineNumber::FromStart(rows.0), LineNumber::FromStart(rows.1));
+                return Ok(ReviewAction::AddNote(
+                    self.find_note(optional, tokens)?,
+                    optional,
+                    file_path,
+                    vec![rows.into()],
+                ));
+            }
+            NoteType::NecessaryWithHunk((diff, index)) => {
+                let text = self.find_note(false, tokens)?;
+                return Ok(ReviewAction::AddHunkNote(text, false, diff, index));
+            }
+            NoteType::OptionalWithHunk((diff, index)) => {
+                let text = self.find_note(true, tokens)?;
+                return Ok(ReviewAction::AddHunkNote(text, true, diff, index));
+            }
+        };
+        Ok(ReviewAction::AddNote(
+            self.find_note(optional, tokens)?,
+            optional,
+            self.active_file.clone(),
+            ranges,
+        ))
+    }
+
+    /// Code file of the task ending with the name, e.g. `main.h` or `include/main.h`
+    fn find_code_file(&self, name: Option<&str>) -> anyhow::Result<PathBuf> {
+        let Some(name) = name else {
+            return Ok(self.task.code_file_path(&self.project_dir));
+        };
+        let files = self.task.code_files(&self.project_dir);
+        let mut found = files.iter().filter(|path| path.ends_with(name));
+        let path = found
+            .next()
+            .with_context(|| Message::NotTaskCodeFile.format(&[&name]))?;
+        ensure!(
+            found.next().is_none(),
+            "{}",
+            Message::AmbiguousCodeFile.format(&[&name])
+        );
+        Ok(path.clone())
+    }
+
+    /// Path relative to the directory of the task for the messages
+    fn relative_path<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.project_dir.join("tasks").join(&self.task.name))
+            .unwrap_or(path)
+    }
+
+    fn bookmark(&self, name: &str) -> anyhow::Result<(LineNumber, LineNumber)> {
+        self.bookmarks
+            .get(name)
+            .copied()
+            .with_context(|| Message::NoBookmark.format(&[&name, &name]))
+    }
+
+    /// Index of the note by its id in the next token
+    fn parse_note_id(&self, tokens: &mut Tokens<'_>, optional: bool) -> anyhow::Result<usize> {
+        let id = tokens.next().context(Message::NoNumberOfNote.text())?;
+        self.note_ids.index(&id, optional)
+    }
+
+    /// Make the type optional if the next token is the id of an optional note, e.g. `O1`
+    fn apply_id_bucket(&self, note_type: NoteType, tokens: &Tokens<'_>) -> NoteType {
+        match tokens.peek().and_then(|id| self.note_ids.optional(&id)) {
+            Some(true) => note_type.into_optional(),
+            _ => note_type,
+        }
+    }
+
+    /// Authors who added at least one note, the config author if nobody did
+    fn contributors(&self) -> Vec<&Author> {
+        let contributors = self
+            .authors
+            .iter()
+            .filter(|author| {
+                self.current_notes
+                    .notes()
+                    .any(|note| note.author() == Some(author.name.as_str()))
+            })
+            .collect::<Vec<_>>();
+        if contributors.is_empty() {
+            vec![&self.authors[0]]
+        } else {
+            contributors
+        }
+    }
+
+    /// Show author tags only if notes were added by several authors
+    fn update_author_tags(&mut self) {
+        let show_author = self.contributors().len() > 1;
+        for note in self.current_notes.notes_mut() {
+            note.set_show_author(show_author);
         }
     }
 
-    fn find_note<'a>(
+    /// Add the note with references to the rows of the code file to the review, asking whether to keep
+    /// the blank references. The note is built once, so the code file is read once.
+    ///
+    /// Returns false if the note was skipped as a duplicate.
+    fn add_referenced_note(
+        &mut self,
+        text: NoteText,
+        optional: bool,
+        file_path: PathBuf,
+        ranges: &[CodeRange],
+    ) -> anyhow::Result<bool> {
+        let mut note = self.build_referenced_note(text.text, file_path, ranges)?;
+        self.confirm_blank_references(&mut note)?;
+        self.store_referenced_note(note, text.template, optional)
+    }
+
+    /// Store the built note, the template and the last reference are remembered
+    fn store_referenced_note(
+        &mut self,
+        mut note: ReviewNote,
+        template: Option<usize>,
+        optional: bool,
+    ) -> anyhow::Result<bool> {
+        self.check_reference_lengths(&note)?;
+        if let Some(num) = template {
+            note.set_template(num, optional);
+        }
+        if let Some(reference) = note.references().last() {
+            self.last_reference = Some((reference.file.clone(), reference.rows));
+        }
+        self.store_note(note, optional)
+    }
+
+    fn build_referenced_note(
         &self,
+        text: String,
+        file_path: PathBuf,
+        ranges: &[CodeRange],
+    ) -> anyhow::Result<ReviewNote> {
+        let mut note = self.new_note(text);
+        if !ranges.is_empty() {
+            let code = self.read_code(&file_path)?;
+            for range in ranges {
+                note.add_code_reference(
+                    file_path.clone(),
+                    &code,
+                    range,
+                    self.clamp_references,
+                    &self.reference_ignore_patterns,
+                )?;
+            }
+        }
+        if self.inline_single_line_references {
+            note.inline_single_line_references();
+        }
+        Ok(note)
+    }
+
+    /// Report the references longer than `max_reference_lines`
+    fn check_reference_lengths(&self, note: &ReviewNote) -> anyhow::Result<()> {
+        let Some(max_lines) = self.max_reference_lines else {
+            return Ok(());
+        };
+        for reference in note.references() {
+            let lines = reference.rows.1 + 1 - reference.rows.0;
+            if lines <= max_lines {
+                continue;
+            }
+            let message = Message::LongReference.format(&[
+                &reference.rows.0,
+                &reference.rows.1,
+                &lines,
+                &max_lines,
+            ]);
+            ensure!(!self.strict, message);
+            eprintln!("{} {}", color::warning(Message::Warning.text()), message);
+        }
+        Ok(())
+    }
+
+    /// Ask whether to keep references quoting only blank lines or comments, the declined ones are dropped
+    fn confirm_blank_references(&mut self, note: &mut ReviewNote) -> anyhow::Result<()> {
+        let mut declined = Vec::new();
+        for (index, reference) in note.references().iter().enumerate() {
+            if !reference.blank {
+                continue;
+            }
+            eprintln!(
+                "{} {}",
+                color::warning(Message::Warning.text()),
+                Message::BlankReference.format(&[&reference.rows.0, &reference.rows.1])
+            );
+            if !self.confirm(Message::KeepReference.text())? {
+                declined.push(index);
+            }
+        }
+        for index in declined.into_iter().rev() {
+            note.remove_reference(index);
+        }
+        Ok(())
+    }
+
+    /// Add the note quoting the hunk `index` of the diff file to the review
+    fn add_hunk_note(
+        &mut self,
+        text: NoteText,
         optional: bool,
-        mut tokens: impl Iterator<Item = &'a str>,
+        diff_path: PathBuf,
+        index: usize,
+    ) -> anyhow::Result<bool> {
+        let mut note = self.build_hunk_note(text.text, diff_path, index)?;
+        if let Some(num) = text.template {
+            note.set_template(num, optional);
+        }
+        self.store_note(note, optional)
+    }
+
+    fn build_hunk_note(
+        &self,
+        text: String,
+        diff_path: PathBuf,
+        index: usize,
     ) -> anyhow::Result<ReviewNote> {
-        let num = tokens
-            .next()
-            .context("No number in note")?
-            .parse()
-            .context("Incorrect number of note")?;
-        Ok(self.task.find_note(num, optional)?.to_string().into())
+        let diff = fs::read_to_string(&diff_path).context(Message::CantReadDiffFile.text())?;
+        let mut hunks = parse_hunks(&diff).context(Message::IncorrectDiffFile.text())?;
+        ensure!(
+            index < hunks.len(),
+            "{}",
+            Message::NoHunk.format(&[&hunks.len(), &index])
+        );
+        let mut note = self.new_note(text);
+        note.add_hunk_reference(diff_path, hunks.swap_remove(index));
+        Ok(note)
+    }
+
+    /// Note of the active author in the current category
+    fn new_note(&self, text: String) -> ReviewNote {
+        let mut note = ReviewNote::new(text);
+        note.set_gutter(self.gutter.clone());
+        if let Some(category) = &self.category {
+            note.set_category(category.clone());
+        }
+        if self.authors.len() > 1 {
+            note.set_author(self.authors[self.active_author].name.clone());
+        }
+        note
+    }
+
+    fn store_note(&mut self, note: ReviewNote, optional: bool) -> anyhow::Result<bool> {
+        let added = if optional {
+            self.current_notes.add_optional_note(note)
+        } else {
+            self.current_notes.add_note(note)
+        };
+        self.update_author_tags();
+        self.current_notes
+            .save()
+            .context(Message::CantSaveNotes.text())?;
+        Ok(added)
+    }
+
+    /// Text of the task note by its number, or the free-form text if there is no number
+    fn find_note(&mut self, optional: bool, tokens: Tokens<'_>) -> anyhow::Result<NoteText> {
+        let first = tokens.peek().context(Message::NoNumberInNote.text())?;
+        let Some(num) = self
+            .note_ids
+            .is_id(&first)
+            .then(|| self.note_ids.index(&first, optional))
+            .transpose()?
+        else {
+            return Ok(NoteText::new(tokens.rest().to_string(), None));
+        };
+        ensure!(
+            self.task.has_notes(optional),
+            if optional {
+                Message::NoOptionalTaskNotes
+            } else {
+                Message::NoNecessaryTaskNotes
+            }
+            .text()
+        );
+        let note = self.task.find_note(num, optional)?.clone();
+        let mut values = Vec::new();
+        for name in note.placeholders() {
+            eprint!("{}: ", name);
+            stderr().flush().context(Message::CantFlushPrompt.text())?;
+            let mut value = String::new();
+            self.buf_reader
+                .read_line(&mut value)
+                .context(Message::ReadingLineFail.text())?;
+            values.push((name.to_string(), value.trim().to_string()));
+        }
+        Ok(NoteText::new(note.fill(&values), Some(num)))
+    }
+
+    /// Necessary task notes which are neither added nor waived
+    fn unaddressed_notes(&self) -> Vec<usize> {
+        let addressed = self
+            .current_notes
+            .notes()
+            .filter_map(|note| note.template())
+            .filter(|(_, optional)| !optional)
+            .map(|(num, _)| num)
+            .collect::<BTreeSet<_>>();
+        (0..self.task.notes_count().0)
+            .filter(|num| !addressed.contains(num) && !self.waived.contains(num))
+            .collect()
+    }
+
+    /// Read the code file at the review revision, or from the working tree
+    fn read_code(&self, file_path: &Path) -> anyhow::Result<String> {
+        if let Some(revision) = &self.revision {
+            match self.read_code_at_revision(file_path, revision) {
+                Ok(code) => return Ok(code),
+                Err(err) => warn!(
+                    "{}",
+                    Message::CantReadRevision.format(&[
+                        &file_path.display(),
+                        &revision,
+                        &format!("{:#}", err)
+                    ])
+                ),
+            }
+        }
+        Self::read_with_progress(file_path).context(Message::CantReadFileWithCode.text())
+    }
+
+    /// Files larger than this show a progress bar while being read
+    const PROGRESS_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+    fn read_with_progress(file_path: &Path) -> anyhow::Result<String> {
+        let file = File::open(file_path)?;
+        let size = file.metadata()?.len();
+        if size < Self::PROGRESS_THRESHOLD || !stderr().is_terminal() {
+            return Self::decode_code(fs::read(file_path)?, file_path);
+        }
+        let progress = ProgressBar::new(size).with_style(ProgressStyle::with_template(
+            "Reading code {bar:40} {bytes}/{total_bytes}",
+        )?);
+        let mut code = Vec::with_capacity(size as usize);
+        progress.wrap_read(file).read_to_end(&mut code)?;
+        progress.finish_and_clear();
+        Self::decode_code(code, file_path)
+    }
+
+    /// Bytes checked for zeros to detect a binary file, as git does
+    const BINARY_CHECK_LENGTH: usize = 8000;
+
+    fn decode_code(code: Vec<u8>, file_path: &Path) -> anyhow::Result<String> {
+        ensure!(
+            !code
+                .iter()
+                .take(Self::BINARY_CHECK_LENGTH)
+                .any(|&byte| byte == 0),
+            "{}",
+            Message::BinaryCodeFile.format(&[&file_path.display()])
+        );
+        String::from_utf8(code)
+            .with_context(|| Message::CodeFileNotUtf8.format(&[&file_path.display()]))
+    }
+
+    fn read_code_at_revision(&self, file_path: &Path, revision: &str) -> anyhow::Result<String> {
+        let dir = file_path
+            .parent()
+            .context(Message::CodeFileHasNoDirectory.text())?;
+        let file_name = file_path
+            .file_name()
+            .context(Message::CodeFileHasNoName.text())?;
+        let output = run_with_timeout(
+            Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .arg("show")
+                .arg(format!("{}:./{}", revision, file_name.to_string_lossy())),
+            self.external_command_timeout,
+        )?;
+        ensure!(
+            output.status.success(),
+            "{}",
+            Message::GitShowFailed.format(&[&String::from_utf8_lossy(&output.stderr).trim()])
+        );
+        Self::decode_code(output.stdout, file_path)
+    }
+
+    /// Pager from the value of `$PAGER` or the default one of the platform
+    fn pager_command(env: Option<OsString>) -> String {
+        match env.map(|pager| pager.to_string_lossy().to_string()) {
+            Some(pager) if !pager.trim().is_empty() => pager,
+            _ if cfg!(windows) => "more".to_string(),
+            _ => "less".to_string(),
+        }
+    }
+
+    /// Pipe the numbered code file into the pager of `$PAGER`
+    fn open_pager(&self) -> anyhow::Result<()> {
+        self.open_pager_with(&Self::pager_command(std::env::var_os("PAGER")))
+    }
+
+    /// Pipe the numbered code file into the pager, print it if the pager can't be started
+    fn open_pager_with(&self, pager: &str) -> anyhow::Result<()> {
+        let code = self.read_code(&self.active_file)?;
+        let numbered = code
+            .lines()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}\n", self.gutter.number(i + 1), line))
+            .collect::<String>();
+        let mut args = Tokens::new(pager);
+        let program = args.next().context(Message::EmptyPagerCommand.text())?;
+        let child = Command::new(&program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                warn!("{}", Message::CantStartPager.format(&[&program, &err]));
+                eprint!("{}", numbered);
+                return Ok(());
+            }
+        };
+        if let Some(mut input) = child.stdin.take() {
+            // The pager may be closed before reading everything
+            let _ = input.write_all(numbered.as_bytes());
+        }
+        child.wait().context(Message::PagerFailed.text())?;
+        Ok(())
+    }
+
+    /// Run configured check commands over the code file
+    fn run_checks(&self) {
+        if self.check_commands.is_empty() {
+            eprintln!("{}", Message::NoCheckCommands.text());
+            return;
+        }
+        let code_file = self.task.code_file_path(&self.project_dir);
+        for check in &self.check_commands {
+            eprintln!("{}", Message::RunCheck.format(&[check]));
+            let mut args =
+                Tokens::new(check).map(|arg| arg.replace("{file}", &code_file.to_string_lossy()));
+            let Some(program) = args.next() else {
+                continue;
+            };
+            let mut command = Command::new(program);
+            command.args(args);
+            match run_with_timeout(&mut command, self.external_command_timeout) {
+                Ok(output) => {
+                    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+                    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+                    eprintln!("{}", Message::CheckFinished.format(&[&output.status]));
+                }
+                Err(err) => eprintln!("{:#}", err),
+            }
+        }
+    }
+
+    /// Ask the reviewer a yes/no question, anything but yes is no
+    fn confirm(&mut self, question: &str) -> anyhow::Result<bool> {
+        eprint!("{} [y/N] ", question);
+        stderr().flush().context(Message::CantFlushPrompt.text())?;
+        let mut input = String::new();
+        self.buf_reader
+            .read_line(&mut input)
+            .context(Message::ReadingLineFail.text())?;
+        Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+
+    /// Offer clang-tidy findings from the `-export-fixes` file as optional notes
+    fn import_diagnostics(&mut self, path: &Path) -> anyhow::Result<()> {
+        let yaml = fs::read_to_string(path).context(Message::CantReadDiagnosticsFile.text())?;
+        let diagnostics = parse_diagnostics(&yaml, &self.project_dir)?;
+        let mut imported = 0;
+        for diagnostic in &diagnostics {
+            eprintln!(
+                "{}:{}: {}",
+                diagnostic.file.display(),
+                diagnostic.line,
+                diagnostic.note_text()
+            );
+            if !self.confirm(Message::AddDiagnostic.text())? {
+                continue;
+            }
+            let line = LineNumber::FromStart(diagnostic.line);
+            let text = diagnostic.note_text();
+            if self.add_referenced_note(
+                NoteText::new(text, None),
+                true,
+                diagnostic.file.clone(),
+                &[(line, line).into()],
+            )? {
+                imported += 1;
+            }
+        }
+        eprintln!(
+            "{}",
+            Message::ImportedDiagnostics.format(&[&imported, &diagnostics.len()])
+        );
+        Ok(())
     }
 
     fn show(&self) {
         match &self.task.show_method {
             ShowMethod::Console => self.show_with_writer(&mut std::io::BufWriter::new(stdout())),
-            ShowMethod::File { file_name } => self.show_with_writer(&mut std::io::BufWriter::new(
-                File::create(file_name).unwrap(),
-            )),
+            ShowMethod::File { file_name } | ShowMethod::Latex { file_name, .. } => {
+                let text = String::from_utf8(self.render()).expect("Review is not valid UTF-8");
+                fs::write(file_name, self.output_encoding.encode(&text)).unwrap()
+            }
+        }
+    }
+
+    /// Write the review to the file instead of the one of the show method, `-` is stdout
+    fn show_to(&self, path: &Path) -> anyhow::Result<()> {
+        let text = String::from_utf8(self.render()).context(Message::ReviewNotUtf8.text())?;
+        if path == Path::new("-") {
+            print!("{}", text);
+            return stdout().flush().context(Message::CantPrintReview.text());
+        }
+        fs::write(path, self.output_encoding.encode(&text))
+            .with_context(|| Message::CantWriteReviewTo.format(&[&path.display()]))
+    }
+
+    /// Review in the format of the show method: LaTeX or plain text
+    fn render(&self) -> Vec<u8> {
+        let mut text = Vec::new();
+        match &self.task.show_method {
+            ShowMethod::Latex { standalone, .. } => {
+                let document = self.document();
+                LatexReview {
+                    header: document.header,
+                    necessary_notes: &document.necessary_notes,
+                    optional_notes: &document.optional_notes,
+                    resolved_notes: &document.resolved_notes,
+                    language: &self.latex_language,
+                    footer: &self.footer,
+                    ids: self.note_ids,
+                    optional_first: self.current_notes.format().optional_first,
+                    standalone: *standalone,
+                }
+                .write(&mut text)
+                .unwrap()
+            }
+            ShowMethod::Console | ShowMethod::File { .. } => self.show_with_writer(&mut text),
+        }
+        text
+    }
+
+    /// Contributors and the link to the code
+    fn header_lines(&self) -> Vec<String> {
+        let mut lines = self
+            .contributors()
+            .iter()
+            .map(|author| author.to_string())
+            .collect::<Vec<_>>();
+        if let Some(source_url) = &self.source_url {
+            let source_url = source_url
+                .replace("{task}", &self.task.name)
+                .replace("{rev}", self.revision.as_deref().unwrap_or("HEAD"));
+            lines.push(format!("Source: {}", source_url));
         }
+        if let Some(title) = &self.task.title {
+            lines.push(format!("Task: {}", title));
+        }
+        if let Some(max_points) = self.task.max_points {
+            lines.push(format!("Max points: {}", max_points));
+        }
+        lines
     }
 
-    const AUTHOR_SEPARATOR: &'static str = separator!("+", 50);
+    /// Show the new numbers of the notes after they were moved
+    fn print_renumbered(&self, necessary: bool, optional: bool) {
+        if !self.print_renumbered_notes {
+            return;
+        }
+        let mut text = Vec::new();
+        if necessary {
+            ReviewDocument::write_toc_section(
+                &mut text,
+                self.note_ids,
+                false,
+                self.current_notes.necessary_notes(),
+            )
+            .unwrap();
+        }
+        if optional {
+            ReviewDocument::write_toc_section(
+                &mut text,
+                self.note_ids,
+                true,
+                self.current_notes.optional_notes(),
+            )
+            .unwrap();
+        }
+        let text = String::from_utf8(text).expect("Notes are not valid UTF-8");
+        if !text.is_empty() {
+            eprintln!("{}", text.trim_start());
+        }
+    }
+
+    /// The review as it is shown, the notes are copied from the storage
+    fn document(&self) -> ReviewDocument {
+        ReviewDocument {
+            header: self.header_lines(),
+            contents: self.table_of_contents,
+            group_by: self.group_by,
+            necessary_notes: self
+                .current_notes
+                .necessary_notes()
+                .iter()
+                .filter(|note| !note.is_resolved())
+                .cloned()
+                .collect(),
+            optional_notes: self
+                .current_notes
+                .optional_notes()
+                .iter()
+                .filter(|note| !note.is_resolved())
+                .cloned()
+                .collect(),
+            // The section tells they are resolved, the tag is not repeated
+            resolved_notes: self
+                .current_notes
+                .notes()
+                .filter(|note| note.is_resolved())
+                .map(|note| {
+                    let mut note = note.clone();
+                    note.set_resolved(false);
+                    note
+                })
+                .collect(),
+            footer: (!self.footer.is_empty()).then(|| self.footer.clone()),
+        }
+    }
 
     fn show_with_writer(&self, writer: &mut impl std::io::Write) {
-        write!(writer, "{}", self.author).unwrap();
-        write!(writer, "{}", Self::AUTHOR_SEPARATOR).unwrap();
-        self.current_notes.save_with_writer(writer).unwrap()
+        self.document()
+            .render(writer, self.current_notes.format())
+            .unwrap();
+    }
+
+    /// Check that all references point to existing lines of code files of the task
+    fn verify_references(&self) -> Vec<String> {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let code_files = self
+            .task
+            .code_files(&self.project_dir)
+            .iter()
+            .map(|path| canonical(path))
+            .collect::<Vec<_>>();
+        self.current_notes
+            .notes()
+            .flat_map(|note| note.references())
+            // References read back from the resumed review don't know their file
+            .filter(|reference| !reference.file.as_os_str().is_empty())
+            .filter_map(|reference| {
+                let location = format!(
+                    "{}:{}-{}",
+                    reference.file.display(),
+                    reference.rows.0,
+                    reference.rows.1
+                );
+                if reference.from_diff {
+                    (!reference.file.is_file())
+                        .then(|| Message::MissingReferenceFile.format(&[&location]))
+                } else if !code_files.contains(&canonical(&reference.file)) {
+                    Some(Message::NotCodeFile.format(&[&location]))
+                } else if !reference.file.is_file() {
+                    Some(Message::MissingReferenceFile.format(&[&location]))
+                } else {
+                    match self.read_code(&reference.file) {
+                        Ok(code) if code.lines().count() < reference.rows.1 => {
+                            Some(Message::OutOfFile.format(&[&location, &code.lines().count()]))
+                        }
+                        Ok(_) => None,
+                        Err(err) => Some(
+                            Message::UnreadableReference
+                                .format(&[&location, &format!("{:#}", err)]),
+                        ),
+                    }
+                }
+            })
+            .collect()
     }
 
     fn finish_review(&mut self) -> anyhow::Result<()> {
+        self.current_notes
+            .flush()
+            .context(Message::CantSaveNotes.text())?;
+        let problems = self.verify_references();
+        if !problems.is_empty() {
+            eprintln!(
+                "{} {}",
+                color::warning(Message::Warning.text()),
+                Message::StaleReferences.format(&[&problems.len()])
+            );
+            for problem in problems {
+                eprintln!("  {}", problem);
+            }
+        }
+        self.print_used_templates();
+        if !self.previous_notes.is_empty() {
+            let current = self
+                .current_notes
+                .notes()
+                .map(|note| note.body())
+                .collect::<Vec<_>>();
+            eprintln!("{}", compare_notes(&self.previous_notes, &current));
+        }
         self.state = ReviewState::Finish;
-        println!("Review finished");
+        self.completed = true;
+        if self.session_file.exists() {
+            fs::remove_file(&self.session_file).context(Message::CantRemoveSessionFile.text())?;
+        }
+        let duration = format_duration(self.duration());
+        eprintln!("{}", Message::ReviewFinished.format(&[&duration]));
         Ok(())
     }
 
+    /// Task notes the review notes were added from, e.g. `Task notes used: necessary 0, 2; optional 1`
+    fn print_used_templates(&self) {
+        let sections = [
+            (false, Message::TemplatesNecessary),
+            (true, Message::TemplatesOptional),
+        ]
+        .into_iter()
+        .filter_map(|(optional, name)| {
+            let ids = self
+                .current_notes
+                .notes()
+                .filter_map(|note| note.template())
+                .filter(|(_, template_optional)| *template_optional == optional)
+                .map(|(num, _)| num)
+                .sorted()
+                .dedup()
+                .map(|num| self.note_ids.id(num, optional))
+                .collect::<Vec<_>>();
+            (!ids.is_empty()).then(|| name.format(&[&ids.join(", ")]))
+        })
+        .collect::<Vec<_>>();
+        if !sections.is_empty() {
+            eprintln!("{}", Message::TaskNotesUsed.format(&[&sections.join("; ")]));
+        }
+    }
+
+    /// Time since the review was started
+    fn duration(&self) -> Duration {
+        self.clock.now() - self.started
+    }
+
+    /// Take the time from the clock instead of the system one
+    #[cfg(test)]
+    fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.started = clock.now();
+        self.last_flush = clock.now();
+        self.clock = clock;
+        self
+    }
+
     pub(crate) fn is_finished(&self) -> bool {
         matches!(self.state, ReviewState::Finish)
     }
+
+    pub(crate) fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Give the input back for the review of the next task
+    pub(crate) fn into_reader(self) -> InputReader {
+        self.buf_reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{project, TempDir};
+    use std::cell::Cell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    fn review(context: ProjectContext, input: &str) -> anyhow::Result<Review> {
+        let options = ReviewOptions {
+            quiet: true,
+            ..ReviewOptions::default()
+        };
+        let reader = InputReader(Box::new(Cursor::new(input.to_string())));
+        Review::new(context, options, reader)
+    }
+
+    #[test]
+    fn unreadable_review_is_not_overwritten() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let bytes = b"Format: 2\nNecessary:\n0) Not in UTF-8 \xFF\n";
+        fs::write(dir.join("reviews/t.txt"), bytes).unwrap();
+        let err = review(context, "").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::CantReadPreviousReview.format(&[&dir.join("reviews/t.txt").display()])
+        );
+        assert_eq!(fs::read(dir.join("reviews/t.txt")).unwrap(), bytes);
+    }
+
+    fn run(review: &mut Review) {
+        while !review.is_finished() {
+            review.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn authors_of_the_resumed_review_are_kept() {
+        let dir = TempDir::new();
+        let co_authors = serde_json::json!({
+            "co_authors": [{"name": "Ivan Ivanov", "contacts": "@ivanov"}],
+        });
+        let input = "new Unused variable\nadd 0\nwho II\nadd 0\nquit\n";
+        run(&mut review(project(&dir, co_authors.clone()), input).unwrap());
+
+        let mut resumed = review(project(&dir, co_authors), "y\n").unwrap();
+        resumed.step().unwrap();
+        let header = resumed.header_lines();
+        assert_eq!(header.len(), 2);
+        assert!(header[0].contains("Anton Kopanov"));
+        assert!(header[1].contains("Ivan Ivanov"));
+        let notes = resumed.current_notes.necessary_notes();
+        assert_eq!(notes[0].text(), "[Anton Kopanov] Unused variable");
+        assert_eq!(notes[1].text(), "[Ivan Ivanov] Unused variable");
+    }
+
+    #[test]
+    fn task_notes_of_the_resumed_review_are_kept() {
+        let dir = TempDir::new();
+        let input = "new Unused variable\nnew o Use const\nadd 0\nadd o 0\nquit\n";
+        run(&mut review(project(&dir, serde_json::json!({})), input).unwrap());
+
+        let mut resumed = review(project(&dir, serde_json::json!({})), "y\n").unwrap();
+        resumed.step().unwrap();
+        let templates = resumed
+            .current_notes
+            .notes()
+            .map(|note| note.template())
+            .collect::<Vec<_>>();
+        assert_eq!(templates, [Some((0, false)), Some((0, true))]);
+    }
+
+    #[test]
+    fn added_and_waived_notes_of_the_resumed_review_are_addressed() {
+        let dir = TempDir::new();
+        let settings = serde_json::json!({"require_all_necessary": true});
+        let input = "new Unused variable\nnew Magic number\nnew Off-by-one\nadd 0\nwaive 1\nquit\n";
+        run(&mut review(project(&dir, settings.clone()), input).unwrap());
+
+        let mut resumed = review(project(&dir, settings.clone()), "y\ncomplete\nquit\n").unwrap();
+        run(&mut resumed);
+        assert!(!resumed.is_completed());
+        assert_eq!(resumed.unaddressed_notes(), [2]);
+
+        let input = "y\nadd 2\ncomplete\n";
+        let mut resumed = review(project(&dir, settings), input).unwrap();
+        run(&mut resumed);
+        assert!(resumed.is_completed());
+    }
+
+    fn blank_line_project(dir: &TempDir) -> ProjectContext {
+        let context = project(dir, serde_json::json!({}));
+        fs::write(
+            dir.join("tasks/t/main.cpp"),
+            "int main() {\n\n    return 0;\n}\n",
+        )
+        .unwrap();
+        context
+    }
+
+    #[test]
+    fn referenced_note_is_added_without_questions() {
+        let dir = TempDir::new();
+        let mut review = review(blank_line_project(&dir), "").unwrap();
+        let line = LineNumber::FromStart(2);
+        let note = review
+            .build_referenced_note(
+                "Remove the blank line".to_string(),
+                dir.join("tasks/t/main.cpp"),
+                &[(line, line).into()],
+            )
+            .unwrap();
+        let added = review.store_referenced_note(note, None, false).unwrap();
+        assert!(added);
+        assert_eq!(
+            review.current_notes.necessary_notes()[0].references().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn blank_reference_is_dropped_when_declined() {
+        let dir = TempDir::new();
+        let input = "add r 2 2 Remove the blank line\nn\nadd r 1 2 Too long\nquit\n";
+        let mut review = review(blank_line_project(&dir), input).unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        assert_eq!(notes[0].references().len(), 0);
+        assert_eq!(notes[1].references().len(), 1);
+    }
+
+    #[test]
+    fn failed_command_is_not_repeated() {
+        let dir = TempDir::new();
+        let input = "new Unused variable\nadd 0\nadd r 100 100 Out of the file\n!!\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        assert_eq!(review.last_command.as_deref().map(str::trim), Some("add 0"));
+        assert_eq!(review.current_notes.necessary_notes().len(), 2);
+    }
+
+    #[test]
+    fn informational_command_is_not_repeated() {
+        let dir = TempDir::new();
+        let input = "add Unused variable\ncheck\n!!\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        assert_eq!(
+            review.last_command.as_deref().map(str::trim),
+            Some("add Unused variable")
+        );
+        assert_eq!(review.current_notes.necessary_notes().len(), 2);
+    }
+
+    /// Clock moved by the test
+    #[derive(Debug, Clone)]
+    struct ManualClock(Rc<Cell<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, secs: u64) {
+            self.0.set(self.0.get() + Duration::from_secs(secs));
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn duration_is_taken_from_the_clock() {
+        let dir = TempDir::new();
+        let clock = ManualClock(Rc::new(Cell::new(Instant::now())));
+        let review = review(project(&dir, serde_json::json!({})), "")
+            .unwrap()
+            .with_clock(Box::new(clock.clone()));
+        clock.advance(3725);
+        assert_eq!(review.duration(), Duration::from_secs(3725));
+        assert_eq!(format_duration(review.duration()), "1:02:05");
+        assert_eq!(format_duration(Duration::from_secs(59)), "0:00:59");
+    }
+
+    #[test]
+    fn notes_are_autosaved_after_the_interval() {
+        let dir = TempDir::new();
+        let clock = ManualClock(Rc::new(Cell::new(Instant::now())));
+        let context = project(&dir, serde_json::json!({"autosave_secs": 60}));
+        let mut review = review(context, "new Unused variable\nadd 0\n")
+            .unwrap()
+            .with_clock(Box::new(clock.clone()));
+        review.step().unwrap();
+        review.step().unwrap();
+        review.step().unwrap();
+        let saved = || fs::read_to_string(dir.join("reviews/t.txt")).unwrap();
+        assert!(!saved().contains("Unused variable"));
+        clock.advance(60);
+        review.autosave().unwrap();
+        assert!(saved().contains("Unused variable"));
+    }
+
+    #[test]
+    fn review_is_completed_only_when_confirmed() {
+        let settings = serde_json::json!({"confirm_complete": true});
+        for (answer, completed) in [("n", false), ("", false), ("Y", true), ("yes", true)] {
+            let dir = TempDir::new();
+            let input = format!("complete\n{}\nquit\n", answer);
+            let mut review = review(project(&dir, settings.clone()), &input).unwrap();
+            run(&mut review);
+            assert_eq!(review.is_completed(), completed, "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn merged_notes_are_joined() {
+        let dir = TempDir::new();
+        let input = "add r 2 2 Unused variable\nadd Remove it\nmerge 0 1\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].body(), "Unused variable Remove it");
+        assert_eq!(notes[0].references().len(), 1);
+    }
+
+    #[test]
+    fn notes_are_moved_between_sections() {
+        let dir = TempDir::new();
+        let input = "add Unused variable\nadd o Use const\ndemote 0\npromote 0\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        let necessary = review.current_notes.necessary_notes();
+        let optional = review.current_notes.optional_notes();
+        assert_eq!(necessary.len(), 1);
+        assert_eq!(necessary[0].body(), "Use const");
+        assert_eq!(optional.len(), 1);
+        assert_eq!(optional[0].body(), "Unused variable");
+    }
+
+    #[test]
+    fn code_is_read_at_the_revision() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let task_dir = dir.join("tasks/t");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&task_dir)
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "main.cpp"]);
+        git(&["commit", "-q", "-m", "Submission"]);
+        fs::write(task_dir.join("main.cpp"), "int main() {}\n").unwrap();
+
+        let options = ReviewOptions {
+            quiet: true,
+            revision: Some("HEAD".to_string()),
+            ..ReviewOptions::default()
+        };
+        let reader = InputReader(Box::new(Cursor::new(String::new())));
+        let review = Review::new(context, options, reader).unwrap();
+        let code = review.read_code(&task_dir.join("main.cpp")).unwrap();
+        assert!(code.contains("int x;"));
+    }
+
+    #[test]
+    fn note_text_is_added_without_task_notes() {
+        let dir = TempDir::new();
+        let input = "add Unused variable\nadd 0\nadd o Use const\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        let necessary = review.current_notes.necessary_notes();
+        assert_eq!(necessary.len(), 1);
+        assert_eq!(necessary[0].body(), "Unused variable");
+        assert_eq!(necessary[0].template(), None);
+        assert_eq!(review.current_notes.optional_notes()[0].body(), "Use const");
+    }
+
+    #[test]
+    fn peeked_note_is_not_added() {
+        let dir = TempDir::new();
+        let input = "peek r 2 2 Unused variable\np o Use const\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        assert_eq!(review.current_notes.notes().count(), 0);
+        assert!(matches!(
+            review.parse_action("peek r 2 2 Unused variable"),
+            Ok(ReviewAction::Peek(action)) if matches!(*action, ReviewAction::AddNote(..))
+        ));
+    }
+
+    #[test]
+    fn references_past_the_end_are_reported() {
+        let dir = TempDir::new();
+        let input = "add r 1 1 Missing includes\nadd r 3 4 Return is not needed\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        assert!(review.verify_references().is_empty());
+        let file = dir.join("tasks/t/main.cpp");
+        fs::write(&file, "int main() {}\n").unwrap();
+        assert_eq!(
+            review.verify_references(),
+            [Message::OutOfFile.format(&[&format!("{}:3-4", file.display()), &1])]
+        );
+    }
+
+    #[test]
+    fn checklist_is_acknowledged_before_the_review() {
+        let dir = TempDir::new();
+        let settings = serde_json::json!({"checklist": ["Tests", "Style", "Memory"]});
+        let input = "\nskip\nadd Unused variable\nquit\n";
+        let mut review = review(project(&dir, settings), input).unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].body(), "Unused variable");
+    }
+
+    #[test]
+    fn same_reference_reuses_the_previous_lines() {
+        let dir = TempDir::new();
+        let input = "add r same Nothing to reuse\nadd r 2 3 Unused variable\nadd o r same Use const\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        let necessary = review.current_notes.necessary_notes();
+        assert_eq!(necessary.len(), 1);
+        let optional = review.current_notes.optional_notes();
+        assert_eq!(optional[0].body(), "Use const");
+        assert_eq!(optional[0].references()[0].rows, (2, 3));
+        assert_eq!(
+            optional[0].references()[0].file,
+            necessary[0].references()[0].file
+        );
+    }
+
+    #[test]
+    fn binary_code_file_is_not_read() {
+        let path = Path::new("main.cpp");
+        let err = Review::decode_code(b"int\0main".to_vec(), path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::BinaryCodeFile.format(&[&path.display()])
+        );
+        let err = Review::decode_code(b"int \xFF".to_vec(), path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Message::CodeFileNotUtf8.format(&[&path.display()])
+        );
+        assert_eq!(
+            Review::decode_code(b"int main() {}".to_vec(), path).unwrap(),
+            "int main() {}"
+        );
+    }
+
+    #[test]
+    fn code_is_shown_in_the_pager_or_printed() {
+        let dir = TempDir::new();
+        let review = review(project(&dir, serde_json::json!({})), "").unwrap();
+        let pager = Review::pager_command(Some(OsString::from("cat -u")));
+        assert_eq!(pager, "cat -u");
+        review.open_pager_with(&pager).unwrap();
+        review.open_pager_with("assist_tool_missing_pager").unwrap();
+        let default = if cfg!(windows) { "more" } else { "less" };
+        assert_eq!(Review::pager_command(Some(OsString::from(" "))), default);
+        assert_eq!(Review::pager_command(None), default);
+    }
+
+    #[test]
+    fn bookmarked_lines_are_referenced_and_resumed() {
+        let dir = TempDir::new();
+        let input =
+            "bookmark body 2 -2\nadd r bm body Unused variable\nadd r bm head Missing\nquit\n";
+        let mut first = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut first);
+        let notes = first.current_notes.necessary_notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].references()[0].rows, (2, 3));
+
+        let mut resumed = review(project(&dir, serde_json::json!({})), "y\n").unwrap();
+        resumed.step().unwrap();
+        assert_eq!(
+            resumed.bookmarks.keys().collect::<Vec<_>>(),
+            [&"body".to_string()]
+        );
+    }
+
+    #[test]
+    fn reference_links_are_relative_to_the_project() {
+        let dir = TempDir::new();
+        let input = "add r 2 2 Unused variable\nquit\n";
+        let settings = serde_json::json!({"reference_links": true});
+        let mut review = review(project(&dir, settings), input).unwrap();
+        run(&mut review);
+        let text = review.current_notes.necessary_notes()[0].text();
+        let link = Path::new("tasks/t/main.cpp").display().to_string() + ":2\n";
+        assert!(text.contains(&link), "{}", text);
+    }
+
+    #[test]
+    fn review_is_shown_to_the_given_file() {
+        let dir = TempDir::new();
+        let given = dir.join("given.txt");
+        let output = dir.join("output.txt");
+        let input = format!(
+            "add Unused variable\nshow \"{}\"\nshow\nquit\n",
+            given.display()
+        );
+        let options = ReviewOptions {
+            quiet: true,
+            output: Some(output.clone()),
+            ..ReviewOptions::default()
+        };
+        let reader = InputReader(Box::new(Cursor::new(input)));
+        let context = project(&dir, serde_json::json!({}));
+        run(&mut Review::new(context, options, reader).unwrap());
+        for path in [given, output] {
+            let text = fs::read_to_string(&path).unwrap();
+            assert!(text.contains("0) Unused variable"), "{}", path.display());
+        }
+        assert!(!dir.join("tasks/t/out.txt").exists());
+    }
+
+    #[test]
+    fn separators_have_the_default_length() {
+        assert_eq!(separator!("*", 3), "\n***\n");
+        for separator in [
+            TASK_SEPARATOR,
+            ReviewNote::NOTE_SEPARATOR,
+            ReviewDocument::AUTHOR_SEPARATOR,
+        ] {
+            assert_eq!(separator.trim().chars().count(), SEPARATOR_LENGTH);
+            assert!(separator.starts_with('\n') && separator.ends_with('\n'));
+        }
+    }
+
+    #[test]
+    fn long_reference_fails_only_in_the_strict_mode() {
+        let input = "add r 1 3 Too long\nadd r 2 3 Short\nquit\n";
+        for (strict, added) in [(false, 2), (true, 1)] {
+            let dir = TempDir::new();
+            let mut context = project(&dir, serde_json::json!({"max_reference_lines": 2}));
+            context.settings.cli_strict = strict;
+            let mut review = review(context, input).unwrap();
+            run(&mut review);
+            assert_eq!(review.current_notes.necessary_notes().len(), added);
+        }
+    }
+
+    #[test]
+    fn reference_at_the_limit_is_added_in_the_strict_mode() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({"max_reference_lines": 3}));
+        context.settings.cli_strict = true;
+        let mut review = review(
+            context,
+            "add r 1 3 At the limit
+quit
+",
+        )
+        .unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        assert_eq!(notes[0].references()[0].rows, (1, 3));
+    }
+
+    #[test]
+    fn long_reference_is_kept_whole_in_the_lenient_mode() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({"max_reference_lines": 1}));
+        let mut review = review(
+            context,
+            "add r 1 4 Beyond the limit
+quit
+",
+        )
+        .unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        assert_eq!(notes[0].references()[0].rows, (1, 4));
+    }
+
+    #[test]
+    fn resolved_notes_are_shown_in_their_section() {
+        let dir = TempDir::new();
+        let input = "add Unused variable\nadd Magic number\nadd o Use const\nresolve 0\nresolve o 0\nresolve o 0\nquit\n";
+        let mut review = review(project(&dir, serde_json::json!({})), input).unwrap();
+        run(&mut review);
+        let document = review.document();
+        let texts = |notes: &[ReviewNote]| notes.iter().map(|note| note.text()).collect::<Vec<_>>();
+        assert_eq!(texts(&document.necessary_notes), ["Magic number"]);
+        assert_eq!(texts(&document.optional_notes), ["Use const"]);
+        assert_eq!(texts(&document.resolved_notes), ["Unused variable"]);
+
+        let mut text = Vec::new();
+        document
+            .render(&mut text, review.current_notes.format())
+            .unwrap();
+        let parsed = ReviewDocument::parse(text.as_slice(), review.current_notes.format()).unwrap();
+        assert!(parsed.resolved_notes[0].is_resolved());
+        assert_eq!(parsed.resolved_notes[0].body(), "Unused variable");
+        assert_eq!(texts(&parsed.necessary_notes), ["Magic number"]);
+    }
+
+    #[test]
+    fn large_code_file_is_read_with_progress_on_stderr() {
+        let dir = TempDir::new();
+        let path = dir.join("large.cpp");
+        let line = "int x = 0;\n";
+        let code = line.repeat(Review::PROGRESS_THRESHOLD as usize / line.len() + 1);
+        fs::write(&path, &code).unwrap();
+        // The progress bar is drawn only if stderr is a terminal, the code is read whole anyway
+        assert_eq!(Review::read_with_progress(&path).unwrap(), code);
+    }
+
+    #[test]
+    fn references_quote_the_used_file() {
+        let dir = TempDir::new();
+        let mut context = project(&dir, serde_json::json!({}));
+        context.tasks[0].extra_code_files = vec![PathBuf::from("include/util.h")];
+        fs::create_dir_all(dir.join("tasks/t/include")).unwrap();
+        fs::write(dir.join("tasks/t/include/util.h"), "#pragma once\n").unwrap();
+        let input = "usefile other.h\nusefile util.h\nadd r 1 1 Header note\nusefile\nadd r 2 2 Code note\nquit\n";
+        let mut review = review(context, input).unwrap();
+        run(&mut review);
+        let notes = review.current_notes.necessary_notes();
+        let files = notes.iter().map(|note| note.references()[0].file.clone());
+        assert_eq!(
+            files.collect::<Vec<_>>(),
+            [
+                dir.join("tasks/t/include/util.h"),
+                dir.join("tasks/t/main.cpp")
+            ]
+        );
+        assert_eq!(notes[0].references()[0].code(), "#pragma once");
+    }
+
+    #[test]
+    fn read_only_review_is_not_changed() {
+        let dir = TempDir::new();
+        let read_only = |input: &str| {
+            let options = ReviewOptions {
+                quiet: true,
+                read_only: true,
+                ..ReviewOptions::default()
+            };
+            let context = project(&dir, serde_json::json!({}));
+            let reader = InputReader(Box::new(Cursor::new(input.to_string())));
+            Review::new(context, options, reader)
+        };
+        assert!(read_only("quit\n").is_err());
+        let mut first = review(
+            project(&dir, serde_json::json!({})),
+            "add Unused variable\nquit\n",
+        )
+        .unwrap();
+        run(&mut first);
+        let text = fs::read_to_string(dir.join("reviews/t.txt")).unwrap();
+        let mut viewed = read_only("add Other note\nshow\ncomplete\nquit\n").unwrap();
+        run(&mut viewed);
+        assert!(!viewed.is_completed());
+        assert_eq!(viewed.current_notes.notes().count(), 1);
+        assert_eq!(fs::read_to_string(dir.join("reviews/t.txt")).unwrap(), text);
+        // The review is shown to stdout instead of the output file of the task
+        assert!(!dir.join("tasks/t/out.txt").exists());
+    }
 }
@@ -0,0 +1,87 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::reviewing::notes::LineNumber;
+
+/// Extension of the session files next to the reviews
+pub(crate) const SESSION_EXTENSION: &str = "session.json";
+
+/// Transient state of the review which is not in the notes file, saved after each action to
+/// resume the review if the tool was killed
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(super) struct Session {
+    /// Name of the active author
+    pub author: String,
+    pub category: Option<String>,
+    pub last_reference: Option<(PathBuf, (usize, usize))>,
+    pub last_command: Option<String>,
+    #[serde(default)]
+    pub bookmarks: BTreeMap<String, (LineNumber, LineNumber)>,
+    /// Necessary task notes left out with `waive`, the added ones are known from the notes
+    #[serde(default)]
+    pub waived: BTreeSet<usize>,
+    /// Notes of the previous finished review of the task
+    #[serde(default)]
+    pub previous_notes: Vec<String>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path).context(Message::CantReadSessionFile.text())?;
+        serde_json::from_str(&text).context(Message::CantParseSessionFile.text())
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text =
+            serde_json::to_string_pretty(self).context(Message::CantSerializeSession.text())?;
+        // Write the whole file at once, so a crash leaves the previous session intact
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, text).context(Message::CantWriteSessionFile.text())?;
+        fs::rename(&temp_path, path).context(Message::CantWriteSessionFile.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn session_round_trips_through_the_file() {
+        let dir = TempDir::new();
+        let path = dir.join(Path::new("t").with_extension(SESSION_EXTENSION));
+        let session = Session {
+            author: "Ivan Ivanov".to_string(),
+            category: Some("Style".to_string()),
+            last_reference: Some((PathBuf::from("main.cpp"), (2, 3))),
+            last_command: Some("add 0".to_string()),
+            waived: BTreeSet::from([1]),
+            ..Session::default()
+        };
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded.author, session.author);
+        assert_eq!(loaded.category, session.category);
+        assert_eq!(loaded.last_reference, session.last_reference);
+        assert_eq!(loaded.last_command, session.last_command);
+        assert_eq!(loaded.waived, session.waived);
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn session_of_the_older_version_is_loaded() {
+        let dir = TempDir::new();
+        let path = dir.join("t.session.json");
+        let text = r#"{"author": "Anton Kopanov", "category": null, "last_reference": null, "last_command": null}"#;
+        fs::write(&path, text).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded.author, "Anton Kopanov");
+        assert!(loaded.bookmarks.is_empty());
+        assert!(loaded.waived.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+
+use anyhow::Context;
+use itertools::Itertools;
+use log::warn;
+use serde::Serialize;
+
+use crate::messages::Message;
+use crate::preparing::context::ProjectContext;
+use crate::reviewing::document::ReviewDocument;
+use crate::reviewing::session::SESSION_EXTENSION;
+
+/// Summary of all reviews of the project
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Stats {
+    pub tasks: usize,
+    /// Review files with at least one note
+    pub reviews: usize,
+    pub necessary_notes: usize,
+    pub optional_notes: usize,
+    pub average_notes: f64,
+    pub references: usize,
+    pub referenced_lines: usize,
+    /// Notes of each category, the notes without category are in `General`
+    pub categories: BTreeMap<String, CategoryStats>,
+}
+
+/// Notes of one category by their severity
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct CategoryStats {
+    pub necessary: usize,
+    pub optional: usize,
+}
+
+/// Read all review files of the project
+pub(crate) fn collect_stats(context: &ProjectContext) -> anyhow::Result<Stats> {
+    let mut stats = Stats {
+        tasks: context.tasks.len(),
+        ..Stats::default()
+    };
+    let reviews_dir = context.project_dir.join("reviews");
+    let format = context.settings.notes_format();
+    for entry in fs::read_dir(&reviews_dir).context(Message::CantReadReviewsDirectory.text())? {
+        let path = entry
+            .context(Message::CantReadReviewsDirectory.text())?
+            .path();
+        if !path.is_file() || path.to_string_lossy().ends_with(SESSION_EXTENSION) {
+            continue;
+        }
+        let bytes = fs::read(&path)
+            .with_context(|| Message::CantReadNamedReview.format(&[&path.display()]))?;
+        let text = match context.settings.output_encoding.decode(&bytes) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!(
+                    "{}",
+                    Message::ReviewSkipped.format(&[&path.display(), &format!("{:#}", err)])
+                );
+                continue;
+            }
+        };
+        let (necessary, optional) = match ReviewDocument::parse(text.as_bytes(), &format) {
+            Ok(review) => (review.necessary_notes, review.optional_notes),
+            Err(err) => {
+                warn!(
+                    "{}",
+                    Message::ReviewSkipped.format(&[&path.display(), &format!("{:#}", err)])
+                );
+                continue;
+            }
+        };
+        if necessary.is_empty() && optional.is_empty() {
+            continue;
+        }
+        stats.reviews += 1;
+        stats.necessary_notes += necessary.len();
+        stats.optional_notes += optional.len();
+        for (note, is_optional) in necessary
+            .iter()
+            .map(|note| (note, false))
+            .chain(optional.iter().map(|note| (note, true)))
+        {
+            let category = note
+                .category()
+                .unwrap_or(ReviewDocument::DEFAULT_CATEGORY)
+                .to_string();
+            let category = stats.categories.entry(category).or_default();
+            if is_optional {
+                category.optional += 1;
+            } else {
+                category.necessary += 1;
+            }
+        }
+        for reference in necessary
+            .iter()
+            .chain(&optional)
+            .flat_map(|note| note.references())
+        {
+            stats.references += 1;
+            stats.referenced_lines += reference.rows.1 + 1 - reference.rows.0;
+        }
+    }
+    if stats.reviews > 0 {
+        stats.average_notes =
+            (stats.necessary_notes + stats.optional_notes) as f64 / stats.reviews as f64;
+    }
+    Ok(stats)
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = [
+            (Message::StatsTasks, self.tasks.to_string()),
+            (Message::StatsReviews, self.reviews.to_string()),
+            (
+                Message::StatsNecessaryNotes,
+                self.necessary_notes.to_string(),
+            ),
+            (Message::StatsOptionalNotes, self.optional_notes.to_string()),
+            (
+                Message::StatsNotesPerReview,
+                format!("{:.1}", self.average_notes),
+            ),
+            (Message::StatsReferences, self.references.to_string()),
+            (
+                Message::StatsReferencedLines,
+                self.referenced_lines.to_string(),
+            ),
+        ];
+        // The values are aligned after the longest label of the locale
+        let width = rows
+            .iter()
+            .map(|(label, _)| label.text().chars().count() + 1)
+            .max()
+            .unwrap_or_default();
+        let table = rows
+            .iter()
+            .map(|(label, value)| format!("{:<width$} {}", label.text(), value))
+            .join("\n");
+        write!(f, "{}", table)?;
+        if !self.categories.is_empty() {
+            write!(f, "\n{}", Message::StatsCategories.text())?;
+        }
+        for (name, category) in &self.categories {
+            write!(
+                f,
+                "\n  {}",
+                Message::StatsCategory.format(&[name, &category.necessary, &category.optional])
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preparing::notes::FORMAT_HEADER;
+    use crate::reviewing::notes::ReviewNote;
+    use crate::testing::{project, TempDir};
+
+    #[test]
+    fn notes_are_counted_by_category_and_severity() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let review = "Format: 2\nNecessary:\n0) Unused variable\nMeta: {\"category\":\"Style\"}\n\
+            1) Off-by-one\nOptional:\n0) Use const\nMeta: {\"category\":\"Style\"}\n";
+        fs::write(dir.join("reviews/t.txt"), review).unwrap();
+        fs::write(dir.join("reviews/t.session.json"), "{}").unwrap();
+
+        let stats = collect_stats(&context).unwrap();
+        assert_eq!(stats.reviews, 1);
+        assert_eq!(stats.necessary_notes, 2);
+        assert_eq!(stats.optional_notes, 1);
+        let categories = stats
+            .categories
+            .iter()
+            .map(|(name, category)| (name.as_str(), category.necessary, category.optional))
+            .collect::<Vec<_>>();
+        assert_eq!(categories, [("General", 1, 0), ("Style", 1, 1)]);
+        let expected = format!(
+            "{}\n  {}\n  {}",
+            Message::StatsCategories.text(),
+            Message::StatsCategory.format(&[&"General", &1, &0]),
+            Message::StatsCategory.format(&[&"Style", &1, &1])
+        );
+        assert!(stats.to_string().ends_with(&expected));
+    }
+
+    #[test]
+    fn directory_of_sample_reviews_is_summed() {
+        let dir = TempDir::new();
+        let context = project(&dir, serde_json::json!({}));
+        let note_separator = ReviewNote::NOTE_SEPARATOR;
+        let author_separator = ReviewDocument::AUTHOR_SEPARATOR;
+        let samples = [
+            // The reviews file
+            (
+                "t.txt",
+                format!(
+                    "{FORMAT_HEADER}\nNecessary:\n0) Unused variable{note_separator}   2:     int x;\n   3:     return 0;{note_separator}"
+                ),
+            ),
+            // The shown review with the author header
+            (
+                "u.txt",
+                format!(
+                    "Author: Anton Kopanov(tg: @ak){author_separator}Necessary:\n0) Off-by-one\nOptional:\n0) Use const\n"
+                ),
+            ),
+            // The review left without notes is not counted
+            ("v.txt", format!("{FORMAT_HEADER}\n")),
+        ];
+        for (name, text) in samples {
+            fs::write(dir.join("reviews").join(name), text).unwrap();
+        }
+
+        let stats = collect_stats(&context).unwrap();
+        assert_eq!(stats.reviews, 2);
+        assert_eq!(stats.necessary_notes, 2);
+        assert_eq!(stats.optional_notes, 1);
+        assert_eq!(stats.average_notes, 1.5);
+        assert_eq!(stats.references, 1);
+        assert_eq!(stats.referenced_lines, 2);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+/// Other names of the keywords of the commands, e.g. `опц` for `optional`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CommandAliases(BTreeMap<String, String>);
+
+impl CommandAliases {
+    /// Aliases are case-insensitive as the keywords
+    pub fn new(aliases: &BTreeMap<String, String>) -> Self {
+        Self(
+            aliases
+                .iter()
+                .map(|(alias, keyword)| (alias.to_lowercase(), keyword.to_lowercase()))
+                .collect(),
+        )
+    }
+
+    /// The keyword of the alias
+    pub fn keyword(&self, alias: &str) -> Option<&str> {
+        self.0.get(&alias.to_lowercase()).map(String::as_str)
+    }
+
+    /// The lowercase keyword of the word, the word itself if it is not an alias
+    pub fn resolve(&self, word: &str) -> String {
+        self.keyword(word)
+            .map_or_else(|| word.to_lowercase(), str::to_string)
+    }
+}
+
+/// Shell-like words of the command: quotes group several words into one, a backslash escapes
+/// a quote, a backslash or a space. The free-form rest of the command is available as it is.
+#[derive(Debug, Clone)]
+pub(super) struct Tokens<'a> {
+    rest: &'a str,
+    aliases: Option<&'a CommandAliases>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            rest: input,
+            aliases: None,
+        }
+    }
+
+    /// Keywords are also recognized by their aliases
+    pub fn with_aliases(mut self, aliases: &'a CommandAliases) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
+    /// The next word as the keyword: lowercase and with the alias resolved
+    pub fn next_keyword(&mut self) -> Option<String> {
+        let word = self.next()?;
+        Some(match self.aliases {
+            Some(aliases) => aliases.resolve(&word),
+            None => word.to_lowercase(),
+        })
+    }
+
+    /// The next word and the input after it, an unclosed quote lasts to the end of the input
+    fn split(&self) -> Option<(String, &'a str)> {
+        let input = self.rest.trim_start();
+        if input.is_empty() {
+            return None;
+        }
+        let mut word = String::new();
+        let mut quote = None;
+        let mut chars = input.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                (None, c) if c.is_whitespace() => return Some((word, &input[i..])),
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(open), c) if c == open => quote = None,
+                (Some('\''), c) => word.push(c),
+                (_, '\\') => match chars.peek() {
+                    Some(&(_, next))
+                        if next == '"'
+                            || next == '\\'
+                            || (quote.is_none() && (next == '\'' || next.is_whitespace())) =>
+                    {
+                        word.push(next);
+                        chars.next();
+                    }
+                    _ => word.push(c),
+                },
+                (_, c) => word.push(c),
+            }
+        }
+        Some((word, ""))
+    }
+
+    /// The next word without consuming it
+    pub fn peek(&self) -> Option<String> {
+        self.split().map(|(word, _)| word)
+    }
+
+    /// Consume the next word if it is one of the `words` or an alias of one of them
+    pub fn next_if_any(&mut self, words: &[&str]) -> bool {
+        let matches = |word: &str| {
+            words.contains(&word)
+                || self
+                    .aliases
+                    .and_then(|aliases| aliases.keyword(word))
+                    .is_some_and(|keyword| words.contains(&keyword))
+        };
+        match self.split() {
+            Some((word, rest)) if matches(&word) => {
+                self.rest = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Not consumed input as it is, for the free-form text
+    pub fn rest(&self) -> &'a str {
+        self.rest.trim()
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (word, rest) = self.split()?;
+        self.rest = rest;
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        let mut tokens = Tokens::new("ADD Opt Unused Variable");
+        assert_eq!(tokens.next_keyword().as_deref(), Some("add"));
+        assert!(!tokens.next_if_any(&["optional"]));
+        assert_eq!(tokens.next_keyword().as_deref(), Some("opt"));
+        assert_eq!(tokens.rest(), "Unused Variable");
+    }
+
+    #[test]
+    fn quotes_and_backslashes_group_words() {
+        let words = Tokens::new(r#"mark 2 "never read" 'a "b"' a\ b \"x \\ "unclosed quote"#)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            [
+                "mark",
+                "2",
+                "never read",
+                "a \"b\"",
+                "a b",
+                "\"x",
+                "\\",
+                "unclosed quote"
+            ]
+        );
+        assert_eq!(Tokens::new("   ").next(), None);
+    }
+
+    #[test]
+    fn rest_is_kept_as_it_is() {
+        let mut tokens = Tokens::new("add  Don't use \"goto\"  ");
+        tokens.next();
+        assert_eq!(tokens.rest(), "Don't use \"goto\"");
+        // The apostrophe opens a quote lasting to the end
+        assert_eq!(tokens.peek().as_deref(), Some("Dont use \"goto\"  "));
+    }
+
+    #[test]
+    fn aliases_are_resolved_to_the_keywords() {
+        let aliases = CommandAliases::new(&BTreeMap::from([
+            ("Доб".to_string(), "ADD".to_string()),
+            ("опц".to_string(), "optional".to_string()),
+        ]));
+        assert_eq!(aliases.resolve("доб"), "add");
+        assert_eq!(aliases.resolve("Show"), "show");
+        let mut tokens = Tokens::new("ДОБ опц Лишняя переменная").with_aliases(&aliases);
+        assert_eq!(tokens.next_keyword().as_deref(), Some("add"));
+        assert!(tokens.next_if_any(&["optional", "o"]));
+        assert!(!tokens.next_if_any(&["optional"]));
+        assert_eq!(tokens.rest(), "Лишняя переменная");
+        // Without the aliases the word is only lowercased
+        assert_eq!(Tokens::new("Доб").next_keyword().as_deref(), Some("доб"));
+    }
+}
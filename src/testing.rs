@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::preparing::context::ProjectContext;
+
+/// Directory of a test, removed with everything in it when dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "assist_tool_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).expect("Can't create test directory");
+        Self(path)
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Project with the task `t` in the directory, `settings` are the fields added to its config.
+/// The task is switched to, so it's ready for the review.
+pub(crate) fn project(dir: &TempDir, settings: serde_json::Value) -> ProjectContext {
+    fs::create_dir_all(dir.join("tasks/t")).unwrap();
+    fs::write(
+        dir.join("tasks/t/main.cpp"),
+        "int main() {\n    int x;\n    return 0;\n}\n",
+    )
+    .unwrap();
+    let mut config = serde_json::json!({
+        "author_name": "Anton Kopanov",
+        "author_contacts": "@ak",
+        "tasks": [{
+            "name": "t",
+            "code_file_name": "main.cpp",
+            "show_method": {"File": {"file_name": dir.join("tasks/t/out.txt")}},
+            "notes": dir.join("notes/t.txt"),
+        }],
+    });
+    config
+        .as_object_mut()
+        .unwrap()
+        .extend(settings.as_object().unwrap().clone());
+    fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    let mut context = ProjectContext::load_state(
+        dir.join("config.json").display().to_string(),
+        dir.0.display().to_string(),
+        None,
+    )
+    .unwrap();
+    context.switch_to_task("t").unwrap();
+    context
+}
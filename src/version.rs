@@ -0,0 +1,68 @@
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use const_format::concatcp;
+use log::trace;
+
+use crate::messages::Message;
+use crate::reviewing::external::run_with_timeout;
+
+/// Version of the tool with the commit it was built from
+pub(crate) const VERSION: &str = concatcp!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("GIT_COMMIT_HASH"),
+    ")"
+);
+
+const REPOSITORY_URL: &str = "https://github.com/NotnaKO/assist_tool.git";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    let version = (
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    );
+    parts.next().is_none().then_some(version)
+}
+
+/// Compare the version of the tool with the latest release tag in the repository
+pub(crate) fn check_for_update() -> anyhow::Result<()> {
+    let output = run_with_timeout(
+        Command::new("git").args(["ls-remote", "--tags", REPOSITORY_URL]),
+        CHECK_TIMEOUT,
+    )?;
+    ensure!(
+        output.status.success(),
+        "{}",
+        Message::CantGetTags.format(&[
+            &REPOSITORY_URL,
+            &String::from_utf8_lossy(&output.stderr).trim()
+        ])
+    );
+    let current =
+        parse_version(env!("CARGO_PKG_VERSION")).context(Message::IncorrectCrateVersion.text())?;
+    let latest = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.rsplit('/').next())
+        .filter_map(parse_version)
+        .max();
+    trace!("Latest released version: {:?}", latest);
+
+    match latest {
+        Some(latest) if latest > current => eprintln!(
+            "{}",
+            Message::NewVersion.format(&[
+                &format!("{}.{}.{}", latest.0, latest.1, latest.2),
+                &VERSION,
+                &REPOSITORY_URL
+            ])
+        ),
+        Some(_) => eprintln!("{}", Message::LatestVersion.format(&[&VERSION])),
+        None => eprintln!("{}", Message::NoReleases.format(&[&REPOSITORY_URL])),
+    }
+    Ok(())
+}